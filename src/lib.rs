@@ -18,30 +18,38 @@ extern crate chrono;
 extern crate colored;
 extern crate clap;
 extern crate enum_set;
+extern crate regex;
+extern crate rustc_serialize;
 
 
 use std::path::{Path, PathBuf};
-use std::fs::{File};
-use std::collections::{HashMap, HashSet, BTreeMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write, BufRead, BufReader, Cursor};
+use std::collections::{HashMap, HashSet, BTreeMap, BTreeSet};
 use std::ascii::{AsciiExt};
 use std::env;
 use std::process;
 use std::marker::PhantomData;
 use std::mem;
+use std::cmp;
+use std::cmp::Ordering;
 use std::rc::Rc;
 use std::cell::RefCell;
 
 
 use colored::*;
 
-use clap::{Arg, App, SubCommand, AppSettings};
+use clap::{Arg, App, SubCommand, AppSettings, ArgMatches};
 
 // use chrono::*;
 use chrono::offset::local::Local;
+use chrono::offset::utc::UTC;
+use chrono::offset::TimeZone;
 use chrono::naive::datetime::NaiveDateTime;
 use chrono::naive::date::NaiveDate;
 use chrono::naive::time::NaiveTime;
 use chrono::duration::Duration;
+use chrono::Datelike;
 
 use chomp::{SimpleResult, Error, ParseResult};
 use chomp::primitives::{InputBuffer};
@@ -56,6 +64,10 @@ use chomp::ascii::{is_whitespace, decimal, digit};
 
 use enum_set::{EnumSet, CLike};
 
+use regex::Regex;
+
+use rustc_serialize::json::Json;
+
 
 #[allow(cyclomatic_complexity)]
 pub fn main() {
@@ -81,6 +93,111 @@ pub fn main() {
             .long("show-line-num-with-file-location")
             .required(false)
         )
+        .arg(
+            Arg::with_name("format")
+            .next_line_help(true)
+            .help("Output format of tasks.{n}\
+                Formats: text (default), csv, tsv, markdown, html{n}")
+            .long("format")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|format| {
+                if OutputFormat::from_string(&format).is_none() {
+                    return Err(String::from("invalid format"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("lenient")
+            .help("Skip malformed lines and task blocks with a warning instead of exiting.")
+            .long("lenient")
+            .required(false)
+        )
+        .arg(
+            Arg::with_name("error-format")
+            .next_line_help(true)
+            .help("Format of parse errors.{n}\
+                Formats: human (default), short{n}\
+                short emits file:line:col: message, as used by vim quickfix and Emacs compilation mode.{n}")
+            .long("error-format")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|error_format| {
+                if ErrorFormat::from_string(&error_format).is_none() {
+                    return Err(String::from("expected any of: human, short"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("group-by")
+            .next_line_help(true)
+            .help("Group displayed tasks by project, context, tag, or file instead of by \
+                overdue, inbox, deferred, and done.{n}\
+                Projects are drawn as a tree with task counts and time spent rolled up.{n}")
+            .long("group-by")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|group_by| {
+                if GroupBy::from_string(&group_by).is_none() {
+                    return Err(String::from("expected any of: project, context, tag, file"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("oneline")
+            .help("Display each task on a single line.")
+            .long("oneline")
+            .required(false)
+        )
+        .arg(
+            Arg::with_name("template")
+            .next_line_help(true)
+            .help("Display each task on a single line using a template.{n}\
+                Fields: {title}, {note}, {project}, {tags}, {contexts}, {status}, {priority}, {due}, {defer}, \
+                {created}, {done}, {time}, {flagged}, {file}, {line}, {id}, {chain}{n}\
+                Field formats: {due:rel} (relative time), {due:date}, {due:iso}, {due:human}, \
+                {priority:>3} (align right), {title:<20} (align left){n}\
+                Styles: {red}...{/}, {bold}...{/}, and so on. Use {{ and }} for literal braces.{n}\
+                Example: {priority:>3} {title} [{project}] due {due:rel}{n}")
+            .long("template")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .conflicts_with("oneline")
+            .validator(|template| {
+                if let Err(reason) = parse_template(&template) {
+                    return Err(reason);
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("columns")
+            .next_line_help(true)
+            .help("Comma separated list of columns of csv / tsv output.{n}\
+                Columns: title, note, project, tags, contexts, status, priority, due, defer, created, done, time, flagged, file{n}\
+                By default, all columns are output.{n}\
+                Example: title, project, due, priority, tags{n}")
+            .long("columns")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+        )
+        .arg(
+            Arg::with_name("list-delimiter")
+            .next_line_help(true)
+            .help("Delimiter joining tags, contexts, and project paths of csv / tsv output. Default: ;{n}")
+            .long("list-delimiter")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+        )
         .arg(
             Arg::with_name("hide-headers")
             .help("Hide headers. Shown by default.")
@@ -92,6 +209,8 @@ pub fn main() {
             Arg::with_name("due-within")
             .next_line_help(true)
             .help("Display tasks due within a time duration.{n}\
+                Fractions (1.5 h), compact forms (1h30m) and ISO 8601 durations (PT1H30M) are supported.{n}\
+                A month is 30 days and a year is 365 days.{n}\
                 Example: 2 days 4 hrs{n}")
             .short("w")
             .long("due-within")
@@ -99,6 +218,149 @@ pub fn main() {
             .takes_value(true)
             .multiple(false)
         )
+        .arg(
+            Arg::with_name("now")
+            .next_line_help(true)
+            .help("Evaluate tasks as of the given datetime instead of the current time.{n}\
+                Example: oct 21, 2016 5pm{n}")
+            .long("now")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|now| {
+                let now = now.trim();
+                if now.len() <= 0 {
+                    return Err(String::from("invalid datetime"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("created-after")
+            .next_line_help(true)
+            .help("Show only tasks created at or after the given datetime.{n}\
+                Tasks without a `created` datetime are hidden.{n}\
+                Example: oct 1, 2016{n}")
+            .long("created-after")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|date_time| {
+                let date_time = date_time.trim();
+                if date_time.len() <= 0 {
+                    return Err(String::from("invalid datetime"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("created-before")
+            .next_line_help(true)
+            .help("Show only tasks created before the given datetime.{n}\
+                Tasks without a `created` datetime are hidden.{n}\
+                Example: today{n}")
+            .long("created-before")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|date_time| {
+                let date_time = date_time.trim();
+                if date_time.len() <= 0 {
+                    return Err(String::from("invalid datetime"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("done-after")
+            .next_line_help(true)
+            .help("Show only tasks completed at or after the given datetime.{n}\
+                Tasks without a `done` datetime are hidden.{n}\
+                Example: jul 1, 2016{n}")
+            .long("done-after")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|date_time| {
+                let date_time = date_time.trim();
+                if date_time.len() <= 0 {
+                    return Err(String::from("invalid datetime"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("done-before")
+            .next_line_help(true)
+            .help("Show only tasks completed before the given datetime.{n}\
+                Tasks without a `done` datetime are hidden.{n}\
+                Example: oct 1, 2016{n}")
+            .long("done-before")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|date_time| {
+                let date_time = date_time.trim();
+                if date_time.len() <= 0 {
+                    return Err(String::from("invalid datetime"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("due-after")
+            .next_line_help(true)
+            .help("Show only tasks due at or after the given datetime.{n}\
+                Tasks without a `due` datetime are hidden.{n}\
+                Example: tomorrow{n}")
+            .long("due-after")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|date_time| {
+                let date_time = date_time.trim();
+                if date_time.len() <= 0 {
+                    return Err(String::from("invalid datetime"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("due-before")
+            .next_line_help(true)
+            .help("Show only tasks due before the given datetime.{n}\
+                Tasks without a `due` datetime are hidden.{n}\
+                Example: fri 5pm{n}")
+            .long("due-before")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|date_time| {
+                let date_time = date_time.trim();
+                if date_time.len() <= 0 {
+                    return Err(String::from("invalid datetime"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("deferred-until-before")
+            .next_line_help(true)
+            .help("Show only tasks deferred until before the given datetime.{n}\
+                Tasks without a `defer` datetime, or deferred forever, are hidden.{n}\
+                Example: mon{n}")
+            .long("deferred-until-before")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|date_time| {
+                let date_time = date_time.trim();
+                if date_time.len() <= 0 {
+                    return Err(String::from("invalid datetime"));
+                }
+                return Ok(());
+            })
+        )
         .arg(
             Arg::with_name("hide-by-default")
             .help("Hide tasks by default. Usage of flags / options are necessary to display tasks.")
@@ -141,6 +403,38 @@ pub fn main() {
             .long("show-project-tasks")
             .required(false)
         )
+        .arg(
+            Arg::with_name("include-untagged")
+            .help("Include tasks without tags. Used with --only-with-tag")
+            .long("include-untagged")
+            .required(false)
+        )
+        .arg(
+            Arg::with_name("include-no-context")
+            .help("Include tasks without contexts. Used with --only-with-context")
+            .long("include-no-context")
+            .required(false)
+        )
+        .arg(
+            Arg::with_name("include-no-project")
+            .help("Include tasks not belonging to a project. Used with --only-with-project")
+            .long("include-no-project")
+            .required(false)
+        )
+        .arg(
+            Arg::with_name("only-untagged")
+            .help("Show only tasks without tags.")
+            .long("only-untagged")
+            .required(false)
+            .conflicts_with("only-with-tag")
+        )
+        .arg(
+            Arg::with_name("only-no-context")
+            .help("Show only tasks without contexts.")
+            .long("only-no-context")
+            .required(false)
+            .conflicts_with("only-with-context")
+        )
         .arg(
             Arg::with_name("hide-overdue")
             .help("Hide overdue tasks.")
@@ -211,11 +505,38 @@ pub fn main() {
             .long("sort-overdue-by-priority")
             .required(false)
         )
+        .arg(
+            Arg::with_name("sort-by")
+            .next_line_help(true)
+            .help("Sort tasks of every section by a comma separated list of sort keys. \
+                Later keys break ties of earlier keys.{n}\
+                Sort keys: priority, due, defer, created, done, title, time, file{n}\
+                Prefix a sort key with - to reverse its order. \
+                Tasks missing the attribute are shown last.{n}\
+                To sort only a section, prefix the list with one of: overdue, inbox, deferred, done{n}\
+                Example: -due, title{n}\
+                Example: inbox: created{n}")
+            .long("sort-by")
+            .required(false)
+            .takes_value(true)
+            .multiple(true)
+            // sort keys may be reversed with a leading -
+            .allow_hyphen_values(true)
+            .number_of_values(1)
+            .validator(|sort_by| {
+                if let Err(reason) = parse_sort_by(&sort_by) {
+                    return Err(reason);
+                }
+                return Ok(());
+            })
+        )
         .arg(
             Arg::with_name("only-with-project")
             .next_line_help(true)
             .help("Show only tasks with given project path.{n}\
-                Example: path / to / project{n}")
+                A `*` segment matches any one project; `**` matches any number of projects.{n}\
+                Example: path / to / project{n}\
+                Example: clients / * / frontend{n}")
             .short("p")
             .long("only-with-project")
             .required(false)
@@ -234,6 +555,7 @@ pub fn main() {
             .next_line_help(true)
             .help("Show tasks with given project path.\
                 Used with --hide-by-default{n}\
+                A `*` segment matches any one project; `**` matches any number of projects.{n}\
                 Example: path / to / project{n}")
             .short("k")
             .long("show-with-project")
@@ -322,6 +644,126 @@ pub fn main() {
                 return Ok(());
             })
         )
+        .arg(
+            Arg::with_name("hide-with-tag")
+            .next_line_help(true)
+            .help("Hide tasks that have any given list of comma separated tags.{n}\
+                Example: chore, art, to watch{n}")
+            .long("hide-with-tag")
+            .required(false)
+            .takes_value(true)
+            .multiple(true)
+            .validator(|tag| {
+                let tag = tag.trim();
+                if tag.len() <= 0 {
+                    return Err(String::from("invalid tag"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("hide-with-context")
+            .next_line_help(true)
+            .help("Hide tasks that have any given list of comma separated contexts.{n}\
+                Example: phone, computer, internet connection, office{n}")
+            .long("hide-with-context")
+            .required(false)
+            .takes_value(true)
+            .multiple(true)
+            .validator(|context| {
+                let context = context.trim();
+                if context.len() <= 0 {
+                    return Err(String::from("invalid context"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("hide-with-project")
+            .next_line_help(true)
+            .help("Hide tasks with given project path.{n}\
+                A `*` segment matches any one project; `**` matches any number of projects.{n}\
+                Example: path / to / project{n}")
+            .long("hide-with-project")
+            .required(false)
+            .takes_value(true)
+            .multiple(true)
+            .validator(|path| {
+                let path = path.trim();
+                if path.len() <= 0 {
+                    return Err(String::from("invalid project path"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("only-in-file")
+            .next_line_help(true)
+            .help("Show only tasks from files matching the given glob pattern.{n}\
+                Patterns are matched against file paths relative to the working directory, and absolute file paths.{n}\
+                * matches within a directory; ** matches across directories.{n}\
+                Example: projects/*.gtd{n}")
+            .long("only-in-file")
+            .required(false)
+            .takes_value(true)
+            .multiple(true)
+            .validator(|pattern| {
+                let pattern = pattern.trim();
+                if pattern.len() <= 0 {
+                    return Err(String::from("invalid file pattern"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("hide-in-file")
+            .next_line_help(true)
+            .help("Hide tasks from files matching the given glob pattern.{n}\
+                Patterns are matched against file paths relative to the working directory, and absolute file paths.{n}\
+                * matches within a directory; ** matches across directories.{n}\
+                Example: archive/*.gtd{n}")
+            .long("hide-in-file")
+            .required(false)
+            .takes_value(true)
+            .multiple(true)
+            .validator(|pattern| {
+                let pattern = pattern.trim();
+                if pattern.len() <= 0 {
+                    return Err(String::from("invalid file pattern"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("grep")
+            .next_line_help(true)
+            .help("Show only tasks whose title or notes match the given regular expression.{n}\
+                Matches are highlighted.{n}\
+                Example: (buy|order) milk{n}")
+            .long("grep")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|pattern| {
+                if pattern.len() <= 0 {
+                    return Err(String::from("invalid regular expression"));
+                }
+                return Ok(());
+            })
+        )
+        .arg(
+            Arg::with_name("ignore-case")
+            .help("Match --grep case-insensitively.")
+            .long("ignore-case")
+            .required(false)
+            .requires("grep")
+        )
+        .arg(
+            Arg::with_name("grep-metadata")
+            .help("Also match --grep against project paths, tags, and contexts.")
+            .long("grep-metadata")
+            .required(false)
+        )
         .arg(
             Arg::with_name("show-priority")
             .next_line_help(true)
@@ -331,7 +773,7 @@ pub fn main() {
                 <priority> is a signed integer.{n}\
                 There may be whitespace between <operator> and <priority>.{n}\
                 Operators: <=, <, >=, >, =, =={n}\
-                You may combine filters with: and, &, &&, or, |, ||{n}\
+                You may combine filters with: and, &, &&, or, |, ||, not, !{n}\
                 You may wrap filter expressions in parentheses.{n}\
                 {n}\
                 Example: >= 42 (show tasks greater or equal to 42){n}")
@@ -348,6 +790,34 @@ pub fn main() {
                 return Ok(());
             })
         )
+        .arg(
+            Arg::with_name("where")
+            .next_line_help(true)
+            .use_delimiter(false)
+            .help("Filter tasks by a query.{n}\
+                Format of filter: <field> <operator> <value>{n}\
+                Fields: title, note, project, tag, context, status, priority, due, defer, created, done, time, flagged, file{n}\
+                Operators: =, ==, !=, <, <=, >, >=, ^= (starts with), ~ (contains), in{n}\
+                Text comparisons are case-insensitive. Quote values that have whitespace.{n}\
+                Dates may be relative: now, today, tomorrow, yesterday, or a weekday (e.g. fri).{n}\
+                Dates without a time of day are compared by calendar day (e.g. due = today).{n}\
+                Comparisons against a missing attribute are false; except for !=.{n}\
+                You may combine filters with: and, &, &&, or, |, ||, not, !{n}\
+                You may wrap filter expressions in parentheses.{n}\
+                {n}\
+                Example: tag = chore and (due < tomorrow or priority >= 5){n}")
+            .long("where")
+            .required(false)
+            .takes_value(true)
+            .multiple(false)
+            .validator(|filter| {
+                let filter = filter.trim();
+                if filter.len() <= 0 {
+                    return Err(String::from("invalid 'where' filter"));
+                }
+                return Ok(());
+            })
+        )
         .arg(
             Arg::with_name("path to gtdtxt file")
             .help("Path to gtdtxt file.")
@@ -369,19 +839,275 @@ pub fn main() {
         .subcommand(
             SubCommand::with_name("current")
                 .about("Display current task")
-        ).get_matches();
-
-    let path_to_file: String = cmd_matches.value_of("path to gtdtxt file")
-                                                .unwrap()
-                                                .trim()
-                                                .to_string();
-
-    let base_root = format!("{}", env::current_dir().unwrap().display());
-    let mut journal = GTD::new(base_root);
-
-    // priority range filter
-    if let Some(show_priority) = cmd_matches.value_of("show-priority") {
-
+        )
+        .subcommand(
+            SubCommand::with_name("next")
+                .about("Display a ranked short list of next actions")
+                .arg(
+                    Arg::with_name("count")
+                    .help("Number of next actions to display. Default: 5")
+                    .short("n")
+                    .long("count")
+                    .required(false)
+                    .takes_value(true)
+                    .validator(|count| {
+                        match count.trim().parse::<usize>() {
+                            Ok(count) if count > 0 => {
+                                return Ok(());
+                            },
+                            _ => {
+                                return Err(String::from("expected a positive number"));
+                            }
+                        }
+                    })
+                )
+                .arg(
+                    Arg::with_name("only-with-context")
+                    .help("Show only tasks that have any given list of comma separated contexts.")
+                    .short("c")
+                    .long("only-with-context")
+                    .required(false)
+                    .takes_value(true)
+                    .multiple(true)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("ics")
+                .about("Output tasks with due datetimes as an iCalendar (RFC 5545) calendar")
+                .arg(
+                    Arg::with_name("events")
+                    .help("Output tasks as events (VEVENT) instead of to-dos (VTODO).")
+                    .long("events")
+                    .required(false)
+                )
+                .arg(
+                    Arg::with_name("defer-as-start")
+                    .help("Use defer datetimes of tasks as start datetimes.")
+                    .long("defer-as-start")
+                    .required(false)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("view")
+                .about("Display tasks using a view declared in the gtdtxt file(s)")
+                .arg(
+                    Arg::with_name("name")
+                    .help("Name of the view.")
+                    .required(true)
+                    .index(1)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("views")
+                .about("List views declared in the gtdtxt file(s)")
+        )
+        .subcommand(
+            SubCommand::with_name("lsp")
+                .about("Run a language server for gtdtxt files over stdio")
+        )
+        .subcommand(
+            SubCommand::with_name("add")
+                .about("Append a new task block to the gtdtxt file")
+                .arg(
+                    Arg::with_name("title")
+                    .help("Title of the task.")
+                    .required(true)
+                    .index(1)
+                )
+                .arg(
+                    Arg::with_name("project")
+                    .help("Project path of the task. Example: personal/food")
+                    .short("p")
+                    .long("project")
+                    .required(false)
+                    .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("tag")
+                    .help("Tags of the task; may be comma separated.")
+                    .short("t")
+                    .long("tag")
+                    .required(false)
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                )
+                .arg(
+                    Arg::with_name("context")
+                    .help("Contexts of the task; may be comma separated.")
+                    .short("c")
+                    .long("context")
+                    .required(false)
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                )
+                .arg(
+                    Arg::with_name("due")
+                    .help("Due datetime of the task. Example: \"tomorrow 5pm\"")
+                    .long("due")
+                    .required(false)
+                    .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("defer")
+                    .help("Defer the task until the given datetime, or forever.")
+                    .long("defer")
+                    .required(false)
+                    .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("priority")
+                    .help("Priority of the task.")
+                    .long("priority")
+                    .required(false)
+                    .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("flag")
+                    .help("Flag the task.")
+                    .long("flag")
+                    .required(false)
+                )
+                .arg(
+                    Arg::with_name("note")
+                    .help("Notes of the task.")
+                    .long("note")
+                    .required(false)
+                    .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("inbox")
+                    .help("Append the task to this file instead of the gtdtxt file.")
+                    .long("inbox")
+                    .required(false)
+                    .takes_value(true)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("capture")
+                .about("Append tasks written in the quick-add capture syntax to the gtdtxt file")
+                .arg(
+                    Arg::with_name("lines")
+                    .help("Tasks to capture, one per argument. Read from stdin, one per line, if none are given.{n}\
+                        Example: \"call mom @phone +family/parents #weekly !5 due:fri defer:mon\"")
+                    .required(false)
+                    .multiple(true)
+                    .index(1)
+                )
+                .arg(
+                    Arg::with_name("inbox")
+                    .help("Append the tasks to this file instead of the gtdtxt file.")
+                    .long("inbox")
+                    .required(false)
+                    .takes_value(true)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("done")
+                .about("Mark a task as done by editing its task block in place")
+                .arg(
+                    Arg::with_name("selector")
+                    .help("Task id, file:line location of the task, or text to search for in task titles.")
+                    .required(true)
+                    .index(1)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("archive")
+                .about("Move done tasks out of the gtdtxt file(s) into an archive file")
+                .arg(
+                    Arg::with_name("archive file")
+                    .help("Path to the archive file. Example: completed.gtd")
+                    .required(true)
+                    .index(1)
+                )
+                .arg(
+                    Arg::with_name("older-than")
+                    .help("Archive only tasks done more than the given number of days ago.")
+                    .long("older-than")
+                    .required(false)
+                    .takes_value(true)
+                    .validator(|days| {
+                        match days.trim().parse::<u32>() {
+                            Ok(_) => {
+                                return Ok(());
+                            },
+                            _ => {
+                                return Err(String::from("expected a number of days"));
+                            }
+                        }
+                    })
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("chain")
+                .about("Add a chain datetime of now to a task")
+                .arg(
+                    Arg::with_name("selector")
+                    .help("Task id, file:line location of the task, or text to search for in task titles.")
+                    .required(true)
+                    .index(1)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("start")
+                .about("Start a timer for a task")
+                .arg(
+                    Arg::with_name("selector")
+                    .help("Task id, file:line location of the task, or text to search for in task titles.")
+                    .required(true)
+                    .index(1)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("stop")
+                .about("Stop the running timer, and add the time spent to its task")
+        ).get_matches();
+
+    let path_to_file: String = cmd_matches.value_of("path to gtdtxt file")
+                                                .unwrap()
+                                                .trim()
+                                                .to_string();
+
+    if cmd_matches.subcommand_matches("lsp").is_some() {
+        run_language_server(path_to_file);
+        return;
+    }
+
+    let base_root = format!("{}", env::current_dir().unwrap().display());
+    let mut journal = GTD::new(base_root);
+
+    if let Some(error_format) = cmd_matches.value_of("error-format") {
+        journal.error_format = ErrorFormat::from_string(error_format).unwrap();
+    }
+
+    journal.lenient = cmd_matches.is_present("lenient");
+
+    // clock
+    let evaluate_at_given: bool = cmd_matches.is_present("now");
+    if let Some(now) = cmd_matches.value_of("now") {
+
+        let now = now.trim();
+
+        match parse_only(|i| parse_datetime_option(i, false), now.as_bytes()) {
+            Ok(Ok(result)) => {
+                journal.now = result;
+            },
+            Ok(Err(reason)) => {
+                println!("Invalid value to option `--now`: {}", reason);
+                process::exit(1);
+            },
+            Err(_) => {
+                println!("Unable to parse value to option `--now`: {}", now);
+                process::exit(1);
+            }
+        }
+    }
+
+    // priority range filter
+    if let Some(show_priority) = cmd_matches.value_of("show-priority") {
+
         let show_priority = show_priority.trim();
 
         match parse_only(|i| parse_show_priority(i), show_priority.as_bytes()) {
@@ -396,6 +1122,76 @@ pub fn main() {
         }
     }
 
+    // query filter
+    if let Some(filter_where) = cmd_matches.value_of("where") {
+
+        let filter_where = filter_where.trim();
+        let now = journal.now;
+
+        match parse_only(|i| parse_where(i, now), filter_where.as_bytes()) {
+            Ok(Ok(result)) => {
+                journal.filter_where = Some(result);
+            },
+            Ok(Err(reason)) => {
+                println!("Invalid value to option `--where`: {}", reason);
+                process::exit(1);
+            },
+            Err(_) => {
+                println!("Unable to parse value to option `--where`: {}", filter_where);
+                process::exit(1);
+            }
+        }
+    }
+
+    // date range filters
+    for (option_name, field, inequality) in date_range_filters() {
+
+        if let Some(date_time) = cmd_matches.value_of(option_name) {
+
+            let date_time = date_time.trim();
+            let now = journal.now;
+
+            match parse_only(|i| parse_relative_datetime_option(i, false, now), date_time.as_bytes()) {
+                Ok(Ok(result)) => {
+                    journal.add_where_filter(WhereFilter(
+                        field,
+                        WhereOperator::Compare(inequality),
+                        vec![WhereValue::DateTime(result)]));
+                },
+                Ok(Err(reason)) => {
+                    println!("Invalid value to option `--{}`: {}", option_name, reason);
+                    process::exit(1);
+                },
+                Err(_) => {
+                    println!("Unable to parse value to option `--{}`: {}", option_name, date_time);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    // search
+    if let Some(pattern) = cmd_matches.value_of("grep") {
+
+        let pattern = if cmd_matches.is_present("ignore-case") {
+            format!("(?i){}", pattern)
+        } else {
+            pattern.to_owned()
+        };
+
+        match Regex::new(&pattern) {
+            Ok(regex) => {
+                journal.filter_grep = Some(regex);
+            },
+            Err(reason) => {
+                println!("Invalid regular expression to option `--grep`: {}", reason);
+                process::exit(1);
+            }
+        }
+    }
+
+    journal.grep_metadata = cmd_matches.is_present("grep-metadata");
+
     // due within filter
     if let Some(due_within) = cmd_matches.value_of("due-within") {
 
@@ -446,6 +1242,34 @@ pub fn main() {
         }
     }
 
+    if let Some(project_paths) = cmd_matches.values_of("hide-with-project") {
+        for project_path in project_paths {
+
+            match parse_only(|i| parse_string_lists(i, b'/'), project_path.as_bytes()) {
+                Ok(mut result) => {
+                    journal.add_project_blacklist(&mut result);
+                },
+                Err(_) => {
+                    println!("Unable to parse project path `--hide-with-project`: {}", project_path);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    // file filters
+    if let Some(patterns) = cmd_matches.values_of("only-in-file") {
+        for pattern in patterns {
+            journal.only_in_files.push(pattern.trim().to_owned());
+        }
+    }
+
+    if let Some(patterns) = cmd_matches.values_of("hide-in-file") {
+        for pattern in patterns {
+            journal.hide_in_files.push(pattern.trim().to_owned());
+        }
+    }
+
     // tag filters
     if let Some(tags) = cmd_matches.values_of("only-with-tag") {
 
@@ -491,6 +1315,27 @@ pub fn main() {
         }
     }
 
+    if let Some(tags) = cmd_matches.values_of("hide-with-tag") {
+
+        for tag in tags {
+
+            match parse_only(|i| parse_string_lists(i, b','), tag.as_bytes()) {
+                Ok(result) => {
+
+                    if result.len() > 0 {
+                        journal.filter_by_hide_tags = true;
+                    }
+
+                    journal.add_tag_hide_filters(result);
+                },
+                Err(_) => {
+                    println!("Unable to parse tags `--hide-with-tag`: {}", tag);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
     // context filters
     if let Some(contexts) = cmd_matches.values_of("only-with-context") {
 
@@ -536,6 +1381,27 @@ pub fn main() {
         }
     }
 
+    if let Some(contexts) = cmd_matches.values_of("hide-with-context") {
+
+        for context in contexts {
+
+            match parse_only(|i| parse_string_lists(i, b','), context.as_bytes()) {
+                Ok(result) => {
+
+                    if result.len() > 0 {
+                        journal.filter_by_hide_contexts = true;
+                    }
+
+                    journal.add_context_hide_filters(result);
+                },
+                Err(_) => {
+                    println!("Unable to parse contexts `--hide-with-context`: {}", context);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
     // flags
 
     let show_headers: bool = !cmd_matches.is_present("hide-headers");
@@ -558,9 +1424,120 @@ pub fn main() {
     journal.show_flagged = cmd_matches.is_present("show-flagged");
     journal.show_nonproject_tasks = cmd_matches.is_present("show-nonproject-tasks");
     journal.show_project_tasks = cmd_matches.is_present("show-project-tasks");
+    journal.include_untagged = cmd_matches.is_present("include-untagged");
+    journal.include_no_context = cmd_matches.is_present("include-no-context");
+    journal.include_no_project = cmd_matches.is_present("include-no-project");
+    journal.only_untagged = cmd_matches.is_present("only-untagged");
+    journal.only_no_context = cmd_matches.is_present("only-no-context");
+
 
+    if let Some(matches) = cmd_matches.subcommand_matches("add") {
+        add_task_command(&journal, &path_to_file, matches);
+        return;
+    }
+
+    if let Some(matches) = cmd_matches.subcommand_matches("capture") {
+        capture_command(&journal, &path_to_file, matches);
+        return;
+    }
 
     parse_file(None, path_to_file.clone(), &mut journal);
+
+    if let Some(matches) = cmd_matches.subcommand_matches("done") {
+        done_task_command(&journal, &path_to_file, matches);
+        return;
+    }
+
+    if let Some(matches) = cmd_matches.subcommand_matches("archive") {
+        archive_command(&journal, &path_to_file, matches);
+        return;
+    }
+
+    if let Some(matches) = cmd_matches.subcommand_matches("chain") {
+        chain_task_command(&journal, &path_to_file, matches);
+        return;
+    }
+
+    if let Some(matches) = cmd_matches.subcommand_matches("start") {
+        start_timer_command(&journal, &path_to_file, matches);
+        return;
+    }
+
+    if cmd_matches.subcommand_matches("stop").is_some() {
+        stop_timer_command(&journal, &path_to_file);
+        return;
+    }
+
+    let mut view_name: Option<String> = None;
+    if let Some(matches) = cmd_matches.subcommand_matches("view") {
+
+        let name = matches.value_of("name").unwrap().trim();
+
+        let view: View = match journal.get_view(name) {
+            Some(view) => view.as_ref().unwrap().clone(),
+            None => {
+                println!("Unable to find view: {}", name);
+
+                if journal.views.len() <= 0 {
+                    println!("No views are declared.");
+                } else {
+                    println!("Declared views:");
+                    for view in &journal.views {
+                        println!("{:>11} {}", view.as_ref().unwrap().name, view.location());
+                    }
+                }
+
+                process::exit(1);
+            }
+        };
+
+        journal.apply_view(&view);
+        view_name = Some(view.name);
+    }
+
+    // sort orders given by --sort-by take precedence over those of a view
+    if let Some(sort_by_values) = cmd_matches.values_of("sort-by") {
+
+        for sort_by in sort_by_values {
+
+            match parse_sort_by(sort_by) {
+                Ok(sort_by) => {
+                    journal.sort_by.push(sort_by);
+                },
+                Err(reason) => {
+                    println!("Unable to parse value to option `--sort-by`: {}", reason);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    if let Some(matches) = cmd_matches.subcommand_matches("next") {
+
+        if let Some(contexts) = matches.values_of("only-with-context") {
+
+            for context in contexts {
+
+                match parse_only(|i| parse_string_lists(i, b','), context.as_bytes()) {
+                    Ok(result) => {
+
+                        if result.len() > 0 {
+                            journal.filter_by_only_contexts = true;
+                        }
+
+                        journal.add_context_only_filters(result);
+                    },
+                    Err(_) => {
+                        println!("Unable to parse contexts `--only-with-context`: {}", context);
+                        process::exit(1);
+                    }
+                }
+            }
+        }
+    }
+
+    journal.categorize_tasks();
+    journal.sort_tasks();
     let journal: GTD = journal;
 
     if cmd_matches.is_present("validate") {
@@ -588,36 +1565,133 @@ pub fn main() {
 
         return;
 
-    } else if let Some(_matches) = cmd_matches.subcommand_matches("stats") {
+    } else if let Some(matches) = cmd_matches.subcommand_matches("next") {
 
-        println!("{}", "Statistics by file".bold().purple().underline());
-        println!("");
+        let count: usize = match matches.value_of("count") {
+            None => 5,
+            Some(count) => count.trim().parse::<usize>().unwrap()
+        };
 
-        if journal.file_stats.len() <= 0 {
-            println!("No files parsed.");
+        let next_actions = journal.next_actions();
+
+        if next_actions.len() <= 0 {
+            println!("No next actions found.");
             return;
         }
 
-        let mut print_line: bool = false;
+        println!("{}{}",
+            "Next actions".white().bold().underline(),
+            format!(" ({} of {})", cmp::min(count, next_actions.len()), next_actions.len()).white().bold().underline());
+        println!("");
 
-        // for (path, file_stats) in journal.file_stats {
-        for path in &journal.file_stats_stack {
+        for (rank, task_id) in next_actions.iter().take(count).enumerate() {
 
-            let file_stats = journal.file_stats.get(path).unwrap();
+            let task: &Task = journal.tasks.get(task_id).unwrap();
+            print_next_action(&journal, rank + 1, task);
+        }
 
-            if print_line {
-                println!("");
-            } else {
-                print_line = true;
-            }
+        return;
 
-            let path = match Path::new(&path).strip_prefix(&journal.base_root) {
-                Err(_) => {
-                    path.to_owned()
-                },
-                Ok(path) => {
-                    format!("./{}", path.display())
-                }
+    } else if let Some(matches) = cmd_matches.subcommand_matches("ics") {
+
+        let as_events: bool = matches.is_present("events");
+        let defer_as_start: bool = matches.is_present("defer-as-start");
+
+        let mut task_ids: Vec<u64> = Vec::new();
+        task_ids.extend(journal.overdue.iter().cloned());
+        task_ids.extend(journal.inbox.iter().cloned());
+        task_ids.extend(journal.deferred.iter().cloned());
+
+        if journal.show_done {
+            task_ids.extend(journal.done.iter().cloned());
+        }
+
+        print_ics(&journal, &task_ids, as_events, defer_as_start);
+
+        return;
+
+    } else if let Some(_matches) = cmd_matches.subcommand_matches("views") {
+
+        println!("{}", "Views".bold().purple().underline());
+        println!("");
+
+        if journal.views.len() <= 0 {
+            println!("No views are declared.");
+            return;
+        }
+
+        let mut print_line: bool = false;
+
+        for view in &journal.views {
+
+            if print_line {
+                println!("");
+            } else {
+                print_line = true;
+            }
+
+            let location = view.location();
+            let view = view.as_ref().unwrap();
+
+            println!("{:>11} {}",
+                "View:".bold().blue(),
+                view.name);
+
+            println!("{:>11} {}",
+                "Location:".bold().blue(),
+                location);
+
+            if let Some(ref filter_where) = view.filter_where {
+                println!("{:>11} {}",
+                    "Where:".bold().blue(),
+                    where_pretty_tree_art(filter_where));
+            }
+
+            if let Some(sections) = view.sections_string() {
+                println!("{:>11} {}",
+                    "Show:".bold().blue(),
+                    sections);
+            }
+
+            if let Some(sort) = view.sort_string() {
+                println!("{:>11} {}",
+                    "Sort:".bold().blue(),
+                    sort);
+            }
+        }
+
+        return;
+
+    } else if let Some(_matches) = cmd_matches.subcommand_matches("stats") {
+
+        println!("{}", "Statistics by file".bold().purple().underline());
+        println!("");
+
+        if journal.file_stats.len() <= 0 {
+            println!("No files parsed.");
+            return;
+        }
+
+        let mut print_line: bool = false;
+
+        // for (path, file_stats) in journal.file_stats {
+        for path in &journal.file_stats_stack {
+
+            let file_stats = journal.file_stats.get(path).unwrap();
+
+            if print_line {
+                println!("");
+            } else {
+                print_line = true;
+            }
+
+            let path = match Path::new(&path).strip_prefix(&journal.base_root) {
+                Err(_) => {
+                    path.to_owned()
+                },
+                Ok(path) => {
+                    format!("./{}", path.display())
+                }
             };
 
             println!("{:>11} {}",
@@ -698,10 +1772,102 @@ pub fn main() {
         return;
     }
 
+    // Export tasks
+
+    let group_by: Option<GroupBy> = cmd_matches.value_of("group-by").map(|group_by| {
+        GroupBy::from_string(group_by).unwrap()
+    });
+
+    let output_format: OutputFormat = match cmd_matches.value_of("format") {
+        None => OutputFormat::Text,
+        Some(format) => OutputFormat::from_string(format).unwrap()
+    };
+
+    match output_format {
+        OutputFormat::Text => {
+
+            let template: Option<&str> = if cmd_matches.is_present("oneline") {
+                Some(ONELINE_TEMPLATE)
+            } else {
+                cmd_matches.value_of("template")
+            };
+
+            if let Some(template) = template {
+
+                let template = match parse_template(template) {
+                    Ok(template) => template,
+                    Err(reason) => {
+                        println!("Unable to parse value to option `--template`: {}", reason);
+                        process::exit(1);
+                    }
+                };
+
+                print_compact_report(&journal, &template, group_by.as_ref(), show_headers, evaluate_at_given);
+                return;
+            }
+        },
+        OutputFormat::Markdown => {
+            print_markdown_report(&journal, evaluate_at_given);
+            return;
+        },
+        OutputFormat::Html => {
+            print_html_report(&journal, evaluate_at_given);
+            return;
+        },
+        OutputFormat::Csv | OutputFormat::Tsv => {
+
+            let columns: Vec<TaskField> = match cmd_matches.value_of("columns") {
+                None => default_export_columns(),
+                Some(columns) => {
+                    match parse_only(|i| parse_columns(i), columns.as_bytes()) {
+                        Ok(columns) => columns,
+                        Err(_) => {
+                            println!("Unable to parse value to option `--columns`: {}", columns);
+                            process::exit(1);
+                        }
+                    }
+                }
+            };
+
+            let list_delimiter: &str = cmd_matches.value_of("list-delimiter").unwrap_or(";");
+
+            let delimiter = match output_format {
+                OutputFormat::Tsv => '\t',
+                _ => ','
+            };
+
+            print_delimited_tasks(&journal, &displayed_task_ids(&journal), &columns, delimiter, list_delimiter);
+
+            return;
+        }
+    }
+
     // Display tasks
 
     let mut display_divider = false;
 
+    if let Some(ref view_name) = view_name {
+
+        println!("{:>11} {} {}",
+            "",
+            "Displaying view".bold().white(),
+            view_name.white().bold()
+        );
+
+        display_divider = true;
+    }
+
+    if evaluate_at_given {
+
+        println!("{:>11} {} {}",
+            "",
+            "Evaluating tasks as of".bold().white(),
+            format!("{}", journal.now.format("%B %-d, %Y %-l:%M %p")).white().bold()
+        );
+
+        display_divider = true;
+    }
+
     if journal.filter_priority.is_some() {
 
         let tree_art = priority_pretty_tree_art(journal.filter_priority.as_ref().unwrap());
@@ -715,6 +1881,38 @@ pub fn main() {
         display_divider = true;
     }
 
+    if journal.filter_where.is_some() {
+
+        let tree_art = where_pretty_tree_art(journal.filter_where.as_ref().unwrap());
+
+        println!("{:>11} {} {}",
+            "",
+            "Filtering tasks where".bold().white(),
+            tree_art
+        );
+
+        display_divider = true;
+    }
+
+    if journal.filter_grep.is_some() {
+
+        let regex = journal.filter_grep.as_ref().unwrap();
+
+        let searched = if journal.grep_metadata {
+            "Searching titles, notes, projects, tags, and contexts for"
+        } else {
+            "Searching titles and notes for"
+        };
+
+        println!("{:>11} {} /{}/",
+            "",
+            searched.bold().white(),
+            regex
+        );
+
+        display_divider = true;
+    }
+
     if journal.due_within.num_seconds() > 0 {
 
         println!("{:>11} {} {}",
@@ -757,13 +1955,8 @@ pub fn main() {
 
 
     // display tasks that are overdue
-    let mut header_display: bool = show_headers;
-    num_overdue = count_tasks(&journal.overdue);
-    for bucket in journal.overdue.values() {
-
-        if bucket.len() <= 0 {
-            continue;
-        }
+    num_overdue = journal.overdue.len() as u64;
+    if num_overdue > 0 && group_by.is_none() {
 
         if !journal.hide_overdue {
 
@@ -771,15 +1964,14 @@ pub fn main() {
                 println!("");
             }
 
-            if header_display {
-                header_display = false;
+            if show_headers {
                 println!("{}{}",
                     "Overdue".white().bold().underline(),
                     format!(" ({})", num_overdue).white().bold().underline());
                 println!("");
             }
 
-            num_displayed += print_vector_of_tasks(&journal, bucket);
+            num_displayed += print_vector_of_tasks(&journal, &journal.overdue);
 
             if !print_line && num_displayed > 0 {
                 print_line = true;
@@ -787,29 +1979,23 @@ pub fn main() {
         }
     }
 
-    // display inbox ordered by priority.
+    // display inbox (ordered by priority by default).
     // incubated tasks are not included
-    let mut header_display: bool = show_headers;
-    num_inbox = count_tasks(&journal.inbox);
-    for inbox in journal.inbox.values() {
-
-        if inbox.len() <= 0 {
-            continue;
-        }
+    num_inbox = journal.inbox.len() as u64;
+    if num_inbox > 0 && !journal.hide_inbox && group_by.is_none() {
 
         if print_line {
             println!("");
         }
 
-        if header_display {
-            header_display = false;
+        if show_headers {
             println!("{}{}",
                 "Inbox".white().bold().underline(),
                 format!(" ({})", num_inbox).white().bold().underline());
             println!("");
         }
 
-        num_displayed += print_vector_of_tasks(&journal, inbox);
+        num_displayed += print_vector_of_tasks(&journal, &journal.inbox);
 
         if !print_line && num_displayed > 0 {
             print_line = true;
@@ -817,14 +2003,9 @@ pub fn main() {
 
     }
 
-    // display deferred tasks ordered by priority
-    let mut header_display: bool = show_headers;
-    num_deferred = count_tasks(&journal.deferred);
-    for deferred in journal.deferred.values() {
-
-        if deferred.len() <= 0 {
-            continue;
-        }
+    // display deferred tasks (ordered by priority by default)
+    num_deferred = journal.deferred.len() as u64;
+    if num_deferred > 0 && group_by.is_none() {
 
         if journal.show_deferred || journal.hide_tasks_by_default {
 
@@ -832,15 +2013,14 @@ pub fn main() {
                 println!("");
             }
 
-            if header_display {
-                header_display = false;
+            if show_headers {
                 println!("{}{}",
                     "Deferred".white().bold().underline(),
                     format!(" ({})", num_deferred).white().bold().underline());
                 println!("");
             }
 
-            num_displayed += print_vector_of_tasks(&journal, deferred);
+            num_displayed += print_vector_of_tasks(&journal, &journal.deferred);
 
             if !print_line && num_displayed > 0 {
                 print_line = true;
@@ -852,13 +2032,8 @@ pub fn main() {
 
 
     // display completed tasks
-    let mut header_display: bool = show_headers;
-    num_done = count_tasks(&journal.done);
-    for done in journal.done.values() {
-
-        if done.len() <= 0 {
-            continue;
-        }
+    num_done = journal.done.len() as u64;
+    if num_done > 0 && group_by.is_none() {
 
         if journal.show_done || journal.hide_tasks_by_default {
 
@@ -866,15 +2041,14 @@ pub fn main() {
                 println!("");
             }
 
-            if header_display {
-                header_display = false;
+            if show_headers {
                 println!("{}{}",
                     "Done".white().bold().underline(),
                     format!(" ({})", num_done).white().bold().underline());
                 println!("");
             }
 
-            num_displayed += print_vector_of_tasks(&journal, done);
+            num_displayed += print_vector_of_tasks(&journal, &journal.done);
 
             if !print_line && num_displayed > 0 {
                 print_line = true;
@@ -883,6 +2057,11 @@ pub fn main() {
         }
     }
 
+    // display tasks grouped by project, context, tag, or file
+    if let Some(ref group_by) = group_by {
+        num_displayed += print_grouped_tasks(&journal, group_by, show_headers);
+    }
+
     if num_displayed > 0 {
         println!("");
     }
@@ -971,4127 +2150,11516 @@ pub fn main() {
         format!("{}", num_displayed).bold().purple()
     );
 
+    if journal.lenient {
+        println!("{:>20} {}",
+            "Parse warnings".purple(),
+            format!("{}", journal.num_of_warnings).bold().purple()
+        );
+    }
+
+    let executed_at_label = if evaluate_at_given {
+        "Evaluated at"
+    } else {
+        "Executed at"
+    };
+
     println!("{:>20} {}",
-        "Executed at".purple(),
-        format!("{}", Local::now().naive_local().format("%B %-d, %Y %-l:%M:%S %p")).purple()
+        executed_at_label.purple(),
+        format!("{}", journal.now.format("%B %-d, %Y %-l:%M:%S %p")).purple()
     );
 
 }
 
-/* printers */
+/* exporters */
 
-fn print_vector_of_tasks(journal: &GTD, inbox: &[u64]) -> u64 {
+#[derive(Debug, Clone, PartialEq)]
+enum OutputFormat {
+    Text,
+    Csv,
+    Tsv,
+    Markdown,
+    Html
+}
 
-    let mut print_line: bool = false;
-    let mut num_displayed = 0;
+impl OutputFormat {
+    fn from_string(format: &str) -> Option<OutputFormat> {
+        match format.trim().to_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "csv" => Some(OutputFormat::Csv),
+            "tsv" => Some(OutputFormat::Tsv),
+            "markdown" | "md" => Some(OutputFormat::Markdown),
+            "html" => Some(OutputFormat::Html),
+            _ => None
+        }
+    }
+}
 
-    for task_id in inbox {
+// tasks of the sections that are displayed, in order of display
+fn displayed_task_ids(journal: &GTD) -> Vec<u64> {
 
-        if print_line {
-            println!("");
-        }
+    let mut task_ids: Vec<u64> = Vec::new();
 
-        let task: &Task = journal.tasks.get(task_id).unwrap();
+    if !journal.hide_overdue {
+        task_ids.extend(journal.overdue.iter().cloned());
+    }
 
-        print_task(journal, task);
-        num_displayed += 1;
+    if !journal.hide_inbox {
+        task_ids.extend(journal.inbox.iter().cloned());
+    }
 
-        if !print_line {
-            print_line = true;
-        }
+    if journal.show_deferred || journal.hide_tasks_by_default {
+        task_ids.extend(journal.deferred.iter().cloned());
+    }
 
+    if journal.show_done || journal.hide_tasks_by_default {
+        task_ids.extend(journal.done.iter().cloned());
     }
 
-    num_displayed
+    return task_ids;
 }
 
-fn print_task(journal: &GTD, task: &Task) {
-    _print_task(journal, task, true);
+fn default_export_columns() -> Vec<TaskField> {
+    vec![
+        TaskField::Title,
+        TaskField::Note,
+        TaskField::Project,
+        TaskField::Tag,
+        TaskField::Context,
+        TaskField::Status,
+        TaskField::Priority,
+        TaskField::Due,
+        TaskField::Defer,
+        TaskField::Created,
+        TaskField::Done,
+        TaskField::Time,
+        TaskField::Flagged,
+        TaskField::File
+    ]
 }
 
-// this function outputs a given task with the option of validating the task title requirement
-#[allow(cyclomatic_complexity)]
-fn _print_task(journal: &GTD, task: &Task, require_title: bool) {
-
-    if task.current {
-        println!("{:>11} ",
-            "CURRENT".bold().purple().italic()
-        );
+fn export_column_name(field: &TaskField) -> &'static str {
+    match *field {
+        TaskField::Tag => "tags",
+        TaskField::Context => "contexts",
+        _ => field.string()
     }
+}
 
-    if task.flag && !journal.show_only_flagged {
-            println!("{:>11} ",
-                "Flagged".bold().yellow()
-            );
+fn iso8601_datetime(datetime: &NaiveDateTime) -> String {
+    format!("{}", datetime.format("%Y-%m-%dT%H:%M:%S"))
+}
+
+fn format_iso8601_duration(seconds: u64) -> String {
+
+    if seconds <= 0 {
+        return "PT0S".to_owned();
     }
 
-    match task.title {
-        None => {
+    let days = seconds / 86400;
+    let hours = (seconds % 86400) / 3600;
+    let minutes = (seconds % 3600) / 60;
+    let seconds = seconds % 60;
 
-            if require_title {
-                println!("Missing task title (i.e. `task: <title>`) in task block found {}",
-                    task.debug_range_string()
-                );
-                println!("Captured:");
-                _print_task(journal, task, false);
-                process::exit(1);
-            }
+    let mut duration = "P".to_owned();
 
-        },
+    if days > 0 {
+        duration.push_str(&format!("{}D", days));
+    }
 
-        Some(ref title) => {
-            println!("{:>11} {}", "Task:".blue().bold(), title);
-        }
+    if hours > 0 || minutes > 0 || seconds > 0 {
+        duration.push('T');
     }
 
-    match task.status {
-        None => {},
-        Some(ref status) => {
-            let status_string = status.string();
-            println!("{:>11} {}", "Status:".bold().blue(), status_string);
-        }
+    if hours > 0 {
+        duration.push_str(&format!("{}H", hours));
     }
 
-    match task.created_at {
-        None => {},
-        Some(ref created_at) => {
+    if minutes > 0 {
+        duration.push_str(&format!("{}M", minutes));
+    }
 
-            let rel_time = relative_time(created_at.timestamp(), Local::now().naive_local().timestamp());
+    if seconds > 0 {
+        duration.push_str(&format!("{}S", seconds));
+    }
 
-            let rel_time = match rel_time {
-                RelativeTime::Now(_, rel_time) => {
-                    format!("({})", rel_time)
-                },
-                RelativeTime::Past(_, rel_time) => {
-                    format!("({})", rel_time)
-                },
-                RelativeTime::Future(_, rel_time) => {
-                    format!("({})", rel_time)
-                }
-            };
+    return duration;
+}
 
-            println!("{:>11} {} {}",
-                "Added at:".bold().blue(),
-                created_at.format("%B %-d, %Y %-l:%M %p"),
-                rel_time
-            );
+// path of the task's file as displayed; relative to the current directory if possible
+fn task_file_location(journal: &GTD, task: &Task) -> String {
+
+    let path = match task.source_file {
+        None => unsafe { debug_unreachable!() },
+        Some(ref path) => path
+    };
+
+    let path = match Path::new(path).strip_prefix(&journal.base_root) {
+        Err(_) => {
+            path.to_owned()
+        },
+        Ok(path) => {
+            format!("./{}", path.display())
         }
-    }
+    };
 
-    match task.done_at {
-        None => {},
-        Some(ref done_at) => {
+    if journal.show_line_num_with_file_location {
+        return format!("{}:{}", path, task.task_block_range_start);
+    }
 
-            let rel_time = relative_time(done_at.timestamp(), Local::now().naive_local().timestamp());
+    return path;
+}
 
-            let rel_time = match rel_time {
-                RelativeTime::Now(_, rel_time) => {
-                    format!("({})", rel_time)
-                },
-                RelativeTime::Past(_, rel_time) => {
-                    format!("({})", rel_time)
-                },
-                RelativeTime::Future(_, rel_time) => {
-                    format!("({})", rel_time)
-                }
-            };
+fn task_field_string(journal: &GTD, task: &Task, field: &TaskField, list_delimiter: &str) -> String {
+    match *field {
+        TaskField::Title => task.title.clone().unwrap_or_else(String::new),
+        TaskField::Note => task.note.as_ref().map(|x| note_text(x)).unwrap_or_else(String::new),
+        TaskField::Project => {
+            task.project.as_ref().map(|x| x.join(list_delimiter)).unwrap_or_else(String::new)
+        },
+        TaskField::Tag => {
+            task.tags.as_ref().map(|x| x.join(list_delimiter)).unwrap_or_else(String::new)
+        },
+        TaskField::Context => {
+            task.contexts.as_ref().map(|x| x.join(list_delimiter)).unwrap_or_else(String::new)
+        },
+        TaskField::Status => {
+            task.status.as_ref().map(|x| x.string()).unwrap_or_else(String::new)
+        },
+        TaskField::Priority => format!("{}", task.priority),
+        TaskField::Due => {
+            task.due_at.as_ref().map(iso8601_datetime).unwrap_or_else(String::new)
+        },
+        TaskField::Defer => {
+            match task.defer {
+                None => String::new(),
+                Some(Defer::Forever) => "forever".to_owned(),
+                Some(Defer::Until(ref defer_till)) => iso8601_datetime(defer_till)
+            }
+        },
+        TaskField::Created => {
+            task.created_at.as_ref().map(iso8601_datetime).unwrap_or_else(String::new)
+        },
+        TaskField::Done => {
+            task.done_at.as_ref().map(iso8601_datetime).unwrap_or_else(String::new)
+        },
+        TaskField::Time => format_iso8601_duration(task.time),
+        TaskField::Flagged => format!("{}", task.flag),
+        TaskField::File => task_file_location(journal, task)
+    }
+}
 
-            println!("{:>11} {} {}",
-                "Done at:".bold().blue(),
-                done_at.format("%B %-d, %Y %-l:%M %p"),
-                rel_time
-            );
-        }
+// note without the padding the parser adds to its continuation lines
+fn note_text(note: &str) -> String {
+    note.lines().map(|line| line.trim()).collect::<Vec<&str>>().join("\n").trim().to_owned()
+}
+
+// quote fields containing the delimiter, quotes, or line breaks (see RFC 4180)
+fn quote_delimited_field(field: &str, delimiter: char) -> String {
+
+    if !field.contains(delimiter) && !field.contains('"') && !field.contains('\n') && !field.contains('\r') {
+        return field.to_owned();
     }
 
-    match task.defer {
-        None => {},
-        Some(ref defer) => {
+    return format!("\"{}\"", field.replace("\"", "\"\""));
+}
 
-            match *defer {
-                Defer::Forever => {
-                    println!("{:>11} {}",
-                        "Defer till:".bold().blue(),
-                        "Forever".bold().green()
-                    );
-                },
-                Defer::Until(defer_till) => {
+fn print_delimited_tasks(journal: &GTD, task_ids: &[u64], columns: &[TaskField], delimiter: char,
+    list_delimiter: &str) {
 
-                    let rel_time = relative_time(defer_till.timestamp(), Local::now().naive_local().timestamp());
+    let delimiter_string = delimiter.to_string();
 
-                    let rel_time = match rel_time {
-                        RelativeTime::Now(_, rel_time) => {
-                            let rel_time = format!("({})", rel_time);
-                            rel_time.red()
-                        },
-                        RelativeTime::Past(_, rel_time) => {
-                            let rel_time = format!("({})", rel_time);
-                            rel_time.bold().red()
-                        },
-                        RelativeTime::Future(_, rel_time) => {
-                            let rel_time = format!("({})", rel_time);
-                            rel_time.bold().green()
-                        }
-                    };
+    let header: Vec<String> = columns.iter()
+        .map(|column| quote_delimited_field(export_column_name(column), delimiter))
+        .collect();
 
-                    println!("{:>11} {} {}",
-                        "Defer till:".bold().blue(),
-                        defer_till.format("%B %-d, %Y %-l:%M %p"),
-                        rel_time
-                    );
-                }
-            }
+    println!("{}", header.join(&delimiter_string));
+
+    for task_id in task_ids {
 
+        let task: &Task = journal.tasks.get(task_id).unwrap();
+
+        let row: Vec<String> = columns.iter()
+            .map(|column| {
+                let field = task_field_string(journal, task, column, list_delimiter);
+                quote_delimited_field(&field, delimiter)
+            })
+            .collect();
+
+        println!("{}", row.join(&delimiter_string));
+    }
+}
+
+fn parse_columns(input: Input<u8>) -> U8Result<Vec<TaskField>> {
+    parse!{input;
+        skip_many(space_or_tab);
+        let columns = parse_list(parse_list_status_delimeter, parse_columns_reducer);
+        skip_many(space_or_tab);
+        eof();
+        ret columns
+    }
+}
 
+fn parse_columns_reducer(input: Input<u8>, accumulator: Rc<RefCell<Vec<TaskField>>>) -> U8Result<()> {
+    parse!{input;
+        let column = parse_task_field();
+        ret {
+            accumulator.borrow_mut().push(column);
+            ()
         }
     }
+}
 
-    match task.due_at {
-        None => {},
-        Some(ref due_at) => {
-            let rel_time = relative_time(due_at.timestamp(), Local::now().naive_local().timestamp());
+/* reports */
 
-            let rel_time = match rel_time {
-                RelativeTime::Now(_, rel_time) => {
-                    let rel_time = format!("({})", rel_time);
-                    rel_time.red()
-                },
-                RelativeTime::Past(_, rel_time) => {
-                    let rel_time = format!("({})", rel_time);
-                    rel_time.bold().red()
-                },
-                RelativeTime::Future(_, rel_time) => {
-                    let rel_time = format!("({})", rel_time);
-                    rel_time.bold().green()
-                }
-            };
+// sections that are displayed, and their tasks in order of display
+fn report_sections(journal: &GTD) -> Vec<(&'static str, &[u64])> {
 
-            println!("{:>11} {} {}",
-                "Due at:".bold().blue(),
-                due_at.format("%B %-d, %Y %-l:%M %p"),
-                rel_time
-            );
-        }
+    let mut sections: Vec<(&'static str, &[u64])> = Vec::new();
+
+    if !journal.hide_overdue && journal.overdue.len() > 0 {
+        sections.push(("Overdue", &journal.overdue[..]));
     }
 
-    match task.source_file {
-        None => unsafe { debug_unreachable!() },
-        Some(ref path) => {
+    if !journal.hide_inbox && journal.inbox.len() > 0 {
+        sections.push(("Inbox", &journal.inbox[..]));
+    }
 
-            let path = match Path::new(path).strip_prefix(&journal.base_root) {
-                Err(_) => {
-                    // TODO: remove
-                    // format!("{}", path)
-                    path.to_owned()
-                },
-                Ok(path) => {
-                    format!("./{}", path.display())
-                }
-            };
+    if (journal.show_deferred || journal.hide_tasks_by_default) && journal.deferred.len() > 0 {
+        sections.push(("Deferred", &journal.deferred[..]));
+    }
 
-            if journal.show_line_num_with_file_location {
-                println!("{:>11} {}:{}",
-                    "File:".bold().blue(),
-                    path,
-                    task.task_block_range_start
-                );
-            } else {
-                println!("{:>11} {}",
-                    "File:".bold().blue(),
-                    path
-                );
-            }
+    if (journal.show_done || journal.hide_tasks_by_default) && journal.done.len() > 0 {
+        sections.push(("Done", &journal.done[..]));
+    }
+
+    return sections;
+}
+
+// group tasks by project path in order of first appearance
+fn group_by_project(journal: &GTD, task_ids: &[u64]) -> Vec<(String, Vec<u64>)> {
 
+    let mut groups: Vec<(String, Vec<u64>)> = Vec::new();
+
+    for task_id in task_ids {
+
+        let task: &Task = journal.tasks.get(task_id).unwrap();
+
+        let project = match task.project {
+            None => "No project".to_owned(),
+            Some(ref project_path) => project_path.join(" / ")
+        };
+
+        match groups.iter().position(|&(ref name, _)| *name == project) {
+            None => {
+                groups.push((project, vec![*task_id]));
+            },
+            Some(index) => {
+                groups[index].1.push(*task_id);
+            }
         }
+    }
+
+    return groups;
+}
+
+fn report_datetime(journal: &GTD, datetime: &NaiveDateTime) -> String {
+
+    let rel_time = match relative_time(datetime.timestamp(), journal.now.timestamp()) {
+        RelativeTime::Now(_, rel_time) |
+        RelativeTime::Past(_, rel_time) |
+        RelativeTime::Future(_, rel_time) => rel_time
     };
 
-    if task.task_block_range_start != task.task_block_range_end {
-        println!("{:>11} Lines {} to {}",
-            "Located:".bold().blue(),
-            task.task_block_range_start,
-            task.task_block_range_end
-        );
-    } else {
-        println!("{:>11} Line {}",
-            "Located:".bold().blue(),
-            task.task_block_range_start
-        );
+    return format!("{} ({})", datetime.format("%B %-d, %Y %-l:%M %p"), rel_time);
+}
+
+// attributes of a task as shown by _print_task; the project is a heading of reports
+fn report_task_attributes(journal: &GTD, task: &Task) -> Vec<(&'static str, String)> {
+
+    let mut attributes: Vec<(&'static str, String)> = Vec::new();
+
+    if task.current {
+        attributes.push(("Current", "yes".to_owned()));
     }
 
-    match task.tags {
-        None => {},
-        Some(ref tags) => {
-            println!("{:>11} {}",
-                "Tags:".bold().blue(),
-                tags.join(", ")
-            );
-        }
+    if task.flag {
+        attributes.push(("Flagged", "yes".to_owned()));
     }
 
-    match task.contexts {
-        None => {},
-        Some(ref contexts) => {
-            println!("{:>11} {}",
-                "Contexts:".bold().blue(),
-                contexts.join(", ")
-            );
-        }
+    if let Some(ref status) = task.status {
+        attributes.push(("Status", status.string()));
     }
 
-    match task.project {
+    if let Some(ref created_at) = task.created_at {
+        attributes.push(("Added at", report_datetime(journal, created_at)));
+    }
+
+    if let Some(ref done_at) = task.done_at {
+        attributes.push(("Done at", report_datetime(journal, done_at)));
+    }
+
+    match task.defer {
         None => {},
-        Some(ref project_path) => {
-            println!("{:>11} {}",
-                "Project:".bold().blue(),
-                project_path.join(" / ")
-            );
+        Some(Defer::Forever) => {
+            attributes.push(("Defer till", "Forever".to_owned()));
+        },
+        Some(Defer::Until(ref defer_till)) => {
+            attributes.push(("Defer till", report_datetime(journal, defer_till)));
         }
     }
 
-    if task.time > 0 {
-        println!("{:>11} {}",
-            "Time spent:".bold().blue(),
-            Timerange::new(task.time).print(2)
-        );
-
+    if let Some(ref due_at) = task.due_at {
+        attributes.push(("Due at", report_datetime(journal, due_at)));
     }
 
-    if task.has_chain() {
-        let chain_at: NaiveDateTime = task.get_chain();
+    attributes.push(("File", task_file_location(journal, task)));
 
-        let rel_time = relative_time(chain_at.timestamp(), Local::now().naive_local().timestamp());
+    if let Some(ref tags) = task.tags {
+        attributes.push(("Tags", tags.join(", ")));
+    }
 
-        let rel_time = match rel_time {
-            RelativeTime::Now(_, rel_time) => {
-                let rel_time = format!("({})", rel_time);
-                rel_time.red()
-            },
-            RelativeTime::Past(_, rel_time) => {
-                let rel_time = format!("({})", rel_time);
-                rel_time.bold().red()
-            },
-            RelativeTime::Future(_, rel_time) => {
-                let rel_time = format!("({})", rel_time);
-                rel_time.bold().green()
-            }
-        };
+    if let Some(ref contexts) = task.contexts {
+        attributes.push(("Contexts", contexts.join(", ")));
+    }
 
-        println!("{:>11} {} {}",
-            "Last chain:".bold().blue(),
-            chain_at.format("%B %-d, %Y %-l:%M %p"),
-            rel_time
-        );
+    if task.time > 0 {
+        attributes.push(("Time spent", Timerange::new(task.time).print(2)));
+    }
+
+    if task.has_chain() {
+        attributes.push(("Last chain", report_datetime(journal, &task.get_chain())));
     }
 
     if task.priority != 0 {
-        println!("{:>11} {}", "Priority:".bold().blue(), task.priority);
+        attributes.push(("Priority", format!("{}", task.priority)));
     }
 
-    match task.note {
-        None => {},
-        Some(ref note) => {
+    return attributes;
+}
 
-            let note = if journal.hide_notes {
-                format!("{}", "(hidden)".red())
-            } else {
-                note.clone()
-            };
+// paragraphs of a note are separated by blank lines
+fn note_paragraphs(note: &str) -> Vec<String> {
 
-            println!("{:>11} {}",
-                "Notes:".bold().blue(),
-                note
-            );
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+
+    for line in note.lines() {
+
+        let line = line.trim();
+
+        if line.len() <= 0 {
+            if paragraph.len() > 0 {
+                paragraphs.push(paragraph.join("\n"));
+                paragraph.clear();
+            }
+            continue;
         }
+
+        paragraph.push(line);
+    }
+
+    if paragraph.len() > 0 {
+        paragraphs.push(paragraph.join("\n"));
     }
 
+    return paragraphs;
 }
 
-/* data structures */
+// number of tasks completed for each of the past 7 days
+fn report_pulse(journal: &GTD) -> Vec<(String, usize)> {
 
-// index project path filters
-#[derive(Debug)]
-enum NodeType {
-    Node(Tree),
-    Leaf
-}
-type Tree = HashMap<String, NodeType>;
+    let mut pulse: Vec<(String, usize)> = Vec::new();
 
-// types
-type ProjectPath = Vec<String>;
-type Contexts = Vec<String>;
-type Tags = Vec<String>;
-type Priority = i64;
-type TimeLength = u64;
+    for days_ago in 0..8 {
 
-#[repr(u32)]
-#[derive(Debug, Clone, Eq, PartialEq)]
-enum Status {
-    Done,
-    Incubate,
-    NotDone
-}
+        let items_num = match journal.pulse.get(&days_ago) {
+            None => 0,
+            Some(bucket) => bucket.len()
+        };
 
-impl Status {
-    fn string(&self) -> String {
-        match *self {
-            Status::Done => "Done".to_owned(),
-            Status::Incubate => "Incubate".to_owned(),
-            Status::NotDone => "Not Done".to_owned()
-        }
+        pulse.push((format!("{} days ago", days_ago), items_num));
     }
+
+    return pulse;
 }
 
-impl CLike for Status {
-    fn to_u32(&self) -> u32 {
-        let foo: Self = self.clone();
-        foo as u32
-    }
+fn report_summary(journal: &GTD, evaluate_at_given: bool) -> Vec<(&'static str, String)> {
 
-    unsafe fn from_u32(v: u32) -> Status {
-        mem::transmute(v)
-    }
-}
+    let num_displayed: usize = report_sections(journal).iter()
+        .map(|&(_, task_ids)| task_ids.len())
+        .sum();
 
-#[derive(Debug)]
-struct Task {
+    let executed_at_label = if evaluate_at_given {
+        "Evaluated at"
+    } else {
+        "Executed at"
+    };
 
-    /* debug*/
-    task_block_range_start: u64,
-    task_block_range_end: u64,
+    let mut summary = vec![
+        ("Tasks overdue", format!("{}", journal.overdue.len())),
+        ("Tasks inbox", format!("{}", journal.inbox.len())),
+        ("Tasks deferred", format!("{}", journal.deferred.len())),
+        ("Tasks complete", format!("{}", journal.done.len())),
+        ("Tasks found", format!("{}", journal.tasks.len())),
+        ("Tasks not displayed", format!("{}", journal.tasks.len() - num_displayed)),
+        ("Tasks displayed", format!("{}", num_displayed))
+    ];
 
-    /* props */
-    current: bool,
-    title: Option<String>, // required
+    if journal.lenient {
+        summary.push(("Parse warnings", format!("{}", journal.num_of_warnings)));
+    }
 
-    note: Option<String>,
-    created_at: Option<NaiveDateTime>,
-    done_at: Option<NaiveDateTime>,
-    chains: Option<BTreeMap<NaiveDateTime, bool>>,
-    due_at: Option<NaiveDateTime>,
-    defer: Option<Defer>,
-    status: Option<Status>,
-    project: Option<ProjectPath>,
-    contexts: Option<Contexts>,
-    tags: Option<Tags>,
-    priority: Priority,
-    time: TimeLength,
-    // TODO: rename to flagged
-    flag: bool,
+    summary.push((executed_at_label, format!("{}", journal.now.format("%B %-d, %Y %-l:%M:%S %p"))));
 
-    source_file: Option<String>
+    return summary;
 }
 
-impl Task {
-
-    fn new(task_block_range_start: u64) -> Task {
-        Task {
+fn escape_markdown(text: &str) -> String {
 
-            task_block_range_start: task_block_range_start,
-            task_block_range_end: task_block_range_start,
+    let mut escaped = String::new();
 
-            /* props */
-            current: false,
-            title: None,
-            note: None,
-            created_at: None,
-            done_at: None,
-            chains: None,
-            due_at: None,
-            defer: None,
-            status: None,
-            project: None,
-            contexts: None,
-            tags: None,
-            priority: 0,
-            time: 0,
-            flag: false,
-            source_file: None
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            _ => escaped.push(c)
         }
     }
 
-    fn is_done(&self) -> bool {
+    return escaped;
+}
 
-        match self.status {
-            None => {},
-            Some(ref status) => {
+fn print_markdown_report(journal: &GTD, evaluate_at_given: bool) {
 
-                if let Status::Done = *status {
-                    return true;
-                }
+    println!("# Tasks");
 
-            }
-        };
+    for (section, task_ids) in report_sections(journal) {
 
-        return false;
-    }
+        println!("");
+        println!("## {} ({})", section, task_ids.len());
 
-    fn has_chain(&self) -> bool {
+        for (project, task_ids) in group_by_project(journal, task_ids) {
 
-        if self.chains.is_none() {
-            return false;
-        }
+            println!("");
+            println!("### {}", escape_markdown(&project));
 
-        match self.chains {
-            None => unsafe { debug_unreachable!() },
-            Some(ref tree) => {
-                return tree.len() > 0;
-            }
-        }
-    }
+            for task_id in &task_ids {
 
-    fn get_chain(&self) -> NaiveDateTime {
+                let task: &Task = journal.tasks.get(task_id).unwrap();
 
-        match self.chains {
-            None => unsafe { debug_unreachable!() },
-            Some(ref tree) => {
-                // see: http://stackoverflow.com/a/33699340/412627
-                // let (key, _) = tree.iter().last().unwrap();
-                let (key, _) = tree.iter().next_back().unwrap();
+                println!("");
+                println!("#### {}", escape_markdown(task.title.as_ref().unwrap()));
+                println!("");
 
-                return *key;
+                for (label, value) in report_task_attributes(journal, task) {
+                    println!("- **{}:** {}", label, escape_markdown(&value));
+                }
+
+                if journal.hide_notes {
+                    continue;
+                }
+
+                if let Some(ref note) = task.note {
+                    for paragraph in note_paragraphs(note) {
+                        println!("");
+                        // trailing backslash is a line break
+                        println!("{}", escape_markdown(&paragraph).replace("\n", "\\\n"));
+                    }
+                }
             }
         }
     }
 
-    fn debug_range_string(&self) -> String {
+    println!("");
+    println!("## Tasks completed in the past week");
+    println!("");
 
-        if self.task_block_range_start == self.task_block_range_end {
-            return format!("on line {}", self.task_block_range_start);
-        }
+    let pulse = report_pulse(journal);
 
-        return format!("between lines {} and {}",
-            self.task_block_range_start,
-            self.task_block_range_end
-        );
+    let labels: Vec<String> = pulse.iter().map(|&(ref label, _)| label.clone()).collect();
+    let dividers: Vec<&str> = pulse.iter().map(|_| "---:").collect();
+    let counts: Vec<String> = pulse.iter().map(|&(_, count)| format!("{}", count)).collect();
+
+    println!("| {} |", labels.join(" | "));
+    println!("| {} |", dividers.join(" | "));
+    println!("| {} |", counts.join(" | "));
+
+    println!("");
+    println!("## Summary");
+    println!("");
+
+    for (label, value) in report_summary(journal, evaluate_at_given) {
+        println!("- **{}:** {}", label, value);
     }
 }
 
-#[derive(Debug)]
-struct GTD {
+fn escape_html(text: &str) -> String {
 
-    /* debug */
-    // the line of the last task block line parsed
-    previous_task_block_line: u64,
+    let mut escaped = String::new();
 
-    /* flag/switches */
-    hide_notes: bool,
-    hide_flagged: bool,
-    show_only_flagged: bool,
-    show_done: bool,
-    show_incubate: bool,
-    show_deferred: bool,
-    hide_overdue: bool,
-    hide_nonproject_tasks: bool,
-    hide_incomplete: bool,
-    project_only_filter: Tree,
-    project_whitelist: Tree,
-    show_line_num_with_file_location: bool,
-    sort_overdue_by_priority: bool,
-    filter_by_only_tags: bool,
-    filter_by_only_contexts: bool,
-    due_within: Duration,
-    filter_priority: Option<PriorityFilterTree>,
-    hide_tasks_by_default: bool,
-    show_overdue: bool,
-    show_incomplete: bool,
-    show_flagged: bool,
-    show_nonproject_tasks: bool,
-    show_project_tasks: bool,
-    filter_by_include_tags: bool,
-    include_tags: HashSet<String>,
-    filter_by_include_contexts: bool,
-    include_contexts: HashSet<String>,
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c)
+        }
+    }
 
-    /* data */
+    return escaped;
+}
 
-    current_task: Option<u64>,
+const HTML_REPORT_STYLE: &'static str = "
+body { font-family: -apple-system, Helvetica, Arial, sans-serif; color: #222; max-width: 50em; margin: 2em auto; padding: 0 1em; line-height: 1.4; }
+h1 { border-bottom: 2px solid #222; }
+h2 { border-bottom: 1px solid #ccc; margin-top: 2em; }
+h3 { color: #555; }
+.task { border-left: 3px solid #4a90d9; padding: 0 0 0 1em; margin: 1em 0; }
+.task h4 { margin: 0 0 0.5em 0; }
+.task dl { display: grid; grid-template-columns: max-content auto; gap: 0.2em 1em; margin: 0; }
+.task dt { font-weight: bold; color: #4a90d9; }
+.task dd { margin: 0; }
+.section-overdue .task { border-left-color: #d94a4a; }
+.section-done .task { border-left-color: #4ad97a; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: right; }
+dl.summary { display: grid; grid-template-columns: max-content auto; gap: 0.2em 1em; }
+dl.summary dt { font-weight: bold; }
+dl.summary dd { margin: 0; }
+";
 
-    base_root: String,
+fn print_html_report(journal: &GTD, evaluate_at_given: bool) {
 
-    // track files opened
-    opened_files: HashSet<String>,
+    println!("<!DOCTYPE html>");
+    println!("<html>");
+    println!("<head>");
+    println!("<meta charset=\"utf-8\">");
+    println!("<title>Tasks</title>");
+    println!("<style>{}</style>", HTML_REPORT_STYLE);
+    println!("</head>");
+    println!("<body>");
+    println!("<h1>Tasks</h1>");
 
-    // This tracks the order of the inserted path to file_stats. Used for output.
-    file_stats_stack: Vec<String>,
-    // path to file -> FileStats
-    file_stats: HashMap<String, FileStats>,
+    for (section, task_ids) in report_sections(journal) {
 
-    pulse: HashMap<i64, Vec<u64>>,
+        println!("<section class=\"section-{}\">", section.to_lowercase());
+        println!("<h2>{} ({})</h2>", section, task_ids.len());
 
-    only_tags: HashSet<String>,
+        for (project, task_ids) in group_by_project(journal, task_ids) {
 
-    only_contexts: HashSet<String>,
+            println!("<h3>{}</h3>", escape_html(&project));
 
-    // lookup table for tasks
-    tasks: HashMap<u64, Task>,
+            for task_id in &task_ids {
 
-    // this contains any tasks that are overdue
-    // timestamp difference -> task id
-    overdue: BTreeMap<i64, Vec<u64>>,
+                let task: &Task = journal.tasks.get(task_id).unwrap();
 
-    // this contains any tasks that are either due soon
-    // timestamp difference -> task id
-    // due_soon: BTreeMap<i64, Vec<i32>>,
+                println!("<article class=\"task\">");
+                println!("<h4>{}</h4>", escape_html(task.title.as_ref().unwrap()));
+                println!("<dl>");
 
-    // inbox contain any tasks that do not have a project
-    // priority -> vector of task ids ordered by recent appearance (default)
-    inbox: BTreeMap<i64, Vec<u64>>,
+                for (label, value) in report_task_attributes(journal, task) {
+                    println!("<dt>{}</dt><dd>{}</dd>", label, escape_html(&value));
+                }
 
-    // this contains any tasks that are inactive
-    // priority -> vector of task ids ordered by recent appearance (default)
-    deferred: BTreeMap<i64, Vec<u64>>,
+                println!("</dl>");
 
-    // this contains any tasks that are compelted
-    // priority -> vector of task ids ordered by recent appearance (default)
-    done: BTreeMap<i64, Vec<u64>>
-}
+                if !journal.hide_notes {
+                    if let Some(ref note) = task.note {
+                        for paragraph in note_paragraphs(note) {
+                            println!("<p>{}</p>", escape_html(&paragraph).replace("\n", "<br>\n"));
+                        }
+                    }
+                }
 
-impl GTD {
-    fn new(base_root: String) -> GTD {
+                println!("</article>");
+            }
+        }
 
-        let mut inbox = BTreeMap::new();
-        // inbox at priority 0
-        inbox.insert(0, Vec::new());
-        let inbox = inbox;
+        println!("</section>");
+    }
 
-        let mut done = BTreeMap::new();
-        // done bucket at priority 0
-        done.insert(0, Vec::new());
-        let done = done;
+    println!("<h2>Tasks completed in the past week</h2>");
+    println!("<table>");
 
-        let mut deferred = BTreeMap::new();
-        // deferred bucket at priority 0
-        deferred.insert(0, Vec::new());
-        let deferred = deferred;
+    let pulse = report_pulse(journal);
 
-        GTD {
+    println!("<tr>");
+    for &(ref label, _) in &pulse {
+        println!("<th>{}</th>", label);
+    }
+    println!("</tr>");
 
-            /* error output */
-            previous_task_block_line: 0,
+    println!("<tr>");
+    for &(_, count) in &pulse {
+        println!("<td>{}</td>", count);
+    }
+    println!("</tr>");
 
-            /* options */
-            hide_notes: false,
-            hide_flagged: false,
-            show_only_flagged: false,
-            show_done: false,
-            show_incubate: false,
-            show_deferred: false,
-            hide_overdue: false,
-            hide_nonproject_tasks: false,
-            hide_incomplete: false,
-            project_only_filter: HashMap::new(),
-            project_whitelist: HashMap::new(),
-            show_line_num_with_file_location: false,
-            sort_overdue_by_priority: false,
-            filter_by_only_tags: false,
-            filter_by_only_contexts: false,
-            due_within: Duration::seconds(0),
-            filter_priority: None,
-            hide_tasks_by_default: false,
-            show_overdue: false,
-            show_incomplete: false,
-            show_flagged: false,
-            show_nonproject_tasks: false,
-            show_project_tasks: false,
-            filter_by_include_tags: false,
-            include_tags: HashSet::new(),
-            filter_by_include_contexts: false,
-            include_contexts: HashSet::new(),
+    println!("</table>");
 
-            /* data */
+    println!("<h2>Summary</h2>");
+    println!("<dl class=\"summary\">");
 
-            current_task: None,
+    for (label, value) in report_summary(journal, evaluate_at_given) {
+        println!("<dt>{}</dt><dd>{}</dd>", label, escape_html(&value));
+    }
 
-            base_root: base_root,
-            opened_files: HashSet::new(),
+    println!("</dl>");
+    println!("</body>");
+    println!("</html>");
+}
 
-            file_stats_stack: Vec::new(),
-            file_stats: HashMap::new(),
+#[test]
+fn report_test() {
 
-            pulse: HashMap::new(),
+    assert_eq!(note_paragraphs("\n    Lorem ipsum\n    dolor sit amet.\n\n\n    Praesent faucibus.\n"),
+        vec!["Lorem ipsum\ndolor sit amet.".to_owned(), "Praesent faucibus.".to_owned()]);
 
-            only_tags: HashSet::new(),
-            only_contexts: HashSet::new(),
+    assert_eq!(escape_markdown("buy *milk* #1"), "buy \\*milk\\* \\#1".to_owned());
+    assert_eq!(escape_html("<b>milk & \"eggs\"</b>"),
+        "&lt;b&gt;milk &amp; &quot;eggs&quot;&lt;/b&gt;".to_owned());
+}
 
-            tasks: HashMap::new(),
-            inbox: inbox,
-            done: done,
-            deferred: deferred,
-            overdue: BTreeMap::new()
-        }
-    }
+/* grouping */
 
-    fn add_tag_only_filters(&mut self, tags: Vec<String>) {
-        for tag in tags {
-            self.only_tags.insert(tag);
+#[derive(Debug, Clone, PartialEq)]
+enum GroupBy {
+    Project,
+    Context,
+    Tag,
+    File
+}
+
+impl GroupBy {
+    fn from_string(group_by: &str) -> Option<GroupBy> {
+        match group_by.trim().to_lowercase().as_str() {
+            "project" | "projects" => Some(GroupBy::Project),
+            "context" | "contexts" => Some(GroupBy::Context),
+            "tag" | "tags" => Some(GroupBy::Tag),
+            "file" | "files" => Some(GroupBy::File),
+            _ => None
         }
     }
+}
 
-    fn add_tag_include_filters(&mut self, tags: Vec<String>) {
-        for tag in tags {
-            self.include_tags.insert(tag);
-        }
-    }
+// node of the project hierarchy; tasks are in order of display
+#[derive(Debug)]
+struct ProjectNode {
+    name: String,
+    task_ids: Vec<u64>,
+    children: Vec<ProjectNode>
+}
 
-    fn have_only_tags(&mut self, tags: &[String]) -> bool {
-        for tag in tags {
-            if self.only_tags.contains(tag) {
-                return true;
-            }
+impl ProjectNode {
+    fn new(name: String) -> ProjectNode {
+        ProjectNode {
+            name: name,
+            task_ids: Vec::new(),
+            children: Vec::new()
         }
-
-        return false;
     }
 
-    fn have_include_tags(&mut self, tags: &[String]) -> bool {
-        for tag in tags {
-            if self.include_tags.contains(tag) {
-                return true;
-            }
+    fn insert(&mut self, path: &[String], task_id: u64) {
+
+        if path.len() <= 0 {
+            self.task_ids.push(task_id);
+            return;
         }
 
-        return false;
-    }
+        let index = match self.children.iter().position(|child| child.name == path[0]) {
+            Some(index) => index,
+            None => {
+                self.children.push(ProjectNode::new(path[0].clone()));
+                self.children.len() - 1
+            }
+        };
 
-    fn add_context_only_filters(&mut self, contexts: Vec<String>) {
-        for context in contexts {
-            self.only_contexts.insert(context);
-        }
+        self.children[index].insert(&path[1..], task_id);
     }
 
-    fn add_context_include_filters(&mut self, contexts: Vec<String>) {
-        for context in contexts {
-            self.include_contexts.insert(context);
-        }
+    // number of tasks of this project and its sub-projects
+    fn num_of_tasks(&self) -> usize {
+        self.task_ids.len() + self.children.iter().map(|child| child.num_of_tasks()).sum::<usize>()
     }
 
-    fn have_only_contexts(&mut self, contexts: &[String]) -> bool {
-        for context in contexts {
-            if self.only_contexts.contains(context) {
-                return true;
-            }
-        }
-
-        return false;
-    }
+    // time spent on tasks of this project and its sub-projects
+    fn time_spent(&self, journal: &GTD) -> TimeLength {
 
-    fn have_include_contexts(&mut self, contexts: &[String]) -> bool {
-        for context in contexts {
-            if self.include_contexts.contains(context) {
-                return true;
-            }
-        }
+        let time: TimeLength = self.task_ids.iter()
+            .map(|task_id| journal.tasks.get(task_id).unwrap().time)
+            .sum();
 
-        return false;
+        return time + self.children.iter().map(|child| child.time_spent(journal)).sum::<TimeLength>();
     }
 
-    fn add_project_only_filter(&mut self, path: &mut Vec<String>) {
-        traverse(path, &mut self.project_only_filter);
-    }
+    fn summary(&self, journal: &GTD) -> String {
 
-    fn add_project_whitelist(&mut self, path: &mut Vec<String>) {
-        traverse(path, &mut self.project_whitelist);
-    }
+        let num_of_tasks = self.num_of_tasks();
 
-    fn has_project_only_filters(&mut self) -> bool {
-        self.project_only_filter.len() > 0
-    }
+        let mut summary = if num_of_tasks == 1 {
+            "1 task".to_owned()
+        } else {
+            format!("{} tasks", num_of_tasks)
+        };
 
-    fn has_project_whitelist(&mut self) -> bool {
-        self.project_whitelist.len() > 0
-    }
+        let time_spent = self.time_spent(journal);
 
-    fn should_only_filter_project(&mut self, path: &[String]) -> bool {
-        return path_satisfies_tree(&(self.project_only_filter), path);
-    }
+        if time_spent > 0 {
+            summary = format!("{}, {}", summary, Timerange::new(time_spent).print(2));
+        }
 
-    fn should_whitelist_project(&mut self, path: &[String]) -> bool {
-        return path_satisfies_tree(&(self.project_whitelist), path);
+        return summary;
     }
+}
 
-    fn add_task(&mut self, task: Task, directive_switch: &LocalDirectiveSwitches) {
+fn project_tree(journal: &GTD, task_ids: &[u64]) -> ProjectNode {
 
-        // TODO: is this the best placement for this?
-        let mut task = task;
-        task.task_block_range_end = self.previous_task_block_line;
-        let task = task;
+    let mut root = ProjectNode::new(String::new());
 
-        /* pass task through local scoped directive switches */
+    for task_id in task_ids {
 
-        let mut task = task;
-        directive_switch.transform_task(&mut task, self);
-        let task = task;
+        let task: &Task = journal.tasks.get(task_id).unwrap();
 
-        if !directive_switch.pass_validation(&task, self) {
-            // TODO: pass_validation prints errors and therefore produces side-effects; refactor
-            return;
+        if let Some(ref project_path) = task.project {
+            root.insert(project_path, *task_id);
         }
+    }
 
-        /* pass task through global scoped directive switches */
+    return root;
+}
 
-        // TODO: complete
-        // self.directive_switches.transform_task(&mut task, self);
+fn project_tree_art(journal: &GTD, node: &ProjectNode, prefix: &str, lines: &mut Vec<String>) {
 
-        // task title is required attribute
-        if task.title.is_none() {
+    for (index, child) in node.children.iter().enumerate() {
 
-            println!("Missing task title (i.e. `task: <title>`) in task block found {}",
-                task.debug_range_string()
-            );
-            println!("Captured:");
-            _print_task(self, &task, false);
-            process::exit(1);
-        }
+        let is_last = index + 1 >= node.children.len();
 
-        let new_id: u64 = self.next_task_id();
+        let (branch, indent) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
 
-        if task.current {
+        lines.push(format!("{}{}{} ({})", prefix, branch, child.name, child.summary(journal)));
 
-            match self.current_task {
-                Some(first_task_id) => {
+        project_tree_art(journal, child, &format!("{}{}", prefix, indent), lines);
+    }
+}
 
-                    println!("Found at least two current tasks.");
-                    println!("Only one task can be marked as current.");
-                    println!("");
+// groups of projects in depth-first order
+fn project_groups(journal: &GTD, node: &ProjectNode, path: &mut Vec<String>, groups: &mut Vec<(String, Vec<u64>)>) {
 
-                    println!("First task found to be current:");
-                    let first_task: &Task = self.tasks.get(&first_task_id).unwrap();
-                    print_task(self, first_task);
+    if node.task_ids.len() > 0 {
+        groups.push((format!("{} ({})", path.join(" / "), node.summary(journal)), node.task_ids.clone()));
+    }
 
-                    println!("");
+    for child in &node.children {
+        path.push(child.name.clone());
+        project_groups(journal, child, path, groups);
+        path.pop();
+    }
+}
 
-                    println!("Second task found to be current:");
-                    print_task(self, &task);
+// groups of tasks with headers; tasks may be in more than one group (e.g. with many contexts)
+fn group_tasks(journal: &GTD, task_ids: &[u64], group_by: &GroupBy) -> Vec<(String, Vec<u64>)> {
 
-                    process::exit(1);
-                },
-                None => {
-                    self.current_task = Some(new_id);
-                }
-            };
-        }
+    let mut groups: Vec<(String, Vec<u64>)> = Vec::new();
+    let mut ungrouped: Vec<u64> = Vec::new();
 
-        match task.done_at {
-            None => {},
-            Some(ref done_at) => {
+    match *group_by {
+        GroupBy::Project => {
 
-                if !task.is_done() {
+            let root = project_tree(journal, task_ids);
+            project_groups(journal, &root, &mut Vec::new(), &mut groups);
 
-                    println!("In file: {}", task.source_file.as_ref().unwrap());
-                    println!("Task is incorrectly given a `done` datetime found at {}",
-                        task.debug_range_string()
-                    );
-                    println!("Mayhaps you forgot to add: 'status: done'");
-                    process::exit(1);
-                } else {
-                    self.add_to_pulse(done_at, new_id);
-                }
+            ungrouped.extend(task_ids.iter().cloned().filter(|task_id| {
+                journal.tasks.get(task_id).unwrap().project.is_none()
+            }));
+        },
+        GroupBy::Context | GroupBy::Tag => {
 
-            }
-        };
+            let mut named_groups: BTreeMap<String, Vec<u64>> = BTreeMap::new();
 
-        match task.source_file {
-            None => unsafe { debug_unreachable!() },
-            Some(ref source_file) => {
+            for task_id in task_ids {
 
-                match task.status {
-                    None => {
+                let task: &Task = journal.tasks.get(task_id).unwrap();
 
-                        if self.is_overdue(&task) {
-                            let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                            file_stats.add_overdue_task_id(new_id);
-                        } else if !self.should_defer(&task) {
-                            let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                            // add task to inbox
-                            file_stats.add_inbox_task_id(new_id);
-                        } else {
-                            let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                            file_stats.add_deferred_task_id(new_id);
-                        }
+                let names = match *group_by {
+                    GroupBy::Context => &task.contexts,
+                    _ => &task.tags
+                };
 
+                match *names {
+                    None => {
+                        ungrouped.push(*task_id);
                     },
-                    Some(ref status) => {
-
-                        match *status {
-                            Status::NotDone => {
-                                if self.is_overdue(&task) {
-                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                                    file_stats.add_overdue_task_id(new_id);
-                                } else if !self.should_defer(&task) {
-                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                                    // add task to inbox
-                                    file_stats.add_inbox_task_id(new_id);
-                                } else {
-                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                                    file_stats.add_deferred_task_id(new_id);
-                                }
-                            },
-                            Status::Incubate => {
-
-                                if self.is_overdue(&task) {
-                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                                    file_stats.add_overdue_task_id(new_id);
-                                } else if !self.should_defer(&task) {
-                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                                    file_stats.add_incubate_task_id(new_id);
-                                } else {
-                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                                    file_stats.add_deferred_task_id(new_id);
-                                }
-
-                            },
-                            Status::Done => {
-                                let file_stats = self.file_stats.get_mut(source_file).unwrap();
-                                file_stats.add_finished_task_id(new_id);
-                            }
+                    Some(ref names) => {
+                        for name in names {
+                            named_groups.entry(name.clone()).or_insert_with(Vec::new).push(*task_id);
                         }
                     }
-                };
-
-                let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                }
+            }
 
-                match task.tags {
-                    None => {},
-                    Some(ref tags) => {
-                        for tag in tags {
-                            file_stats.add_tag(tag.clone());
-                        }
+            for (name, task_ids) in named_groups {
 
-                    }
+                let header = match *group_by {
+                    GroupBy::Context => format!("@{} ({})", name, task_ids.len()),
+                    _ => format!("{} ({})", name, task_ids.len())
                 };
 
-                match task.contexts {
-                    None => {},
-                    Some(ref contexts) => {
+                groups.push((header, task_ids));
+            }
+        },
+        GroupBy::File => {
 
-                        for context in contexts {
-                            file_stats.add_context(context.clone());
-                        }
+            // files in order of being parsed
+            for path in &journal.file_stats_stack {
 
-                    }
-                };
+                let file_task_ids: Vec<u64> = task_ids.iter().cloned().filter(|task_id| {
+                    journal.tasks.get(task_id).unwrap().source_file.as_ref() == Some(path)
+                }).collect();
 
-                match task.project {
-                    None => {},
-                    Some(ref project_path) => {
-                        file_stats.add_project_path(project_path.clone());
-                    }
+                if file_task_ids.len() <= 0 {
+                    continue;
+                }
+
+                let path = match Path::new(path).strip_prefix(&journal.base_root) {
+                    Err(_) => path.to_owned(),
+                    Ok(path) => format!("./{}", path.display())
                 };
 
+                groups.push((format!("{} ({})", path, file_task_ids.len()), file_task_ids));
             }
+        }
+    }
+
+    if ungrouped.len() > 0 {
+
+        let name = match *group_by {
+            GroupBy::Project => "No project",
+            GroupBy::Context => "No context",
+            GroupBy::Tag => "No tags",
+            GroupBy::File => unsafe { debug_unreachable!() }
         };
 
-        // sort tasks into various data structures (e.g. overdue, inbox, etc) that shall be displayed
-        // to the user
+        groups.push((format!("{} ({})", name, ungrouped.len()), ungrouped));
+    }
 
-        if self.hide_tasks_by_default {
+    return groups;
+}
 
-            // hide task unless it satisfy [whitelist] filters
+// returns number of tasks displayed
+fn print_grouped_tasks(journal: &GTD, group_by: &GroupBy, show_headers: bool) -> u64 {
 
-            self.add_task_default_hidden(&task, new_id);
+    let task_ids = displayed_task_ids(journal);
+    let mut print_line: bool = false;
 
-        } else {
+    if *group_by == GroupBy::Project && show_headers {
 
-            // default behaviour
+        let root = project_tree(journal, &task_ids);
 
-            self.add_task_default(&task, new_id);
-        }
+        if root.children.len() > 0 {
 
-        // add task to look-up table
-        self.tasks.insert(new_id, task);
+            println!("{}", "Projects".white().bold().underline());
+            println!("");
 
-    }
+            let mut lines: Vec<String> = Vec::new();
+            project_tree_art(journal, &root, "", &mut lines);
 
-    #[allow(cyclomatic_complexity)]
-    fn add_task_default_hidden(&mut self, task: &Task, new_id: u64) {
+            for line in lines {
+                println!("{}", line);
+            }
 
-        if self.should_hide_task(task) {
-            return;
+            print_line = true;
         }
+    }
 
-        let mut shall_show: bool =
-            self.filter_by_only_tags && task.tags.is_some() ||
-            self.filter_by_only_contexts && task.contexts.is_some() ||
-            self.has_project_only_filters() && task.project.is_some() ||
-            self.show_only_flagged && task.flag ||
-            self.show_flagged && task.flag ||
-            self.show_nonproject_tasks && task.project.is_none() ||
-            self.show_project_tasks && task.project.is_some();
+    for (header, task_ids) in group_tasks(journal, &task_ids, group_by) {
 
+        if print_line {
+            println!("");
+        }
 
-        if self.has_project_whitelist() {
+        if show_headers {
+            println!("{}", header.white().bold().underline());
+            println!("");
+        }
 
-            if let Some(ref project_path) = task.project {
-                if self.should_whitelist_project(project_path) {
-                    shall_show = true;
-                }
-            }
+        print_vector_of_tasks(journal, &task_ids);
 
-        };
+        print_line = true;
+    }
 
-        if self.filter_by_include_tags {
-            match task.tags {
-                None => {},
-                Some(ref tags) => {
-                    if self.have_include_tags(tags) {
-                        shall_show = true;
-                    }
-                }
-            }
-        }
+    return task_ids.len() as u64;
+}
 
-        if self.filter_by_include_contexts {
-            match task.contexts {
-                None => {},
-                Some(ref contexts) => {
-                    if self.have_include_contexts(contexts) {
-                        shall_show = true;
-                    }
-                }
+#[test]
+fn project_tree_test() {
+
+    let mut journal = GTD::new("/".to_owned());
+
+    let projects = vec![
+        Some(vec!["personal".to_owned(), "food".to_owned()]),
+        Some(vec!["work".to_owned()]),
+        None,
+        Some(vec!["personal".to_owned()]),
+        Some(vec!["personal".to_owned(), "food".to_owned()])
+    ];
+
+    for (index, project) in projects.into_iter().enumerate() {
+        let mut task = Task::new(index as u64);
+        task.project = project;
+        task.time = 60;
+        journal.tasks.insert(index as u64 + 1, task);
+    }
+
+    let root = project_tree(&journal, &[1, 2, 3, 4, 5]);
+
+    let mut lines: Vec<String> = Vec::new();
+    project_tree_art(&journal, &root, "", &mut lines);
+
+    assert_eq!(lines, vec![
+        "├── personal (3 tasks, 3 minutes)".to_owned(),
+        "│   └── food (2 tasks, 2 minutes)".to_owned(),
+        "└── work (1 task, 1 minute)".to_owned()
+    ]);
+
+    let groups: Vec<Vec<u64>> = group_tasks(&journal, &[1, 2, 3, 4, 5], &GroupBy::Project)
+        .into_iter()
+        .map(|(_, task_ids)| task_ids)
+        .collect();
+
+    assert_eq!(groups, vec![vec![4], vec![1, 5], vec![2], vec![3]]);
+}
+
+/* templates */
+
+const ONELINE_TEMPLATE: &'static str = "{priority:>4} {bold}{title}{/} {blue}{project}{/} {red}{due:rel}{/}";
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplateField {
+    Task(TaskField),
+    Line,
+    Id,
+    Chain
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplateModifier {
+    AlignLeft(usize),
+    AlignRight(usize),
+    Relative,
+    Date,
+    Iso,
+    Human
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplateToken {
+    Literal(String),
+    Field(TemplateField, Vec<TemplateModifier>),
+    Style(String),
+    EndStyle
+}
+
+fn is_template_style(style: &str) -> bool {
+    match style {
+        "black" | "red" | "green" | "yellow" | "blue" | "magenta" | "purple" | "cyan" | "white" |
+        "bold" | "dimmed" | "italic" | "underline" => true,
+        _ => false
+    }
+}
+
+fn apply_template_style(text: ColoredString, style: &str) -> ColoredString {
+    match style {
+        "black" => text.black(),
+        "red" => text.red(),
+        "green" => text.green(),
+        "yellow" => text.yellow(),
+        "blue" => text.blue(),
+        "magenta" => text.magenta(),
+        "purple" => text.purple(),
+        "cyan" => text.cyan(),
+        "white" => text.white(),
+        "bold" => text.bold(),
+        "dimmed" => text.dimmed(),
+        "italic" => text.italic(),
+        "underline" => text.underline(),
+        _ => text
+    }
+}
+
+fn parse_template_field(name: &str) -> Option<TemplateField> {
+    match name {
+        "line" => Some(TemplateField::Line),
+        "id" => Some(TemplateField::Id),
+        "chain" => Some(TemplateField::Chain),
+        _ => {
+            match parse_only(|i| parse!{i; let field = parse_task_field(); eof(); ret field}, name.as_bytes()) {
+                Ok(field) => Some(TemplateField::Task(field)),
+                Err(_) => None
             }
         }
+    }
+}
 
-        // sort task by status and priority
-        match task.status {
-            None => {
-
-                if self.hide_incomplete {
-                    // hide task
-                } else if self.is_overdue(task) {
-
-                    if self.show_overdue || shall_show {
-                        self.add_to_overdue(task, new_id);
-                    }
+fn parse_template_modifier(modifier: &str) -> Option<TemplateModifier> {
 
-                } else if !self.should_defer(task) {
+    if modifier.starts_with('<') || modifier.starts_with('>') {
+        return match modifier[1..].parse::<usize>() {
+            Err(_) => None,
+            Ok(width) => {
+                if modifier.starts_with('<') {
+                    Some(TemplateModifier::AlignLeft(width))
+                } else {
+                    Some(TemplateModifier::AlignRight(width))
+                }
+            }
+        };
+    }
 
-                    if self.show_incomplete || shall_show {
-                        // add task to inbox
-                        self.add_to_inbox(task.priority, new_id);
-                    }
+    match modifier {
+        "rel" => Some(TemplateModifier::Relative),
+        "date" => Some(TemplateModifier::Date),
+        "iso" => Some(TemplateModifier::Iso),
+        "human" => Some(TemplateModifier::Human),
+        _ => None
+    }
+}
 
-                } else if self.show_deferred || shall_show {
-                    self.add_to_deferred(task.priority, new_id);
-                }
+// format: literal text with {field:modifier:...}, {style} ... {/}, {{ and }}
+fn parse_template(template: &str) -> Result<Vec<TemplateToken>, String> {
 
-            },
-            Some(ref status) => {
+    let mut tokens: Vec<TemplateToken> = Vec::new();
+    let mut literal = String::new();
+    let mut num_open_styles = 0;
 
-                match *status {
-                    Status::NotDone | Status::Incubate => {
+    let mut chars = template.chars().peekable();
 
-                        if self.hide_incomplete {
-                            // hide task
-                        } else if self.is_overdue(task) {
+    while let Some(c) = chars.next() {
 
-                            if self.show_overdue || shall_show {
-                                self.add_to_overdue(task, new_id);
-                            }
+        if c == '}' {
 
-                        } else if !self.should_defer(task) {
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                literal.push('}');
+                continue;
+            }
 
-                            if self.show_incomplete || shall_show {
-                                // add task to inbox
-                                self.add_to_inbox(task.priority, new_id);
-                            }
+            return Err("Unexpected `}`. Use `}}` for a literal brace.".to_owned());
+        }
 
-                        } else if self.show_deferred || shall_show {
-                            self.add_to_deferred(task.priority, new_id);
-                        }
-                    },
+        if c != '{' {
+            literal.push(c);
+            continue;
+        }
 
-                    // NOTE: merged with above arm
-                    // Status::Incubate => {
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            literal.push('{');
+            continue;
+        }
 
-                    //     if self.hide_incomplete {
-                    //         // hide task
-                    //     } else if self.is_overdue(task) {
+        let mut placeholder = String::new();
+        let mut closed = false;
 
-                    //         if self.show_overdue || shall_show {
-                    //             self.add_to_overdue(task, new_id);
-                    //         }
+        while let Some(c) = chars.next() {
+            if c == '}' {
+                closed = true;
+                break;
+            }
+            placeholder.push(c);
+        }
 
-                    //     } else if !self.should_defer(task) {
+        if !closed {
+            return Err(format!("Missing `}}` of `{{{}`.", placeholder));
+        }
 
-                    //         if self.show_incomplete || shall_show {
-                    //             // add task to inbox
-                    //             self.add_to_inbox(task.priority, new_id);
-                    //         }
+        if literal.len() > 0 {
+            tokens.push(TemplateToken::Literal(literal.clone()));
+            literal.clear();
+        }
 
-                    //     } else if self.show_deferred || shall_show {
-                    //         self.add_to_deferred(task.priority, new_id);
-                    //     }
-                    // },
-                    Status::Done => {
+        let placeholder = placeholder.trim().to_lowercase();
 
-                        if self.show_done || shall_show {
-                            self.add_to_done(task.priority, new_id);
-                        }
+        if placeholder == "/" {
 
-                    }
-                }
+            if num_open_styles <= 0 {
+                return Err("Unexpected `{/}` without a style.".to_owned());
             }
-        }
-
-    }
 
-    fn add_task_default(&mut self, task: &Task, new_id: u64) {
+            num_open_styles -= 1;
+            tokens.push(TemplateToken::EndStyle);
+            continue;
+        }
 
-        if self.should_hide_task(task) {
-            return;
+        if is_template_style(&placeholder) {
+            num_open_styles += 1;
+            tokens.push(TemplateToken::Style(placeholder));
+            continue;
         }
 
-        // sort task by status and priority
-        match task.status {
-            None => {
+        let mut parts = placeholder.split(':');
 
-                if self.hide_incomplete {
-                    // hide task
-                } else if self.is_overdue(task) {
-                    self.add_to_overdue(task, new_id);
-                } else if !self.should_defer(task) {
-                    // add task to inbox
-                    self.add_to_inbox(task.priority, new_id);
-                } else {
-                    self.add_to_deferred(task.priority, new_id);
-                }
+        let name = parts.next().unwrap().trim();
 
+        let field = match parse_template_field(name) {
+            None => {
+                return Err(format!("Unknown field `{}` in `{{{}}}`.", name, placeholder));
             },
-            Some(ref status) => {
+            Some(field) => field
+        };
 
-                match *status {
-                    Status::NotDone => {
+        let mut modifiers: Vec<TemplateModifier> = Vec::new();
 
-                        if self.hide_incomplete {
-                            // hide task
-                        } else if self.is_overdue(task) {
-                            self.add_to_overdue(task, new_id);
-                        } else if !self.should_defer(task) {
-                            // add task to inbox
-                            self.add_to_inbox(task.priority, new_id);
-                        } else {
-                            self.add_to_deferred(task.priority, new_id);
-                        }
-                    },
-                    Status::Incubate => {
+        for modifier in parts {
+            match parse_template_modifier(modifier.trim()) {
+                None => {
+                    return Err(format!("Unknown format `{}` in `{{{}}}`.", modifier.trim(), placeholder));
+                },
+                Some(modifier) => {
+                    modifiers.push(modifier);
+                }
+            }
+        }
 
-                        if self.hide_incomplete {
-                            // hide task
-                        } else if self.is_overdue(task) {
-                            self.add_to_overdue(task, new_id);
-                        } else if !self.should_defer(task) {
+        tokens.push(TemplateToken::Field(field, modifiers));
+    }
 
-                            if self.show_incubate {
+    if literal.len() > 0 {
+        tokens.push(TemplateToken::Literal(literal));
+    }
 
-                                // add task to inbox
-                                self.add_to_inbox(task.priority, new_id);
+    return Ok(tokens);
+}
 
-                            }
+fn template_datetime(journal: &GTD, datetime: &NaiveDateTime, modifiers: &[TemplateModifier]) -> String {
 
-                        } else {
-                            self.add_to_deferred(task.priority, new_id);
-                        }
-                    },
-                    Status::Done => {
-                        self.add_to_done(task.priority, new_id);
-                    }
-                }
-            }
+    for modifier in modifiers {
+        match *modifier {
+            TemplateModifier::Relative => {
+                return match relative_time(datetime.timestamp(), journal.now.timestamp()) {
+                    RelativeTime::Now(_, rel_time) |
+                    RelativeTime::Past(_, rel_time) |
+                    RelativeTime::Future(_, rel_time) => rel_time
+                };
+            },
+            TemplateModifier::Date => {
+                return format!("{}", datetime.format("%Y-%m-%d"));
+            },
+            TemplateModifier::Iso => {
+                return iso8601_datetime(datetime);
+            },
+            TemplateModifier::Human => {
+                return format!("{}", datetime.format("%B %-d, %Y %-l:%M %p"));
+            },
+            _ => {}
         }
-
     }
 
-    fn should_hide_task(&mut self, task: &Task) -> bool {
+    return format!("{}", datetime.format("%Y-%m-%d %H:%M"));
+}
 
-        if self.hide_nonproject_tasks &&!task.project.is_some() {
-            return true;
+fn template_field_string(journal: &GTD, task: &Task, field: &TemplateField, modifiers: &[TemplateModifier])
+-> String {
+
+    let optional_datetime = |datetime: Option<NaiveDateTime>| {
+        match datetime {
+            None => String::new(),
+            Some(datetime) => template_datetime(journal, &datetime, modifiers)
         }
+    };
 
-        if self.filter_by_only_tags {
-            match task.tags {
-                None => {
-                    // TODO: need flag to control this
-                    return true;
-                },
-                Some(ref tags) => {
-                    if !self.have_only_tags(tags) {
-                        return true;
-                    }
-                }
+    match *field {
+        TemplateField::Task(TaskField::Due) => {
+            return optional_datetime(task.due_at);
+        },
+        TemplateField::Task(TaskField::Created) => {
+            return optional_datetime(task.created_at);
+        },
+        TemplateField::Task(TaskField::Done) => {
+            return optional_datetime(task.done_at);
+        },
+        TemplateField::Task(TaskField::Defer) => {
+            return match task.defer {
+                None => String::new(),
+                Some(Defer::Forever) => "forever".to_owned(),
+                Some(Defer::Until(defer_till)) => optional_datetime(Some(defer_till))
+            };
+        },
+        TemplateField::Chain => {
+            if !task.has_chain() {
+                return String::new();
+            }
+            return optional_datetime(Some(task.get_chain()));
+        },
+        TemplateField::Task(TaskField::Title) => {
+            return task.title.clone().unwrap_or_else(String::new);
+        },
+        TemplateField::Task(TaskField::Note) => {
+            // notes are joined into a single line
+            let note = task.note.clone().unwrap_or_else(String::new);
+            let lines: Vec<&str> = note.lines().map(|x| x.trim()).filter(|x| x.len() > 0).collect();
+            return lines.join(" ");
+        },
+        TemplateField::Task(TaskField::Project) => {
+            return task.project.as_ref().map(|x| x.join(" / ")).unwrap_or_else(String::new);
+        },
+        TemplateField::Task(TaskField::Tag) => {
+            return task.tags.as_ref().map(|x| x.join(", ")).unwrap_or_else(String::new);
+        },
+        TemplateField::Task(TaskField::Context) => {
+            return task.contexts.as_ref().map(|x| x.join(", ")).unwrap_or_else(String::new);
+        },
+        TemplateField::Task(TaskField::Status) => {
+            return task.status.as_ref().map(|x| x.string()).unwrap_or_else(String::new);
+        },
+        TemplateField::Task(TaskField::Priority) => {
+            return format!("{}", task.priority);
+        },
+        TemplateField::Task(TaskField::Time) => {
+            if modifiers.contains(&TemplateModifier::Iso) {
+                return format_iso8601_duration(task.time);
             }
+            if task.time <= 0 {
+                return String::new();
+            }
+            return Timerange::new(task.time).print(2);
+        },
+        TemplateField::Task(TaskField::Flagged) => {
+            return if task.flag { "flagged".to_owned() } else { String::new() };
+        },
+        TemplateField::Task(TaskField::File) => {
+            return task_file_location(journal, task);
+        },
+        TemplateField::Line => {
+            return format!("{}", task.task_block_range_start);
+        },
+        TemplateField::Id => {
+            return task.id.clone().unwrap_or_else(String::new);
         }
+    }
+}
 
-        if self.filter_by_only_contexts {
-            match task.contexts {
-                None => {
-                    // TODO: need flag to control this
-                    return true;
-                },
-                Some(ref contexts) => {
-                    if !self.have_only_contexts(contexts) {
-                        return true;
-                    }
-                }
-            }
+fn align_template_field(text: String, modifiers: &[TemplateModifier]) -> String {
+
+    let mut text = text;
+
+    for modifier in modifiers {
+        match *modifier {
+            TemplateModifier::AlignLeft(width) => {
+                text = format!("{:<width$}", text, width = width);
+            },
+            TemplateModifier::AlignRight(width) => {
+                text = format!("{:>width$}", text, width = width);
+            },
+            _ => {}
         }
+    }
 
-        // invariant: task belongs to a project
+    return text;
+}
 
-        // if necessary, apply any project path apply filters
+fn render_template(journal: &GTD, task: &Task, template: &[TemplateToken]) -> String {
 
-        if self.has_project_only_filters() {
+    let mut line = String::new();
+    let mut styles: Vec<&str> = Vec::new();
 
-            let should_filter: bool = match task.project {
-                Some(ref project_path) => {
-                    !self.should_only_filter_project(project_path)
-                },
-                // TODO: need flag to control this
-                None => true
-            };
+    for token in template {
 
-            if should_filter {
-                return true;
+        let text: String = match *token {
+            TemplateToken::Literal(ref literal) => literal.clone(),
+            TemplateToken::Field(ref field, ref modifiers) => {
+                let text = template_field_string(journal, task, field, modifiers);
+                align_template_field(text, modifiers)
+            },
+            TemplateToken::Style(ref style) => {
+                styles.push(style);
+                continue;
+            },
+            TemplateToken::EndStyle => {
+                styles.pop();
+                continue;
             }
+        };
 
+        if styles.len() <= 0 || text.len() <= 0 {
+            line.push_str(&text);
+            continue;
         }
 
-        if self.show_only_flagged {
-            return !task.flag;
-        }
+        let mut styled: ColoredString = text.as_str().normal();
 
-        if self.show_flagged && task.flag {
-            return false;
+        for style in &styles {
+            styled = apply_template_style(styled, style);
         }
 
-        if self.hide_flagged {
-            return task.flag;
-        }
+        line.push_str(&format!("{}", styled));
+    }
 
-        if self.filter_priority.is_some() {
+    return line.trim_right().to_owned();
+}
 
-            let priority_filter = self.filter_priority.as_ref().unwrap();
+// one line per task; headers and the summary are comment lines (i.e. `# ...`) so that the
+// output stays greppable
+fn print_compact_report(journal: &GTD, template: &[TemplateToken], group_by: Option<&GroupBy>,
+    show_headers: bool, evaluate_at_given: bool) {
 
-            return !priority_satisfy_tree(priority_filter, task.priority);
+    let groups: Vec<(String, Vec<u64>)> = match group_by {
+        None => {
+            report_sections(journal).into_iter()
+                .map(|(section, task_ids)| (format!("{} ({})", section, task_ids.len()), task_ids.to_vec()))
+                .collect()
+        },
+        Some(group_by) => group_tasks(journal, &displayed_task_ids(journal), group_by)
+    };
 
-        }
+    for (header, task_ids) in groups {
 
-        // TODO: --hide-with-project
+        if show_headers {
+            println!("# {}", header);
+        }
 
-        // TODO: redundant; remove
-        // if self.show_project_tasks && task.project.is_some() {
-        //     return false;
-        // }
+        for task_id in &task_ids {
+            let task: &Task = journal.tasks.get(task_id).unwrap();
+            println!("{}", render_template(journal, task, template));
+        }
+    }
 
-        return false;
+    if !show_headers {
+        return;
     }
 
-    fn should_defer(&self, task: &Task) -> bool {
+    let pulse: Vec<String> = report_pulse(journal).iter()
+        .map(|&(ref label, count)| format!("{}: {}", label, count))
+        .collect();
 
-        // TODO: necessary??
-        // if self.show_deferred {
-        //     return false;
-        // }
-
-        match task.defer {
-            None => {
-                return false;
-            },
-            Some(ref defer) => {
-
-                match *defer {
-                    Defer::Forever => {
-                        return true;
-                    },
-                    Defer::Until(defer_till) => {
-                        return defer_till.timestamp() > Local::now().naive_local().timestamp();
-                    }
-                }
+    println!("# Tasks completed in the past week: {}", pulse.join(", "));
 
+    for (label, value) in report_summary(journal, evaluate_at_given) {
+        println!("# {}: {}", label, value);
+    }
+}
 
-            }
+#[test]
+fn template_test() {
+
+    match parse_template("{priority:>3} {{{title}}} {red}{due:rel}{/}") {
+        Ok(tokens) => {
+            assert_eq!(tokens, vec![
+                TemplateToken::Field(TemplateField::Task(TaskField::Priority), vec![TemplateModifier::AlignRight(3)]),
+                TemplateToken::Literal(" {".to_owned()),
+                TemplateToken::Field(TemplateField::Task(TaskField::Title), vec![]),
+                TemplateToken::Literal("} ".to_owned()),
+                TemplateToken::Style("red".to_owned()),
+                TemplateToken::Field(TemplateField::Task(TaskField::Due), vec![TemplateModifier::Relative]),
+                TemplateToken::EndStyle
+            ]);
+        },
+        Err(_) => {
+            assert!(false);
         }
-
-        return false;
     }
 
-    fn add_to_pulse(&mut self, done_at: &NaiveDateTime, task_id: u64) {
-
-        let diff = Local::now().naive_local().timestamp() - done_at.timestamp();
+    assert!(parse_template(ONELINE_TEMPLATE).is_ok());
+    assert!(parse_template("{unknown}").is_err());
+    assert!(parse_template("{due:soon}").is_err());
+    assert!(parse_template("{title").is_err());
+    assert!(parse_template("title}").is_err());
+    assert!(parse_template("{/}").is_err());
 
-        if !(0 <= diff && diff <= chrono::Duration::days(7).num_seconds()) {
-            return;
-        }
+    let journal = GTD::new("/".to_owned());
 
-        let diff = diff as f64;
+    let mut task = Task::new(3);
+    task.title = Some("buy milk".to_owned());
+    task.priority = 5;
+    task.project = Some(vec!["personal".to_owned(), "food".to_owned()]);
+    task.source_file = Some("/todo.gtd".to_owned());
 
-        let sec_per_minute: f64 = 60f64;
-        let sec_per_hour: f64 = sec_per_minute * 60f64;
-        let sec_per_day: f64 = sec_per_hour * 24f64;
+    let template = parse_template("{priority:>3} {title} [{project}] {line} {due}").unwrap();
+    assert_eq!(render_template(&journal, &task, &template), "  5 buy milk [personal / food] 3".to_owned());
+}
 
-        let days_ago = (diff / sec_per_day).floor() as i64;
+/* iCalendar */
 
-        self.pulse.entry(days_ago).or_insert_with(Vec::new);
+// tasks due at the end of the day (i.e. due dates without a time) are all-day
+fn is_all_day(datetime: &NaiveDateTime) -> bool {
+    return datetime.time() == NaiveTime::from_hms(23, 59, 0);
+}
 
-        match self.pulse.get_mut(&days_ago) {
-            None => unsafe { debug_unreachable!("journal.overdue missing expected bucket") },
-            Some(bucket) => {
-                (*bucket).push(task_id);
-            }
-        }
+fn ics_date(datetime: &NaiveDateTime) -> String {
+    format!("{}", datetime.format("%Y%m%d"))
+}
 
-    }
+// floating (i.e. local) datetime
+fn ics_datetime(datetime: &NaiveDateTime) -> String {
+    format!("{}", datetime.format("%Y%m%dT%H%M%S"))
+}
 
-    fn is_overdue(&self, task: &Task) -> bool {
+// FNV-1a; the hash shall be stable across versions and platforms
+fn stable_hash(text: &str) -> u64 {
 
-        match task.due_at {
-            None => {
-                return false;
-            },
-            Some(ref due_at) => {
-                return (Local::now().naive_local().timestamp() + self.due_within.num_seconds()) >= due_at.timestamp();
-            }
-        }
+    let mut hash: u64 = 0xcbf29ce484222325;
 
+    for byte in text.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
     }
 
-    fn add_to_overdue(&mut self, task: &Task, task_id: u64) {
+    return hash;
+}
 
-        match task.due_at {
-            None => {
-                return;
-            },
-            Some(ref due_at) => {
+fn ics_uid_key(journal: &GTD, task: &Task) -> String {
 
-                // sort by oldest due to most recently due
+    match task.id {
+        Some(ref id) => format!("id:{}", id),
+        None => {
 
-                let rel_time = due_at.timestamp() - Local::now().naive_local().timestamp();
+            let path = match task.source_file {
+                None => unsafe { debug_unreachable!() },
+                Some(ref path) => path
+            };
 
-                let encoded_key = if self.sort_overdue_by_priority {
+            let path = match Path::new(path).strip_prefix(&journal.base_root) {
+                Err(_) => path.to_owned(),
+                Ok(path) => format!("{}", path.display())
+            };
 
-                    // override to sort by priority
+            format!("{}\n{}", path, task.title.as_ref().unwrap())
+        }
+    }
+}
 
-                    GTD::encode_priority(task.priority) as i64
-                } else {
-                    // largest negative numbers appear first
-                    -rel_time
-                };
+// UIDs of all tasks of the journal, by task key.
+// tasks with the same key (e.g. several `task: run` blocks in a file) are told apart by their order in the file;
+// the first of them keeps the UID of the key alone.
+fn ics_uids(journal: &GTD) -> HashMap<u64, String> {
 
-                self.overdue.entry(encoded_key).or_insert_with(Vec::new);
+    let mut tasks: Vec<(&u64, &Task)> = journal.tasks.iter().collect();
+    tasks.sort_by(|&(_, a), &(_, b)| {
+        (&a.source_file, a.task_block_range_start).cmp(&(&b.source_file, b.task_block_range_start))
+    });
 
-                match self.overdue.get_mut(&encoded_key) {
-                    None => unsafe { debug_unreachable!("journal.overdue missing expected bucket") },
-                    Some(bucket) => {
-                        (*bucket).push(task_id);
-                    }
-                }
+    let mut occurrences: HashMap<String, u64> = HashMap::new();
+    let mut uids: HashMap<u64, String> = HashMap::new();
 
-            }
-        }
+    for (task_key, task) in tasks {
 
-    }
+        let key = ics_uid_key(journal, task);
 
-    fn add_to_inbox(&mut self, task_priority: i64, task_id: u64) {
+        let occurrence = occurrences.entry(key.clone()).or_insert(0);
 
-        self.ensure_priority_inbox(task_priority);
+        let key = if *occurrence > 0 {
+            format!("{}\n{}", key, occurrence)
+        } else {
+            key
+        };
 
-        let task_priority: i64 = GTD::encode_priority(task_priority);
+        *occurrence += 1;
 
-        match self.inbox.get_mut(&task_priority) {
-            None => unsafe { debug_unreachable!("add_to_inbox: expected priority bucket not found") },
-            Some(inbox) => {
-                (*inbox).push(task_id);
-            }
-        }
+        uids.insert(*task_key, format!("{:016x}@gtdtxt", stable_hash(&key)));
     }
 
-    fn add_to_deferred(&mut self, task_priority: i64, task_id: u64) {
+    return uids;
+}
 
-        self.ensure_priority_deferred(task_priority);
+fn escape_ics_text(text: &str) -> String {
 
-        let task_priority: i64 = GTD::encode_priority(task_priority);
+    let mut escaped = String::new();
 
-        match self.deferred.get_mut(&task_priority) {
-            None => unsafe { debug_unreachable!("add_to_deferred: expected priority bucket not found") },
-            Some(deferred) => {
-                (*deferred).push(task_id);
-            }
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {},
+            _ => escaped.push(c)
         }
     }
 
-    fn add_to_done(&mut self, task_priority: i64, task_id: u64) {
+    return escaped;
+}
 
-        self.ensure_priority_done(task_priority);
+// lines longer than 75 octets are folded; multi-octet characters are not split
+fn fold_ics_line(line: &str) -> String {
 
-        let task_priority: i64 = GTD::encode_priority(task_priority);
+    let mut folded = String::new();
+    let mut line_length = 0;
 
-        match self.done.get_mut(&task_priority) {
-            None => unsafe { debug_unreachable!("add_to_done: expected priority bucket not found") },
-            Some(done) => {
-                (*done).push(task_id);
-            }
-        }
-    }
+    for c in line.chars() {
 
+        if line_length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            // the leading space counts towards the length of the line
+            line_length = 1;
+        }
 
-    fn next_task_id(&mut self) -> u64 {
-        to_task_id(self.tasks.len() + 1) as u64
+        folded.push(c);
+        line_length += c.len_utf8();
     }
 
-    // TODO: refactor
+    return folded;
+}
 
-    fn ensure_priority_inbox(&mut self, priority: i64) {
+fn print_ics_line(line: &str) {
+    print!("{}\r\n", fold_ics_line(line));
+}
 
-        let priority = GTD::encode_priority(priority);
+// DTSTAMP is in UTC; it's derived from the journal's clock (see --now) so that output is reproducible.
+// a local time skipped or repeated by a daylight saving transition is taken as UTC.
+fn ics_dtstamp(now: &NaiveDateTime) -> String {
 
-        self.inbox.entry(priority).or_insert_with(Vec::new);
-    }
+    let utc: NaiveDateTime = match Local.from_local_datetime(now).single() {
+        Some(local) => local.with_timezone(&UTC).naive_utc(),
+        None => *now
+    };
 
-    fn ensure_priority_deferred(&mut self, priority: i64) {
+    format!("{}", utc.format("%Y%m%dT%H%M%SZ"))
+}
 
-        let priority = GTD::encode_priority(priority);
+fn print_ics(journal: &GTD, task_ids: &[u64], as_events: bool, defer_as_start: bool) {
 
-        self.deferred.entry(priority).or_insert_with(Vec::new);
-    }
+    let dtstamp = ics_dtstamp(&journal.now);
 
-    fn ensure_priority_done(&mut self, priority: i64) {
+    print_ics_line("BEGIN:VCALENDAR");
+    print_ics_line("VERSION:2.0");
+    print_ics_line(&format!("PRODID:-//gtdtxt//gtdtxt {}//EN", version!()));
+    print_ics_line("CALSCALE:GREGORIAN");
 
-        let priority = GTD::encode_priority(priority);
+    let uids = ics_uids(journal);
 
-        self.done.entry(priority).or_insert_with(Vec::new);
-    }
+    for task_id in task_ids {
 
-    // TODO: refactor
+        let task: &Task = journal.tasks.get(task_id).unwrap();
 
-    fn encode_priority(priority: i64) -> i64 {
-        -priority
-    }
+        let due_at = match task.due_at {
+            None => {
+                continue;
+            },
+            Some(ref due_at) => due_at
+        };
 
-    // NOTE: unused
-    // fn decode_priority(priority: i64) -> i64 {
-    //     -priority
-    // }
-}
+        let all_day = is_all_day(due_at);
 
-/* gtdtxt file parser */
+        let start_at: Option<NaiveDateTime> = match task.defer {
+            Some(Defer::Until(defer_till)) if defer_as_start => {
+                // start shall be before due
+                if defer_till < *due_at {
+                    Some(defer_till)
+                } else {
+                    None
+                }
+            },
+            _ => None
+        };
 
-// for clippy
-#[allow(cyclomatic_complexity)]
-fn parse_file(parent_file: Option<String>, path_to_file_str: String, journal: &mut GTD) {
+        let component = if as_events { "VEVENT" } else { "VTODO" };
 
-    let path_to_file: &Path = Path::new(&path_to_file_str);
+        print_ics_line(&format!("BEGIN:{}", component));
+        print_ics_line(&format!("UID:{}", uids.get(task_id).unwrap()));
+        print_ics_line(&format!("DTSTAMP:{}", dtstamp));
+        print_ics_line(&format!("SUMMARY:{}", escape_ics_text(task.title.as_ref().unwrap())));
 
-    if !path_to_file.is_file() {
-        // TODO: return Err(...)
+        if as_events {
 
-        match parent_file {
-            None => {},
-            Some(parent_file) => {
-                println!("In file: {}",
-                    parent_file
-                );
-            }
-        };
+            if all_day {
 
-        println!("Path is not a file: {}",
-            path_to_file_str
-        );
-        process::exit(1);
-    }
+                let start_at = start_at.unwrap_or(*due_at);
+                // end date of an all-day event is exclusive
+                let end_at = due_at.date().succ();
 
-    // fetch path to file
-    let tracked_path = match path_to_file.canonicalize() {
-        Ok(resolved) => {
-            let resolved: PathBuf = resolved;
-            format!("{}", resolved.display())
-        },
-        Err(e) => {
-            panic!("{:?}", e);
-        }
-    };
+                print_ics_line(&format!("DTSTART;VALUE=DATE:{}", ics_date(&start_at)));
+                print_ics_line(&format!("DTEND;VALUE=DATE:{}", end_at.format("%Y%m%d")));
 
-    if journal.opened_files.contains(&tracked_path) {
-        println!("Cyclic includes detected; file already opened: {}", tracked_path);
-        process::exit(1);
-    }
+            } else {
 
-    let file: File  = File::open(path_to_file).expect("Failed to open file");
+                match start_at {
+                    None => {
+                        print_ics_line(&format!("DTSTART:{}", ics_datetime(due_at)));
+                    },
+                    Some(start_at) => {
+                        print_ics_line(&format!("DTSTART:{}", ics_datetime(&start_at)));
+                        print_ics_line(&format!("DTEND:{}", ics_datetime(due_at)));
+                    }
+                }
+            }
 
-    // track this opened file to ensure we're not opening the same file twice
-    journal.opened_files.insert(tracked_path.clone());
+        } else {
 
+            if all_day {
 
-    // save current working directory
-    let old_working_directory = format!("{}", env::current_dir().unwrap().display());
+                if let Some(start_at) = start_at {
+                    if start_at.date() < due_at.date() {
+                        print_ics_line(&format!("DTSTART;VALUE=DATE:{}", ics_date(&start_at)));
+                    }
+                }
 
-    // set new current working dir
-    let parent_dir: String = {
-        let parent_dir = Path::new(&tracked_path).parent().unwrap();
-        format!("{}", parent_dir.display())
-    };
+                print_ics_line(&format!("DUE;VALUE=DATE:{}", ics_date(due_at)));
 
-    if !env::set_current_dir(&parent_dir).is_ok() {
-        println!("Unable to change working directory to: {}", parent_dir);
-        process::exit(1);
-    }
+            } else {
 
-    journal.file_stats.insert(tracked_path.clone(), FileStats::new());
-    journal.file_stats_stack.push(tracked_path.clone());
+                if let Some(start_at) = start_at {
+                    print_ics_line(&format!("DTSTART:{}", ics_datetime(&start_at)));
+                }
 
-    let mut num_of_lines_parsed = 0;
+                print_ics_line(&format!("DUE:{}", ics_datetime(due_at)));
+            }
 
-    // parse gtdtxt file
+            if task.is_done() {
+                print_ics_line("STATUS:COMPLETED");
+            }
+        }
 
-    let mut input = Source::new(file);
+        let mut categories: Vec<String> = Vec::new();
 
-    // file scoped directive switches
-    let mut directive_switch = LocalDirectiveSwitches::new();
+        if let Some(ref project_path) = task.project {
+            categories.push(escape_ics_text(&project_path.join(" / ")));
+        }
 
-    // initial state
-    let mut previous_state: ParseState = ParseState::Start;
+        if let Some(ref tags) = task.tags {
+            for tag in tags {
+                categories.push(escape_ics_text(tag));
+            }
+        }
 
-    loop {
+        if categories.len() > 0 {
+            print_ics_line(&format!("CATEGORIES:{}", categories.join(",")));
+        }
 
-        let mut n = Numbering::new(LinesParsed::new(), line_token_parser);
-        // If we could implement FnMut for Numbering then we would be good, but we need to wrap now:
-        let m = |i| n.parse(i);
+        if let Some(ref note) = task.note {
+            print_ics_line(&format!("DESCRIPTION:{}", escape_ics_text(&note_text(note))));
+        }
 
-        match input.parse(m) {
-            Ok((lines_parsed, line)) => {
+        print_ics_line(&format!("END:{}", component));
+    }
 
-                // TODO: fix this... off by one hack fix :P
-                // amend behaviour of newline counting
-                let lines_parsed = if lines_parsed == 0 {
-                    1
-                } else {
-                    lines_parsed
-                };
+    print_ics_line("END:VCALENDAR");
+}
 
-                // track the current line number
-                num_of_lines_parsed += lines_parsed;
+#[test]
+fn ics_test() {
 
-                match line {
+    assert_eq!(escape_ics_text("milk, eggs; bread\\jam\nmore"),
+        "milk\\, eggs\\; bread\\\\jam\\nmore".to_owned());
 
-                    LineToken::Task(task_block_line) => {
+    // descriptions leave out the padding of continuation lines of notes
+    assert_eq!(escape_ics_text(&note_text(&format!("from the store\n{:>11} on the corner", ""))),
+        "from the store\\non the corner".to_owned());
 
-                        // mark this line as previous task block seen
-                        journal.previous_task_block_line = num_of_lines_parsed;
+    let line = format!("DESCRIPTION:{}", (0..100).map(|_| "a").collect::<String>());
+    let folded = fold_ics_line(&line);
+    let lines: Vec<&str> = folded.split("\r\n").collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].len(), 75);
+    assert!(lines[1].starts_with(' '));
+    assert_eq!(lines[0].len() + lines[1].len() - 1, line.len());
 
-                        let current_task: &mut Task = match previous_state {
-                            ParseState::Task(ref mut task) => {
-                                task
-                            },
-                            _ => {
-                                let mut new_task: Task = Task::new(num_of_lines_parsed);
-                                new_task.source_file = Some(tracked_path.clone());
-                                previous_state = ParseState::Task(new_task);
+    // multi-octet characters are not split
+    let folded = fold_ics_line(&(0..40).map(|_| "é").collect::<String>());
+    for line in folded.split("\r\n") {
+        assert!(line.len() <= 75);
+    }
 
-                                // TODO: possible to refactor this in a better way?
-                                match previous_state {
-                                    ParseState::Task(ref mut task) => {
-                                        task
-                                    },
-                                    _ => unsafe { debug_unreachable!() }
-                                }
-                            }
-                        };
+    assert_eq!(stable_hash("buy milk"), stable_hash("buy milk"));
+    assert!(stable_hash("buy milk") != stable_hash("buy eggs"));
 
-                        match task_block_line {
-                            TaskBlock::Current => {
-                                current_task.current = true;
-                            },
-                            TaskBlock::Title(title) => {
-                                current_task.title = Some(title);
-                            },
-                            TaskBlock::Note(note) => {
-                                current_task.note = Some(note);
-                            },
-                            TaskBlock::Project(project) => {
+    // tasks with the same title in a file have distinct UIDs
+    let text = "task: run\n\
+        \n\
+        task: run\n\
+        \n\
+        task: stretch\n\
+        id: stretch\n\
+        \n\
+        task: stretch\n\
+        id: stretch\n";
 
-                                if project.len() > 0 {
-                                    current_task.project = Some(project);
-                                } else {
-                                    current_task.project = None;
-                                }
+    let journal = parse_test_journal("ics", text, NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 0, 0));
+    let uids = ics_uids(&journal);
 
-                            },
-                            TaskBlock::Created(created_at) => {
-                                let created_at: NaiveDateTime = created_at;
-                                current_task.created_at = Some(created_at);
-                            },
-                            TaskBlock::Done(done_at) => {
-                                let done_at: NaiveDateTime = done_at;
-                                current_task.done_at = Some(done_at);
-                            },
-                            TaskBlock::Chain(chain_at) => {
-                                let chain_at: NaiveDateTime = chain_at;
-                                match current_task.chains {
-                                    None => {
+    let distinct: HashSet<&String> = uids.values().collect();
+    assert_eq!(uids.len(), 4);
+    assert_eq!(distinct.len(), 4);
 
-                                        let mut tree = BTreeMap::new();
-                                        tree.insert(chain_at, true);
+    // the first of them keeps the UID of its file path and title
+    let (first_key, first_run) = journal.tasks.iter().find(|&(_, task)| task.task_block_range_start == 1).unwrap();
+    assert_eq!(uids.get(first_key), Some(&format!("{:016x}@gtdtxt", stable_hash(&ics_uid_key(&journal, first_run)))));
 
-                                        current_task.chains = Some(tree);
+    // DTSTAMP follows the journal's clock
+    let now = NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 5, 30);
+    let dtstamp = ics_dtstamp(&now);
+    assert_eq!(dtstamp, ics_dtstamp(&now));
+    assert_eq!(UTC.datetime_from_str(&dtstamp, "%Y%m%dT%H%M%SZ").unwrap().with_timezone(&Local).naive_local(), now);
+}
 
-                                    },
-                                    Some(ref mut tree) => {
-                                        tree.insert(chain_at, true);
-                                    }
-                                };
-                            },
-                            TaskBlock::Status(status) => {
+#[test]
+fn export_test() {
+
+    assert_eq!(quote_delimited_field("buy milk", ','), "buy milk".to_owned());
+    assert_eq!(quote_delimited_field("milk, eggs", ','), "\"milk, eggs\"".to_owned());
+    assert_eq!(quote_delimited_field("milk, eggs", '\t'), "milk, eggs".to_owned());
+    assert_eq!(quote_delimited_field("say \"hi\"", '\t'), "\"say \"\"hi\"\"\"".to_owned());
+    assert_eq!(quote_delimited_field("line\nbreak", ','), "\"line\nbreak\"".to_owned());
+
+    assert_eq!(format_iso8601_duration(0), "PT0S".to_owned());
+    assert_eq!(format_iso8601_duration(5400), "PT1H30M".to_owned());
+    assert_eq!(format_iso8601_duration(90000), "P1DT1H".to_owned());
+    assert_eq!(format_iso8601_duration(86400), "P1D".to_owned());
+
+    match parse_only(|i| parse_columns(i), "title, project,due ,tags".as_bytes()) {
+        Ok(columns) => {
+            let columns: Vec<&str> = columns.iter().map(|x| export_column_name(x)).collect();
+            assert_eq!(columns, vec!["title", "project", "due", "tags"]);
+        },
+        Err(_) => {
+            assert!(false);
+        }
+    }
 
-                                current_task.status = Some(status);
-                            },
-                            TaskBlock::Due(due_at) => {
-                                let due_at: NaiveDateTime = due_at;
-                                current_task.due_at = Some(due_at);
-                            },
-                            TaskBlock::Defer(defer) => {
-                                current_task.defer = Some(defer);
-                            },
-                            TaskBlock::Contexts(contexts) => {
+    assert!(parse_only(|i| parse_columns(i), "title, unknown".as_bytes()).is_err());
 
-                                if contexts.len() > 0 {
-                                    current_task.contexts = Some(contexts);
-                                } else {
-                                    current_task.contexts = None;
-                                }
-                            },
-                            TaskBlock::Tags(tags) => {
+    let text = "task: buy milk\n\
+        notes: from the store\n\
+        \ton the corner\n";
 
-                                if tags.len() > 0 {
-                                    current_task.tags = Some(tags);
-                                } else {
-                                    current_task.tags = None;
-                                }
-                            },
-                            TaskBlock::Time(time) => {
-                                current_task.time += time;
-                            },
-                            TaskBlock::ID(_id) => {
-                                // println!("id: '{}'", id);
-                                // TODO: complete
-                            },
-                            TaskBlock::Priority(priority) => {
-                                current_task.priority = priority
-                            },
-                            TaskBlock::Flag(flag) => {
-                                current_task.flag = flag;
-                            }
-                        };
+    let journal = parse_test_journal("export", text, NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 0, 0));
+    let task = journal.tasks.values().next().unwrap();
 
-                    },
+    let note = task_field_string(&journal, task, &TaskField::Note, ", ");
+    assert_eq!(note, "from the store\non the corner".to_owned());
+    assert_eq!(quote_delimited_field(&note, ','), "\"from the store\non the corner\"".to_owned());
+}
 
-                    LineToken::Directive(directive_line) => {
+/* printers */
 
-                        if let ParseState::Task(task) = previous_state {
-                            journal.add_task(task, &directive_switch);
-                        }
+fn print_vector_of_tasks(journal: &GTD, inbox: &[u64]) -> u64 {
+
+    let mut print_line: bool = false;
+    let mut num_displayed = 0;
+
+    for task_id in inbox {
+
+        if print_line {
+            println!("");
+        }
+
+        let task: &Task = journal.tasks.get(task_id).unwrap();
+
+        print_task(journal, task);
+        num_displayed += 1;
+
+        if !print_line {
+            print_line = true;
+        }
+
+    }
+
+    num_displayed
+}
+
+fn print_task(journal: &GTD, task: &Task) {
+    _print_task(journal, task, true);
+}
+
+// compact output of a task for the next subcommand
+fn print_next_action(journal: &GTD, rank: usize, task: &Task) {
+
+    let title = match task.title {
+        None => unsafe { debug_unreachable!() },
+        Some(ref title) => title
+    };
+
+    println!("{:>4} {}", format!("{}.", rank).bold().blue(), title);
+
+    let mut details: Vec<String> = Vec::new();
+
+    if let Some(ref due_at) = task.due_at {
+
+        match relative_time(due_at.timestamp(), journal.now.timestamp()) {
+            RelativeTime::Future(_, rel_time) => {
+                details.push(format!("{}", format!("due {}", rel_time).bold().yellow()));
+            },
+            RelativeTime::Now(_, rel_time) |
+            RelativeTime::Past(_, rel_time) => {
+                details.push(format!("{}", format!("overdue {}", rel_time).bold().red()));
+            }
+        }
+    }
+
+    if task.flag {
+        details.push(format!("{}", "flagged".bold().yellow()));
+    }
+
+    if task.priority != 0 {
+        details.push(format!("priority {}", task.priority));
+    }
+
+    if let Some(ref contexts) = task.contexts {
+        details.push(format!("@{}", contexts.join(", @")));
+    }
+
+    if let Some(ref project_path) = task.project {
+        details.push(project_path.join(" / "));
+    }
+
+    if details.len() > 0 {
+        println!("{:>4} {}", "", details.join("; "));
+    }
+}
+
+// this function outputs a given task with the option of validating the task title requirement
+#[allow(cyclomatic_complexity)]
+fn _print_task(journal: &GTD, task: &Task, require_title: bool) {
+
+    if task.current {
+        println!("{:>11} ",
+            "CURRENT".bold().purple().italic()
+        );
+    }
+
+    if task.flag && !journal.show_only_flagged {
+            println!("{:>11} ",
+                "Flagged".bold().yellow()
+            );
+    }
+
+    match task.title {
+        None => {
+
+            if require_title {
+                println!("Missing task title (i.e. `task: <title>`) in task block found {}",
+                    task.debug_range_string()
+                );
+                println!("Captured:");
+                _print_task(journal, task, false);
+                process::exit(1);
+            }
+
+        },
+
+        Some(ref title) => {
+            println!("{:>11} {}", "Task:".blue().bold(), highlight_matches(journal, title));
+        }
+    }
+
+    match task.status {
+        None => {},
+        Some(ref status) => {
+            let status_string = status.string();
+            println!("{:>11} {}", "Status:".bold().blue(), status_string);
+        }
+    }
+
+    match task.created_at {
+        None => {},
+        Some(ref created_at) => {
+
+            let rel_time = relative_time(created_at.timestamp(), journal.now.timestamp());
+
+            let rel_time = match rel_time {
+                RelativeTime::Now(_, rel_time) => {
+                    format!("({})", rel_time)
+                },
+                RelativeTime::Past(_, rel_time) => {
+                    format!("({})", rel_time)
+                },
+                RelativeTime::Future(_, rel_time) => {
+                    format!("({})", rel_time)
+                }
+            };
+
+            println!("{:>11} {} {}",
+                "Added at:".bold().blue(),
+                created_at.format("%B %-d, %Y %-l:%M %p"),
+                rel_time
+            );
+        }
+    }
+
+    match task.done_at {
+        None => {},
+        Some(ref done_at) => {
+
+            let rel_time = relative_time(done_at.timestamp(), journal.now.timestamp());
+
+            let rel_time = match rel_time {
+                RelativeTime::Now(_, rel_time) => {
+                    format!("({})", rel_time)
+                },
+                RelativeTime::Past(_, rel_time) => {
+                    format!("({})", rel_time)
+                },
+                RelativeTime::Future(_, rel_time) => {
+                    format!("({})", rel_time)
+                }
+            };
+
+            println!("{:>11} {} {}",
+                "Done at:".bold().blue(),
+                done_at.format("%B %-d, %Y %-l:%M %p"),
+                rel_time
+            );
+        }
+    }
+
+    match task.defer {
+        None => {},
+        Some(ref defer) => {
+
+            match *defer {
+                Defer::Forever => {
+                    println!("{:>11} {}",
+                        "Defer till:".bold().blue(),
+                        "Forever".bold().green()
+                    );
+                },
+                Defer::Until(defer_till) => {
+
+                    let rel_time = relative_time(defer_till.timestamp(), journal.now.timestamp());
+
+                    let rel_time = match rel_time {
+                        RelativeTime::Now(_, rel_time) => {
+                            let rel_time = format!("({})", rel_time);
+                            rel_time.red()
+                        },
+                        RelativeTime::Past(_, rel_time) => {
+                            let rel_time = format!("({})", rel_time);
+                            rel_time.bold().red()
+                        },
+                        RelativeTime::Future(_, rel_time) => {
+                            let rel_time = format!("({})", rel_time);
+                            rel_time.bold().green()
+                        }
+                    };
+
+                    println!("{:>11} {} {}",
+                        "Defer till:".bold().blue(),
+                        defer_till.format("%B %-d, %Y %-l:%M %p"),
+                        rel_time
+                    );
+                }
+            }
+
+
+        }
+    }
+
+    match task.due_at {
+        None => {},
+        Some(ref due_at) => {
+            let rel_time = relative_time(due_at.timestamp(), journal.now.timestamp());
+
+            let rel_time = match rel_time {
+                RelativeTime::Now(_, rel_time) => {
+                    let rel_time = format!("({})", rel_time);
+                    rel_time.red()
+                },
+                RelativeTime::Past(_, rel_time) => {
+                    let rel_time = format!("({})", rel_time);
+                    rel_time.bold().red()
+                },
+                RelativeTime::Future(_, rel_time) => {
+                    let rel_time = format!("({})", rel_time);
+                    rel_time.bold().green()
+                }
+            };
+
+            println!("{:>11} {} {}",
+                "Due at:".bold().blue(),
+                due_at.format("%B %-d, %Y %-l:%M %p"),
+                rel_time
+            );
+        }
+    }
+
+    match task.source_file {
+        None => unsafe { debug_unreachable!() },
+        Some(ref path) => {
+
+            let path = match Path::new(path).strip_prefix(&journal.base_root) {
+                Err(_) => {
+                    // TODO: remove
+                    // format!("{}", path)
+                    path.to_owned()
+                },
+                Ok(path) => {
+                    format!("./{}", path.display())
+                }
+            };
+
+            if journal.show_line_num_with_file_location {
+                println!("{:>11} {}:{}",
+                    "File:".bold().blue(),
+                    path,
+                    task.task_block_range_start
+                );
+            } else {
+                println!("{:>11} {}",
+                    "File:".bold().blue(),
+                    path
+                );
+            }
+
+        }
+    };
+
+    if task.task_block_range_start != task.task_block_range_end {
+        println!("{:>11} Lines {} to {}",
+            "Located:".bold().blue(),
+            task.task_block_range_start,
+            task.task_block_range_end
+        );
+    } else {
+        println!("{:>11} Line {}",
+            "Located:".bold().blue(),
+            task.task_block_range_start
+        );
+    }
+
+    match task.tags {
+        None => {},
+        Some(ref tags) => {
+            println!("{:>11} {}",
+                "Tags:".bold().blue(),
+                highlight_metadata(journal, tags).join(", ")
+            );
+        }
+    }
+
+    match task.contexts {
+        None => {},
+        Some(ref contexts) => {
+            println!("{:>11} {}",
+                "Contexts:".bold().blue(),
+                highlight_metadata(journal, contexts).join(", ")
+            );
+        }
+    }
+
+    match task.project {
+        None => {},
+        Some(ref project_path) => {
+            println!("{:>11} {}",
+                "Project:".bold().blue(),
+                highlight_project_path(journal, project_path)
+            );
+        }
+    }
+
+    if task.time > 0 {
+        println!("{:>11} {}",
+            "Time spent:".bold().blue(),
+            Timerange::new(task.time).print(2)
+        );
+
+    }
+
+    if task.has_chain() {
+        let chain_at: NaiveDateTime = task.get_chain();
+
+        let rel_time = relative_time(chain_at.timestamp(), journal.now.timestamp());
+
+        let rel_time = match rel_time {
+            RelativeTime::Now(_, rel_time) => {
+                let rel_time = format!("({})", rel_time);
+                rel_time.red()
+            },
+            RelativeTime::Past(_, rel_time) => {
+                let rel_time = format!("({})", rel_time);
+                rel_time.bold().red()
+            },
+            RelativeTime::Future(_, rel_time) => {
+                let rel_time = format!("({})", rel_time);
+                rel_time.bold().green()
+            }
+        };
+
+        println!("{:>11} {} {}",
+            "Last chain:".bold().blue(),
+            chain_at.format("%B %-d, %Y %-l:%M %p"),
+            rel_time
+        );
+    }
+
+    if task.priority != 0 {
+        println!("{:>11} {}", "Priority:".bold().blue(), task.priority);
+    }
+
+    match task.note {
+        None => {},
+        Some(ref note) => {
+
+            let note = if journal.hide_notes {
+                format!("{}", "(hidden)".red())
+            } else {
+                highlight_matches(journal, note)
+            };
+
+            println!("{:>11} {}",
+                "Notes:".bold().blue(),
+                note
+            );
+        }
+    }
+
+}
+
+// highlight any text that matches the search given by --grep
+fn highlight_matches(journal: &GTD, text: &str) -> String {
+
+    let regex = match journal.filter_grep {
+        None => {
+            return text.to_owned();
+        },
+        Some(ref regex) => regex
+    };
+
+    let mut highlighted = String::new();
+    let mut last_end = 0;
+
+    for (start, end) in regex.find_iter(text) {
+
+        // skip empty matches
+        if start == end {
+            continue;
+        }
+
+        highlighted.push_str(&text[last_end..start]);
+        highlighted.push_str(&format!("{}", text[start..end].black().on_yellow()));
+        last_end = end;
+    }
+
+    highlighted.push_str(&text[last_end..]);
+
+    return highlighted;
+}
+
+fn highlight_metadata(journal: &GTD, items: &[String]) -> Vec<String> {
+
+    if !journal.grep_metadata {
+        return items.to_vec();
+    }
+
+    items.iter().map(|item| highlight_matches(journal, item)).collect()
+}
+
+// the project path is searched as joined by `/` (see GTD::grep_task), so a match may span segments.
+fn highlight_project_path(journal: &GTD, project_path: &[String]) -> String {
+
+    if !journal.grep_metadata {
+        return project_path.join(" / ");
+    }
+
+    highlight_matches(journal, &project_path.join("/")).replace("/", " / ")
+}
+
+#[test]
+fn grep_test() {
+
+    let text = "task: Buy milk\n\
+        tags: grocery\n\
+        \n\
+        task: call mom\n\
+        notes: ask about the milk recipe\n\
+        \n\
+        task: file taxes\n\
+        project: home / finances\n";
+
+    let mut journal = parse_test_journal("grep", text, NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 0, 0));
+
+    let mut task_ids: Vec<u64> = journal.tasks.keys().cloned().collect();
+    task_ids.sort();
+
+    let matching = |journal: &GTD| -> Vec<String> {
+        let found: Vec<u64> = task_ids.iter().cloned().filter(|task_id| {
+            journal.grep_task(journal.tasks.get(task_id).unwrap())
+        }).collect();
+        test_task_titles(journal, &found)
+    };
+
+    // no search
+    assert_eq!(matching(&journal).len(), 3);
+    assert_eq!(highlight_matches(&journal, "Buy milk"), "Buy milk".to_owned());
+
+    // titles and notes; case-sensitive
+    journal.filter_grep = Some(Regex::new("milk").unwrap());
+    assert_eq!(matching(&journal), vec!["Buy milk".to_owned(), "call mom".to_owned()]);
+
+    journal.filter_grep = Some(Regex::new("buy").unwrap());
+    assert_eq!(matching(&journal), Vec::<String>::new());
+
+    // case-insensitive, as given by --ignore-case
+    journal.filter_grep = Some(Regex::new("(?i)buy").unwrap());
+    assert_eq!(matching(&journal), vec!["Buy milk".to_owned()]);
+    assert_eq!(highlight_matches(&journal, "Buy milk, buy bread"),
+        format!("{} milk, {} bread", "Buy".black().on_yellow(), "buy".black().on_yellow()));
+
+    // project path, tags, and contexts only with --grep-metadata
+    journal.filter_grep = Some(Regex::new("grocery|home/fin").unwrap());
+    assert_eq!(matching(&journal), Vec::<String>::new());
+    assert_eq!(highlight_metadata(&journal, &["grocery".to_owned()]), vec!["grocery".to_owned()]);
+    assert_eq!(highlight_project_path(&journal, &["home".to_owned(), "finances".to_owned()]),
+        "home / finances".to_owned());
+
+    journal.grep_metadata = true;
+    assert_eq!(matching(&journal), vec!["Buy milk".to_owned(), "file taxes".to_owned()]);
+    assert_eq!(highlight_metadata(&journal, &["grocery".to_owned()]),
+        vec![format!("{}", "grocery".black().on_yellow())]);
+
+    // matches spanning segments of the project path
+    assert_eq!(highlight_project_path(&journal, &["home".to_owned(), "finances".to_owned()]),
+        format!("{}ances", "home / fin".black().on_yellow()));
+
+    // no match
+    journal.filter_grep = Some(Regex::new("(?i)oranges").unwrap());
+    assert_eq!(matching(&journal), Vec::<String>::new());
+    assert_eq!(highlight_matches(&journal, "Buy milk"), "Buy milk".to_owned());
+}
+
+/* data structures */
+
+// index project path filters
+#[derive(Debug)]
+enum NodeType {
+    Node(Tree),
+    Leaf
+}
+type Tree = HashMap<String, NodeType>;
+
+// types
+type ProjectPath = Vec<String>;
+type Contexts = Vec<String>;
+type Tags = Vec<String>;
+type Priority = i64;
+type TimeLength = u64;
+
+#[repr(u32)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Status {
+    Done,
+    Incubate,
+    NotDone
+}
+
+impl Status {
+    fn string(&self) -> String {
+        match *self {
+            Status::Done => "Done".to_owned(),
+            Status::Incubate => "Incubate".to_owned(),
+            Status::NotDone => "Not Done".to_owned()
+        }
+    }
+}
+
+impl CLike for Status {
+    fn to_u32(&self) -> u32 {
+        let foo: Self = self.clone();
+        foo as u32
+    }
+
+    unsafe fn from_u32(v: u32) -> Status {
+        mem::transmute(v)
+    }
+}
+
+#[derive(Debug)]
+struct Task {
+
+    /* debug*/
+    task_block_range_start: u64,
+    task_block_range_end: u64,
+
+    /* props */
+    current: bool,
+    title: Option<String>, // required
+
+    note: Option<String>,
+    created_at: Option<NaiveDateTime>,
+    done_at: Option<NaiveDateTime>,
+    chains: Option<BTreeMap<NaiveDateTime, bool>>,
+    due_at: Option<NaiveDateTime>,
+    defer: Option<Defer>,
+    status: Option<Status>,
+    project: Option<ProjectPath>,
+    contexts: Option<Contexts>,
+    tags: Option<Tags>,
+    priority: Priority,
+    time: TimeLength,
+    // TODO: rename to flagged
+    flag: bool,
+    // vanity id (e.g. `id: buy-milk`)
+    id: Option<String>,
+
+    source_file: Option<String>
+}
+
+impl Task {
+
+    fn new(task_block_range_start: u64) -> Task {
+        Task {
+
+            task_block_range_start: task_block_range_start,
+            task_block_range_end: task_block_range_start,
+
+            /* props */
+            current: false,
+            title: None,
+            note: None,
+            created_at: None,
+            done_at: None,
+            chains: None,
+            due_at: None,
+            defer: None,
+            status: None,
+            project: None,
+            contexts: None,
+            tags: None,
+            priority: 0,
+            time: 0,
+            flag: false,
+            id: None,
+            source_file: None
+        }
+    }
+
+    fn is_done(&self) -> bool {
+
+        match self.status {
+            None => {},
+            Some(ref status) => {
+
+                if let Status::Done = *status {
+                    return true;
+                }
+
+            }
+        };
+
+        return false;
+    }
+
+    fn has_chain(&self) -> bool {
+
+        if self.chains.is_none() {
+            return false;
+        }
+
+        match self.chains {
+            None => unsafe { debug_unreachable!() },
+            Some(ref tree) => {
+                return tree.len() > 0;
+            }
+        }
+    }
+
+    fn get_chain(&self) -> NaiveDateTime {
+
+        match self.chains {
+            None => unsafe { debug_unreachable!() },
+            Some(ref tree) => {
+                // see: http://stackoverflow.com/a/33699340/412627
+                // let (key, _) = tree.iter().last().unwrap();
+                let (key, _) = tree.iter().next_back().unwrap();
+
+                return *key;
+            }
+        }
+    }
+
+    fn debug_range_string(&self) -> String {
+
+        if self.task_block_range_start == self.task_block_range_end {
+            return format!("on line {}", self.task_block_range_start);
+        }
+
+        return format!("between lines {} and {}",
+            self.task_block_range_start,
+            self.task_block_range_end
+        );
+    }
+}
+
+/* views */
+
+// sections of tasks that are displayed
+#[repr(u32)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Section {
+    Overdue,
+    Inbox,
+    Incubate,
+    Deferred,
+    Done
+}
+
+impl Section {
+    fn string(&self) -> String {
+        match *self {
+            Section::Overdue => "overdue".to_owned(),
+            Section::Inbox => "inbox".to_owned(),
+            Section::Incubate => "incubate".to_owned(),
+            Section::Deferred => "deferred".to_owned(),
+            Section::Done => "done".to_owned()
+        }
+    }
+
+    fn from_string(section: &str) -> Option<Section> {
+        match section.trim().to_lowercase().as_str() {
+            "overdue" => Some(Section::Overdue),
+            "inbox" => Some(Section::Inbox),
+            "incubate" | "incubated" | "someday" => Some(Section::Incubate),
+            "deferred" | "defer" => Some(Section::Deferred),
+            "done" | "completed" | "complete" | "finished" => Some(Section::Done),
+            _ => None
+        }
+    }
+}
+
+impl CLike for Section {
+    fn to_u32(&self) -> u32 {
+        let foo: Self = self.clone();
+        foo as u32
+    }
+
+    unsafe fn from_u32(v: u32) -> Section {
+        mem::transmute(v)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum SortKey {
+    // highest priority first
+    Priority,
+    // earliest first
+    Due,
+    Defer,
+    Created,
+    Done,
+    // alphabetical order
+    Title,
+    // least time spent first
+    Time,
+    // by file path, and then by line number
+    File
+}
+
+impl SortKey {
+    fn string(&self) -> String {
+        match *self {
+            SortKey::Priority => "priority".to_owned(),
+            SortKey::Due => "due".to_owned(),
+            SortKey::Defer => "defer".to_owned(),
+            SortKey::Created => "created".to_owned(),
+            SortKey::Done => "done".to_owned(),
+            SortKey::Title => "title".to_owned(),
+            SortKey::Time => "time".to_owned(),
+            SortKey::File => "file".to_owned()
+        }
+    }
+
+    fn from_string(sort_key: &str) -> Option<SortKey> {
+        match sort_key.trim().to_lowercase().as_str() {
+            "priority" => Some(SortKey::Priority),
+            "due" => Some(SortKey::Due),
+            "defer" | "deferred" => Some(SortKey::Defer),
+            "created" | "date" => Some(SortKey::Created),
+            "done" | "completed" => Some(SortKey::Done),
+            "title" | "task" => Some(SortKey::Title),
+            "time" => Some(SortKey::Time),
+            "file" => Some(SortKey::File),
+            _ => None
+        }
+    }
+}
+
+// sort key, and whether the order is reversed
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct SortOrder(SortKey, bool);
+
+impl SortOrder {
+    fn string(&self) -> String {
+        let SortOrder(ref sort_key, reverse) = *self;
+
+        if reverse {
+            return format!("-{}", sort_key.string());
+        }
+
+        return sort_key.string();
+    }
+}
+
+// chain of sort orders applied to a section, or to every section
+#[derive(Debug, Clone)]
+struct SortBy {
+    section: Option<Section>,
+    orders: Vec<SortOrder>
+}
+
+impl SortBy {
+    fn string(&self) -> String {
+
+        let orders: Vec<String> = self.orders.iter().map(|order| order.string()).collect();
+        let orders = orders.join(", ");
+
+        match self.section {
+            None => orders,
+            Some(ref section) => format!("{}: {}", section.string(), orders)
+        }
+    }
+}
+
+// format: [<section>:] <sort key>, -<sort key>, ...
+fn parse_sort_by(sort_by: &str) -> Result<SortBy, String> {
+
+    let (section, sort_keys) = match sort_by.find(':') {
+        None => (None, sort_by),
+        Some(index) => {
+
+            let section = &sort_by[..index];
+
+            let section = match Section::from_string(section) {
+                None | Some(Section::Incubate) => {
+                    return Err(format!("Invalid section `{}`. \
+                        Expected any of: overdue, inbox, deferred, done", section.trim()));
+                },
+                Some(section) => section
+            };
+
+            (Some(section), &sort_by[(index + 1)..])
+        }
+    };
+
+    let mut orders = Vec::new();
+
+    for sort_key in sort_keys.split(',').map(|x| x.trim()).filter(|x| x.len() > 0) {
+
+        let (sort_key, reverse) = if sort_key.starts_with('-') {
+            (&sort_key[1..], true)
+        } else {
+            (sort_key, false)
+        };
+
+        match SortKey::from_string(sort_key) {
+            None => {
+                return Err(format!("Invalid sort key `{}`. \
+                    Expected any of: priority, due, defer, created, done, title, time, file", sort_key));
+            },
+            Some(sort_key) => {
+                orders.push(SortOrder(sort_key, reverse));
+            }
+        }
+    }
+
+    if orders.len() <= 0 {
+        return Err("Expected at least one sort key.".to_owned());
+    }
+
+    return Ok(SortBy {
+        section: section,
+        orders: orders
+    });
+}
+
+// tasks missing a sorted attribute are placed last
+fn compare_optional<T: Ord>(a: Option<T>, b: Option<T>, reverse: bool) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => {
+            if reverse {
+                b.cmp(&a)
+            } else {
+                a.cmp(&b)
+            }
+        }
+    }
+}
+
+fn defer_sort_value(task: &Task) -> Option<(u8, i64)> {
+    match task.defer {
+        None => None,
+        Some(Defer::Until(ref defer_till)) => Some((0, defer_till.timestamp())),
+        Some(Defer::Forever) => Some((1, 0))
+    }
+}
+
+fn compare_tasks(sort_orders: &[SortOrder], a: &Task, b: &Task) -> Ordering {
+
+    for sort_order in sort_orders {
+
+        let SortOrder(ref sort_key, reverse) = *sort_order;
+
+        let ordering = match *sort_key {
+            SortKey::Priority => {
+                compare_optional(Some(-a.priority), Some(-b.priority), reverse)
+            },
+            SortKey::Due => {
+                compare_optional(a.due_at.map(|x| x.timestamp()), b.due_at.map(|x| x.timestamp()), reverse)
+            },
+            SortKey::Defer => {
+                compare_optional(defer_sort_value(a), defer_sort_value(b), reverse)
+            },
+            SortKey::Created => {
+                compare_optional(a.created_at.map(|x| x.timestamp()), b.created_at.map(|x| x.timestamp()), reverse)
+            },
+            SortKey::Done => {
+                compare_optional(a.done_at.map(|x| x.timestamp()), b.done_at.map(|x| x.timestamp()), reverse)
+            },
+            SortKey::Title => {
+                compare_optional(
+                    a.title.as_ref().map(|x| x.to_lowercase()),
+                    b.title.as_ref().map(|x| x.to_lowercase()),
+                    reverse)
+            },
+            SortKey::Time => {
+                compare_optional(Some(a.time), Some(b.time), reverse)
+            },
+            SortKey::File => {
+                compare_optional(
+                    a.source_file.as_ref().map(|x| (x.clone(), a.task_block_range_start)),
+                    b.source_file.as_ref().map(|x| (x.clone(), b.task_block_range_start)),
+                    reverse)
+            }
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    return Ordering::Equal;
+}
+
+#[test]
+fn sort_by_test() {
+
+    match parse_sort_by("inbox: -due, title") {
+        Ok(sort_by) => {
+            assert_eq!(sort_by.string(), "inbox: -due, title".to_owned());
+        },
+        Err(_) => {
+            assert!(false);
+        }
+    }
+
+    assert!(parse_sort_by("priority, unknown").is_err());
+    assert!(parse_sort_by("incubate: priority").is_err());
+    assert!(parse_sort_by(" , ").is_err());
+
+    let mut a = Task::new(1);
+    a.priority = 1;
+    a.due_at = Some(NaiveDate::from_ymd(2016, 10, 21).and_hms(17, 0, 0));
+
+    let mut b = Task::new(5);
+    b.priority = 1;
+
+    let mut c = Task::new(9);
+    c.priority = 5;
+    c.due_at = Some(NaiveDate::from_ymd(2016, 10, 20).and_hms(17, 0, 0));
+
+    let by_priority = vec![SortOrder(SortKey::Priority, false)];
+    assert_eq!(compare_tasks(&by_priority, &a, &c), Ordering::Greater);
+    assert_eq!(compare_tasks(&by_priority, &a, &b), Ordering::Equal);
+
+    // tasks without a due datetime are last regardless of order
+    let by_due = vec![SortOrder(SortKey::Due, false)];
+    assert_eq!(compare_tasks(&by_due, &c, &a), Ordering::Less);
+    assert_eq!(compare_tasks(&by_due, &b, &a), Ordering::Greater);
+
+    let by_due_reversed = vec![SortOrder(SortKey::Due, true)];
+    assert_eq!(compare_tasks(&by_due_reversed, &c, &a), Ordering::Greater);
+    assert_eq!(compare_tasks(&by_due_reversed, &b, &a), Ordering::Greater);
+
+    let chain = vec![SortOrder(SortKey::Priority, true), SortOrder(SortKey::Due, false)];
+    assert_eq!(compare_tasks(&chain, &a, &b), Ordering::Less);
+}
+
+
+// a named set of filters and display options declared in a gtdtxt file
+#[derive(Debug, Clone)]
+struct View {
+    name: String,
+    filter_where: Option<WhereFilterTree>,
+    sections: Option<EnumSet<Section>>,
+    sort: Vec<SortBy>
+}
+
+impl View {
+    fn new(name: String) -> View {
+        View {
+            name: name,
+            filter_where: None,
+            sections: None,
+            sort: Vec::new()
+        }
+    }
+
+    // repeated `where:` lines shall all be satisfied
+    fn add_where_filter_tree(&mut self, filter_tree: WhereFilterTree) {
+        self.filter_where = match self.filter_where.take() {
+            None => Some(filter_tree),
+            Some(tree) => Some(FilterTree::Intersection(Box::new(tree), Box::new(filter_tree)))
+        };
+    }
+
+    fn set_sections(&mut self, sections: &[String]) -> Result<(), String> {
+
+        let mut set = EnumSet::new();
+
+        for section in sections {
+            match Section::from_string(section) {
+                None => {
+                    return Err(format!("Invalid section `{}` in `show:`. \
+                        Expected any of: overdue, inbox, incubate, deferred, done", section));
+                },
+                Some(section) => {
+                    set.insert(section);
+                }
+            }
+        }
+
+        self.sections = Some(set);
+        return Ok(());
+    }
+
+    fn add_sort_by(&mut self, sort_by: &str) -> Result<(), String> {
+        match parse_sort_by(sort_by) {
+            Ok(sort_by) => {
+                self.sort.push(sort_by);
+                return Ok(());
+            },
+            Err(reason) => {
+                return Err(format!("Invalid `sort:`. {}", reason));
+            }
+        }
+    }
+
+    fn sections_string(&self) -> Option<String> {
+        match self.sections {
+            None => None,
+            Some(ref sections) => {
+                let sections: Vec<String> = sections.iter().map(|section| section.string()).collect();
+                Some(sections.join(", "))
+            }
+        }
+    }
+
+    fn sort_string(&self) -> Option<String> {
+
+        if self.sort.len() <= 0 {
+            return None;
+        }
+
+        let sort: Vec<String> = self.sort.iter().map(|sort_by| sort_by.string()).collect();
+        return Some(sort.join("; "));
+    }
+}
+
+#[derive(Debug)]
+struct GTD {
+
+    /* debug */
+    // the line of the last task block line parsed
+    previous_task_block_line: u64,
+    // how parse errors are reported
+    error_format: ErrorFormat,
+    // skip malformed lines and blocks with a warning, instead of exiting
+    lenient: bool,
+    num_of_warnings: u64,
+    // warnings are collected instead of printed if set (e.g. by the language server)
+    diagnostics: Option<Vec<Diagnostic>>,
+    // text of documents opened in an editor, by canonical path; parsed instead of the files on disk
+    documents: HashMap<String, String>,
+
+    /* clock */
+    // point in time at which tasks are evaluated (e.g. overdue, deferred, relative times)
+    now: NaiveDateTime,
+
+    /* flag/switches */
+    hide_notes: bool,
+    hide_flagged: bool,
+    show_only_flagged: bool,
+    show_done: bool,
+    show_incubate: bool,
+    show_deferred: bool,
+    hide_overdue: bool,
+    hide_inbox: bool,
+    hide_nonproject_tasks: bool,
+    hide_incomplete: bool,
+    project_only_filter: Tree,
+    project_whitelist: Tree,
+    show_line_num_with_file_location: bool,
+    sort_overdue_by_priority: bool,
+    // sort orders given by --sort-by and views; the last one given for a section is used
+    sort_by: Vec<SortBy>,
+    filter_by_only_tags: bool,
+    filter_by_only_contexts: bool,
+    // tasks missing the attribute are not hidden by --only-with-tag, --only-with-context,
+    // or --only-with-project
+    include_untagged: bool,
+    include_no_context: bool,
+    include_no_project: bool,
+    only_untagged: bool,
+    only_no_context: bool,
+    due_within: Duration,
+    filter_priority: Option<PriorityFilterTree>,
+    filter_where: Option<WhereFilterTree>,
+    hide_tasks_by_default: bool,
+    show_overdue: bool,
+    show_incomplete: bool,
+    show_flagged: bool,
+    show_nonproject_tasks: bool,
+    show_project_tasks: bool,
+    filter_by_include_tags: bool,
+    include_tags: HashSet<String>,
+    filter_by_include_contexts: bool,
+    include_contexts: HashSet<String>,
+    filter_by_hide_tags: bool,
+    hide_tags: HashSet<String>,
+    filter_by_hide_contexts: bool,
+    hide_contexts: HashSet<String>,
+    project_blacklist: Tree,
+    // glob patterns of files
+    only_in_files: Vec<String>,
+    hide_in_files: Vec<String>,
+    filter_grep: Option<Regex>,
+    // also search project path, tags, and contexts
+    grep_metadata: bool,
+
+    /* data */
+
+    current_task: Option<u64>,
+
+    // views declared in gtdtxt files in order of appearance
+    views: Vec<LineLocation<View>>,
+
+    base_root: String,
+
+    // track files opened
+    opened_files: HashSet<String>,
+
+    // This tracks the order of the inserted path to file_stats. Used for output.
+    file_stats_stack: Vec<String>,
+    // path to file -> FileStats
+    file_stats: HashMap<String, FileStats>,
+
+    pulse: HashMap<i64, Vec<u64>>,
+
+    only_tags: HashSet<String>,
+
+    only_contexts: HashSet<String>,
+
+    // lookup table for tasks
+    tasks: HashMap<u64, Task>,
+
+    // sections below are vectors of task ids in order of appearance until they're sorted
+    // (see sort_tasks)
+
+    // this contains any tasks that are overdue
+    overdue: Vec<u64>,
+
+    // inbox contain any tasks that do not have a project
+    inbox: Vec<u64>,
+
+    // this contains any tasks that are inactive
+    deferred: Vec<u64>,
+
+    // this contains any tasks that are compelted
+    done: Vec<u64>
+}
+
+impl GTD {
+    fn new(base_root: String) -> GTD {
+
+        GTD {
+
+            /* error output */
+            previous_task_block_line: 0,
+            error_format: ErrorFormat::Human,
+            lenient: false,
+            num_of_warnings: 0,
+            diagnostics: None,
+            documents: HashMap::new(),
+
+            /* clock */
+            now: Local::now().naive_local(),
+
+            /* options */
+            hide_notes: false,
+            hide_flagged: false,
+            show_only_flagged: false,
+            show_done: false,
+            show_incubate: false,
+            show_deferred: false,
+            hide_overdue: false,
+            hide_inbox: false,
+            hide_nonproject_tasks: false,
+            hide_incomplete: false,
+            project_only_filter: HashMap::new(),
+            project_whitelist: HashMap::new(),
+            show_line_num_with_file_location: false,
+            sort_overdue_by_priority: false,
+            sort_by: Vec::new(),
+            filter_by_only_tags: false,
+            filter_by_only_contexts: false,
+            include_untagged: false,
+            include_no_context: false,
+            include_no_project: false,
+            only_untagged: false,
+            only_no_context: false,
+            due_within: Duration::seconds(0),
+            filter_priority: None,
+            filter_where: None,
+            hide_tasks_by_default: false,
+            show_overdue: false,
+            show_incomplete: false,
+            show_flagged: false,
+            show_nonproject_tasks: false,
+            show_project_tasks: false,
+            filter_by_include_tags: false,
+            include_tags: HashSet::new(),
+            filter_by_include_contexts: false,
+            include_contexts: HashSet::new(),
+            filter_by_hide_tags: false,
+            hide_tags: HashSet::new(),
+            filter_by_hide_contexts: false,
+            hide_contexts: HashSet::new(),
+            project_blacklist: HashMap::new(),
+            only_in_files: Vec::new(),
+            hide_in_files: Vec::new(),
+            filter_grep: None,
+            grep_metadata: false,
+
+            /* data */
+
+            current_task: None,
+
+            views: Vec::new(),
+
+            base_root: base_root,
+            opened_files: HashSet::new(),
+
+            file_stats_stack: Vec::new(),
+            file_stats: HashMap::new(),
+
+            pulse: HashMap::new(),
+
+            only_tags: HashSet::new(),
+            only_contexts: HashSet::new(),
+
+            tasks: HashMap::new(),
+            inbox: Vec::new(),
+            done: Vec::new(),
+            deferred: Vec::new(),
+            overdue: Vec::new()
+        }
+    }
+
+    fn add_tag_only_filters(&mut self, tags: Vec<String>) {
+        for tag in tags {
+            self.only_tags.insert(tag);
+        }
+    }
+
+    fn add_tag_include_filters(&mut self, tags: Vec<String>) {
+        for tag in tags {
+            self.include_tags.insert(tag);
+        }
+    }
+
+    fn add_tag_hide_filters(&mut self, tags: Vec<String>) {
+        for tag in tags {
+            self.hide_tags.insert(tag);
+        }
+    }
+
+    fn have_only_tags(&mut self, tags: &[String]) -> bool {
+        for tag in tags {
+            if self.only_tags.contains(tag) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    fn have_include_tags(&mut self, tags: &[String]) -> bool {
+        for tag in tags {
+            if self.include_tags.contains(tag) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    fn have_hide_tags(&mut self, tags: &[String]) -> bool {
+        for tag in tags {
+            if self.hide_tags.contains(tag) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    fn add_context_only_filters(&mut self, contexts: Vec<String>) {
+        for context in contexts {
+            self.only_contexts.insert(context);
+        }
+    }
+
+    fn add_context_include_filters(&mut self, contexts: Vec<String>) {
+        for context in contexts {
+            self.include_contexts.insert(context);
+        }
+    }
+
+    fn add_context_hide_filters(&mut self, contexts: Vec<String>) {
+        for context in contexts {
+            self.hide_contexts.insert(context);
+        }
+    }
+
+    fn have_only_contexts(&mut self, contexts: &[String]) -> bool {
+        for context in contexts {
+            if self.only_contexts.contains(context) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    fn have_include_contexts(&mut self, contexts: &[String]) -> bool {
+        for context in contexts {
+            if self.include_contexts.contains(context) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    fn have_hide_contexts(&mut self, contexts: &[String]) -> bool {
+        for context in contexts {
+            if self.hide_contexts.contains(context) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    fn add_project_only_filter(&mut self, path: &mut Vec<String>) {
+        traverse(path, &mut self.project_only_filter);
+    }
+
+    fn add_project_whitelist(&mut self, path: &mut Vec<String>) {
+        traverse(path, &mut self.project_whitelist);
+    }
+
+    fn add_project_blacklist(&mut self, path: &mut Vec<String>) {
+        traverse(path, &mut self.project_blacklist);
+    }
+
+    fn has_project_only_filters(&mut self) -> bool {
+        self.project_only_filter.len() > 0
+    }
+
+    fn has_project_whitelist(&mut self) -> bool {
+        self.project_whitelist.len() > 0
+    }
+
+    fn has_project_blacklist(&mut self) -> bool {
+        self.project_blacklist.len() > 0
+    }
+
+    fn should_only_filter_project(&mut self, path: &[String]) -> bool {
+        return path_satisfies_tree(&(self.project_only_filter), path);
+    }
+
+    fn should_whitelist_project(&mut self, path: &[String]) -> bool {
+        return path_satisfies_tree(&(self.project_whitelist), path);
+    }
+
+    fn should_blacklist_project(&mut self, path: &[String]) -> bool {
+        return path_satisfies_tree(&(self.project_blacklist), path);
+    }
+
+    // test if the file of the task matches any of the given glob patterns.
+    // patterns are matched against the path relative to the working directory, and the absolute path.
+    fn is_task_in_files(&self, task: &Task, patterns: &[String]) -> bool {
+
+        let source_file = match task.source_file {
+            None => {
+                return false;
+            },
+            Some(ref source_file) => source_file
+        };
+
+        let relative_path = match Path::new(source_file).strip_prefix(&self.base_root) {
+            Err(_) => source_file.clone(),
+            Ok(path) => format!("{}", path.display())
+        };
+
+        for pattern in patterns {
+
+            let pattern = pattern.trim_left_matches("./");
+
+            if glob_matches(pattern.as_bytes(), relative_path.as_bytes()) ||
+                glob_matches(pattern.as_bytes(), source_file.as_bytes()) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    // tasks shall satisfy both the given filter and any existing query filter
+    fn add_where_filter(&mut self, filter: WhereFilter) {
+        self.add_where_filter_tree(FilterTree::Leaf(filter));
+    }
+
+    fn add_where_filter_tree(&mut self, filter_tree: WhereFilterTree) {
+        self.filter_where = match self.filter_where.take() {
+            None => Some(filter_tree),
+            Some(tree) => Some(FilterTree::Intersection(Box::new(tree), Box::new(filter_tree)))
+        };
+    }
+
+    fn add_view(&mut self, view: LineLocation<View>) {
+
+        let existing_location: Option<String> = self.get_view(&view.as_ref().unwrap().name)
+            .map(|existing_view| existing_view.location());
+
+        if let Some(existing_location) = existing_location {
+
+            if self.lenient {
+                let LineLocation(path, line_number, view) = view;
+                let reason = format!("Found conflicting definitions of view `{}`. First defined at: {}",
+                    view.name, existing_location);
+                let diagnostic = Diagnostic::invalid_block(self, &path, line_number, reason);
+                self.add_warning(diagnostic);
+                return;
+            }
+
+            println!("Found conflicting definitions of view `{}`.", view.as_ref().unwrap().name);
+            println!("First defined at: {}", existing_location);
+            println!("Defined again at: {}", view.location());
+            process::exit(1);
+        }
+
+        self.views.push(view);
+    }
+
+    fn get_view(&self, name: &str) -> Option<&LineLocation<View>> {
+        self.views.iter().find(|view| {
+            view.as_ref().unwrap().name.to_lowercase() == name.trim().to_lowercase()
+        })
+    }
+
+    // options of the view are applied on top of any given flags / options
+    fn apply_view(&mut self, view: &View) {
+
+        if let Some(ref filter_tree) = view.filter_where {
+            self.add_where_filter_tree(filter_tree.clone());
+        }
+
+        // sections shown or hidden by flags (e.g. --show-done, --hide-overdue) remain so
+        if let Some(ref sections) = view.sections {
+            self.hide_overdue = self.hide_overdue || !sections.contains(&Section::Overdue);
+            self.hide_inbox = self.hide_inbox || !sections.contains(&Section::Inbox);
+            self.show_incubate = self.show_incubate || sections.contains(&Section::Incubate);
+            self.show_deferred = self.show_deferred || sections.contains(&Section::Deferred);
+            self.show_done = self.show_done || sections.contains(&Section::Done);
+        }
+
+        for sort_by in &view.sort {
+            self.sort_by.push(sort_by.clone());
+        }
+    }
+
+    fn grep_task(&self, task: &Task) -> bool {
+
+        let regex = match self.filter_grep {
+            None => {
+                return true;
+            },
+            Some(ref regex) => regex
+        };
+
+        if let Some(ref title) = task.title {
+            if regex.is_match(title) {
+                return true;
+            }
+        }
+
+        if let Some(ref note) = task.note {
+            if regex.is_match(note) {
+                return true;
+            }
+        }
+
+        if !self.grep_metadata {
+            return false;
+        }
+
+        if let Some(ref project_path) = task.project {
+            if regex.is_match(&project_path.join("/")) {
+                return true;
+            }
+        }
+
+        if let Some(ref tags) = task.tags {
+            if tags.iter().any(|tag| regex.is_match(tag)) {
+                return true;
+            }
+        }
+
+        if let Some(ref contexts) = task.contexts {
+            if contexts.iter().any(|context| regex.is_match(context)) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    // report a malformed line or block that is skipped in lenient mode
+    fn add_warning(&mut self, diagnostic: Diagnostic) {
+
+        self.num_of_warnings += 1;
+
+        match self.diagnostics {
+            None => {
+                diagnostic.print_warning(&self.error_format);
+            },
+            Some(ref mut diagnostics) => {
+                diagnostics.push(diagnostic);
+            }
+        };
+    }
+
+    // report an invalid task block that is skipped in lenient mode
+    fn add_task_warning(&mut self, task: &Task, reason: String) {
+
+        let path = task.source_file.clone().unwrap_or_else(String::new);
+        let diagnostic = Diagnostic::invalid_block(self, &path, task.task_block_range_start, reason);
+
+        self.add_warning(diagnostic);
+    }
+
+    fn add_task(&mut self, task: Task, directive_switch: &LocalDirectiveSwitches) {
+
+        // TODO: is this the best placement for this?
+        let mut task = task;
+        task.task_block_range_end = self.previous_task_block_line;
+        let task = task;
+
+        /* pass task through local scoped directive switches */
+
+        let mut task = task;
+        directive_switch.transform_task(&mut task, self);
+        let task = task;
+
+        if !directive_switch.pass_validation(&task, self) {
+            // invalid tasks are either reported as warnings (lenient mode) or gtdtxt exits
+            return;
+        }
+
+        /* pass task through global scoped directive switches */
+
+        // TODO: complete
+        // self.directive_switches.transform_task(&mut task, self);
+
+        // task title is required attribute
+        if task.title.is_none() {
+
+            if self.lenient {
+                let reason = "Missing task title (i.e. `task: <title>`) in task block".to_owned();
+                self.add_task_warning(&task, reason);
+                return;
+            }
+
+            println!("Missing task title (i.e. `task: <title>`) in task block found {}",
+                task.debug_range_string()
+            );
+            println!("Captured:");
+            _print_task(self, &task, false);
+            process::exit(1);
+        }
+
+        let new_id: u64 = self.next_task_id();
+
+        if task.current {
+
+            match self.current_task {
+                Some(first_task_id) => {
+
+                    if self.lenient {
+                        let location = task_file_location(self, self.tasks.get(&first_task_id).unwrap());
+                        let reason = format!("Found at least two current tasks; only one task can be marked as current. \
+                            First task found to be current: {}", location);
+                        self.add_task_warning(&task, reason);
+                        return;
+                    }
+
+                    println!("Found at least two current tasks.");
+                    println!("Only one task can be marked as current.");
+                    println!("");
+
+                    println!("First task found to be current:");
+                    let first_task: &Task = self.tasks.get(&first_task_id).unwrap();
+                    print_task(self, first_task);
+
+                    println!("");
+
+                    println!("Second task found to be current:");
+                    print_task(self, &task);
+
+                    process::exit(1);
+                },
+                None => {
+                    self.current_task = Some(new_id);
+                }
+            };
+        }
+
+        match task.done_at {
+            None => {},
+            Some(ref done_at) => {
+
+                if !task.is_done() {
+
+                    if self.lenient {
+                        let reason = "Task is incorrectly given a `done` datetime; \
+                            mayhaps you forgot to add: 'status: done'".to_owned();
+                        self.add_task_warning(&task, reason);
+
+                        if self.current_task == Some(new_id) {
+                            self.current_task = None;
+                        }
+
+                        return;
+                    }
+
+                    println!("In file: {}", task.source_file.as_ref().unwrap());
+                    println!("Task is incorrectly given a `done` datetime found at {}",
+                        task.debug_range_string()
+                    );
+                    println!("Mayhaps you forgot to add: 'status: done'");
+                    process::exit(1);
+                } else {
+                    self.add_to_pulse(done_at, new_id);
+                }
+
+            }
+        };
+
+        match task.source_file {
+            None => unsafe { debug_unreachable!() },
+            Some(ref source_file) => {
+
+                match task.status {
+                    None => {
+
+                        if self.is_overdue(&task) {
+                            let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                            file_stats.add_overdue_task_id(new_id);
+                        } else if !self.should_defer(&task) {
+                            let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                            // add task to inbox
+                            file_stats.add_inbox_task_id(new_id);
+                        } else {
+                            let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                            file_stats.add_deferred_task_id(new_id);
+                        }
+
+                    },
+                    Some(ref status) => {
+
+                        match *status {
+                            Status::NotDone => {
+                                if self.is_overdue(&task) {
+                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                                    file_stats.add_overdue_task_id(new_id);
+                                } else if !self.should_defer(&task) {
+                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                                    // add task to inbox
+                                    file_stats.add_inbox_task_id(new_id);
+                                } else {
+                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                                    file_stats.add_deferred_task_id(new_id);
+                                }
+                            },
+                            Status::Incubate => {
+
+                                if self.is_overdue(&task) {
+                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                                    file_stats.add_overdue_task_id(new_id);
+                                } else if !self.should_defer(&task) {
+                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                                    file_stats.add_incubate_task_id(new_id);
+                                } else {
+                                    let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                                    file_stats.add_deferred_task_id(new_id);
+                                }
+
+                            },
+                            Status::Done => {
+                                let file_stats = self.file_stats.get_mut(source_file).unwrap();
+                                file_stats.add_finished_task_id(new_id);
+                            }
+                        }
+                    }
+                };
+
+                let file_stats = self.file_stats.get_mut(source_file).unwrap();
+
+                match task.tags {
+                    None => {},
+                    Some(ref tags) => {
+                        for tag in tags {
+                            file_stats.add_tag(tag.clone());
+                        }
+
+                    }
+                };
+
+                match task.contexts {
+                    None => {},
+                    Some(ref contexts) => {
+
+                        for context in contexts {
+                            file_stats.add_context(context.clone());
+                        }
+
+                    }
+                };
+
+                match task.project {
+                    None => {},
+                    Some(ref project_path) => {
+                        file_stats.add_project_path(project_path.clone());
+                    }
+                };
+
+            }
+        };
+
+        // add task to look-up table
+        self.tasks.insert(new_id, task);
+
+    }
+
+    // sort tasks into various data structures (e.g. overdue, inbox, etc) that shall be displayed
+    // to the user.
+    //
+    // this is done after all files are parsed, since views declared within them may change
+    // how tasks are filtered.
+    fn categorize_tasks(&mut self) {
+
+        let tasks = mem::replace(&mut self.tasks, HashMap::new());
+
+        // tasks are categorized in order of appearance
+        for task_id in 1..(tasks.len() as u64 + 1) {
+
+            let task: &Task = tasks.get(&task_id).unwrap();
+
+            if self.hide_tasks_by_default {
+
+                // hide task unless it satisfy [whitelist] filters
+
+                self.add_task_default_hidden(task, task_id);
+
+            } else {
+
+                // default behaviour
+
+                self.add_task_default(task, task_id);
+            }
+        }
+
+        self.tasks = tasks;
+    }
+
+    #[allow(cyclomatic_complexity)]
+    fn add_task_default_hidden(&mut self, task: &Task, new_id: u64) {
+
+        if self.should_hide_task(task) {
+            return;
+        }
+
+        let mut shall_show: bool =
+            self.filter_by_only_tags && (task.tags.is_some() || self.include_untagged) ||
+            self.filter_by_only_contexts && (task.contexts.is_some() || self.include_no_context) ||
+            self.has_project_only_filters() && (task.project.is_some() || self.include_no_project) ||
+            // invariant: task has no tags / contexts
+            self.only_untagged ||
+            self.only_no_context ||
+            self.show_only_flagged && task.flag ||
+            self.show_flagged && task.flag ||
+            self.show_nonproject_tasks && task.project.is_none() ||
+            self.show_project_tasks && task.project.is_some() ||
+            // invariant: task satisfies the query filter
+            self.filter_where.is_some() ||
+            // invariant: task matches the search
+            self.filter_grep.is_some();
+
+
+        if self.has_project_whitelist() {
+
+            if let Some(ref project_path) = task.project {
+                if self.should_whitelist_project(project_path) {
+                    shall_show = true;
+                }
+            }
+
+        };
+
+        if self.filter_by_include_tags {
+            match task.tags {
+                None => {},
+                Some(ref tags) => {
+                    if self.have_include_tags(tags) {
+                        shall_show = true;
+                    }
+                }
+            }
+        }
+
+        if self.filter_by_include_contexts {
+            match task.contexts {
+                None => {},
+                Some(ref contexts) => {
+                    if self.have_include_contexts(contexts) {
+                        shall_show = true;
+                    }
+                }
+            }
+        }
+
+        // sort task by status and priority
+        match task.status {
+            None => {
+
+                if self.hide_incomplete {
+                    // hide task
+                } else if self.is_overdue(task) {
+
+                    if self.show_overdue || shall_show {
+                        self.add_to_overdue(new_id);
+                    }
+
+                } else if !self.should_defer(task) {
+
+                    if self.show_incomplete || shall_show {
+                        // add task to inbox
+                        self.add_to_inbox(new_id);
+                    }
+
+                } else if self.show_deferred || shall_show {
+                    self.add_to_deferred(new_id);
+                }
+
+            },
+            Some(ref status) => {
+
+                match *status {
+                    Status::NotDone | Status::Incubate => {
+
+                        if self.hide_incomplete {
+                            // hide task
+                        } else if self.is_overdue(task) {
+
+                            if self.show_overdue || shall_show {
+                                self.add_to_overdue(new_id);
+                            }
+
+                        } else if !self.should_defer(task) {
+
+                            if self.show_incomplete || shall_show {
+                                // add task to inbox
+                                self.add_to_inbox(new_id);
+                            }
+
+                        } else if self.show_deferred || shall_show {
+                            self.add_to_deferred(new_id);
+                        }
+                    },
+
+                    // NOTE: merged with above arm
+                    // Status::Incubate => {
+
+                    //     if self.hide_incomplete {
+                    //         // hide task
+                    //     } else if self.is_overdue(task) {
+
+                    //         if self.show_overdue || shall_show {
+                    //             self.add_to_overdue(new_id);
+                    //         }
+
+                    //     } else if !self.should_defer(task) {
+
+                    //         if self.show_incomplete || shall_show {
+                    //             // add task to inbox
+                    //             self.add_to_inbox(new_id);
+                    //         }
+
+                    //     } else if self.show_deferred || shall_show {
+                    //         self.add_to_deferred(new_id);
+                    //     }
+                    // },
+                    Status::Done => {
+
+                        if self.show_done || shall_show {
+                            self.add_to_done(new_id);
+                        }
+
+                    }
+                }
+            }
+        }
+
+    }
+
+    fn add_task_default(&mut self, task: &Task, new_id: u64) {
+
+        if self.should_hide_task(task) {
+            return;
+        }
+
+        // sort task by status and priority
+        match task.status {
+            None => {
+
+                if self.hide_incomplete {
+                    // hide task
+                } else if self.is_overdue(task) {
+                    self.add_to_overdue(new_id);
+                } else if !self.should_defer(task) {
+                    // add task to inbox
+                    self.add_to_inbox(new_id);
+                } else {
+                    self.add_to_deferred(new_id);
+                }
+
+            },
+            Some(ref status) => {
+
+                match *status {
+                    Status::NotDone => {
+
+                        if self.hide_incomplete {
+                            // hide task
+                        } else if self.is_overdue(task) {
+                            self.add_to_overdue(new_id);
+                        } else if !self.should_defer(task) {
+                            // add task to inbox
+                            self.add_to_inbox(new_id);
+                        } else {
+                            self.add_to_deferred(new_id);
+                        }
+                    },
+                    Status::Incubate => {
+
+                        if self.hide_incomplete {
+                            // hide task
+                        } else if self.is_overdue(task) {
+                            self.add_to_overdue(new_id);
+                        } else if !self.should_defer(task) {
+
+                            if self.show_incubate {
+
+                                // add task to inbox
+                                self.add_to_inbox(new_id);
+
+                            }
+
+                        } else {
+                            self.add_to_deferred(new_id);
+                        }
+                    },
+                    Status::Done => {
+                        self.add_to_done(new_id);
+                    }
+                }
+            }
+        }
+
+    }
+
+    fn should_hide_task(&mut self, task: &Task) -> bool {
+
+        if self.hide_nonproject_tasks &&!task.project.is_some() {
+            return true;
+        }
+
+        if self.only_in_files.len() > 0 && !self.is_task_in_files(task, &self.only_in_files) {
+            return true;
+        }
+
+        if self.hide_in_files.len() > 0 && self.is_task_in_files(task, &self.hide_in_files) {
+            return true;
+        }
+
+        if self.filter_by_hide_tags {
+            if let Some(ref tags) = task.tags {
+                if self.have_hide_tags(tags) {
+                    return true;
+                }
+            }
+        }
+
+        if self.filter_by_hide_contexts {
+            if let Some(ref contexts) = task.contexts {
+                if self.have_hide_contexts(contexts) {
+                    return true;
+                }
+            }
+        }
+
+        if self.has_project_blacklist() {
+            if let Some(ref project_path) = task.project {
+                if self.should_blacklist_project(project_path) {
+                    return true;
+                }
+            }
+        }
+
+        if self.only_untagged && task.tags.is_some() {
+            return true;
+        }
+
+        if self.only_no_context && task.contexts.is_some() {
+            return true;
+        }
+
+        if self.filter_by_only_tags {
+            match task.tags {
+                None => {
+                    if !self.include_untagged {
+                        return true;
+                    }
+                },
+                Some(ref tags) => {
+                    if !self.have_only_tags(tags) {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        if self.filter_by_only_contexts {
+            match task.contexts {
+                None => {
+                    if !self.include_no_context {
+                        return true;
+                    }
+                },
+                Some(ref contexts) => {
+                    if !self.have_only_contexts(contexts) {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        // invariant: task belongs to a project
+
+        // if necessary, apply any project path apply filters
+
+        if self.has_project_only_filters() {
+
+            let should_filter: bool = match task.project {
+                Some(ref project_path) => {
+                    !self.should_only_filter_project(project_path)
+                },
+                None => !self.include_no_project
+            };
+
+            if should_filter {
+                return true;
+            }
+
+        }
+
+        if self.filter_where.is_some() {
+
+            let where_filter = self.filter_where.as_ref().unwrap();
+
+            if !where_satisfy_tree(where_filter, task, &self.base_root) {
+                return true;
+            }
+        }
+
+        if !self.grep_task(task) {
+            return true;
+        }
+
+        if self.show_only_flagged {
+            return !task.flag;
+        }
+
+        if self.show_flagged && task.flag {
+            return false;
+        }
+
+        if self.hide_flagged {
+            return task.flag;
+        }
+
+        if self.filter_priority.is_some() {
+
+            let priority_filter = self.filter_priority.as_ref().unwrap();
+
+            return !priority_satisfy_tree(priority_filter, task.priority);
+
+        }
+
+        // TODO: redundant; remove
+        // if self.show_project_tasks && task.project.is_some() {
+        //     return false;
+        // }
+
+        return false;
+    }
+
+    fn should_defer(&self, task: &Task) -> bool {
+
+        // TODO: necessary??
+        // if self.show_deferred {
+        //     return false;
+        // }
+
+        match task.defer {
+            None => {
+                return false;
+            },
+            Some(ref defer) => {
+
+                match *defer {
+                    Defer::Forever => {
+                        return true;
+                    },
+                    Defer::Until(defer_till) => {
+                        return defer_till.timestamp() > self.now.timestamp();
+                    }
+                }
+
+
+            }
+        }
+
+        return false;
+    }
+
+    fn add_to_pulse(&mut self, done_at: &NaiveDateTime, task_id: u64) {
+
+        let diff = self.now.timestamp() - done_at.timestamp();
+
+        if !(0 <= diff && diff <= chrono::Duration::days(7).num_seconds()) {
+            return;
+        }
+
+        let diff = diff as f64;
+
+        let sec_per_minute: f64 = 60f64;
+        let sec_per_hour: f64 = sec_per_minute * 60f64;
+        let sec_per_day: f64 = sec_per_hour * 24f64;
+
+        let days_ago = (diff / sec_per_day).floor() as i64;
+
+        self.pulse.entry(days_ago).or_insert_with(Vec::new);
+
+        match self.pulse.get_mut(&days_ago) {
+            None => unsafe { debug_unreachable!("journal.overdue missing expected bucket") },
+            Some(bucket) => {
+                (*bucket).push(task_id);
+            }
+        }
+
+    }
+
+    fn is_overdue(&self, task: &Task) -> bool {
+
+        match task.due_at {
+            None => {
+                return false;
+            },
+            Some(ref due_at) => {
+                return (self.now.timestamp() + self.due_within.num_seconds()) >= due_at.timestamp();
+            }
+        }
+
+    }
+
+    fn add_to_overdue(&mut self, task_id: u64) {
+        self.overdue.push(task_id);
+    }
+
+    fn add_to_inbox(&mut self, task_id: u64) {
+        self.inbox.push(task_id);
+    }
+
+    fn add_to_deferred(&mut self, task_id: u64) {
+        self.deferred.push(task_id);
+    }
+
+    fn add_to_done(&mut self, task_id: u64) {
+        self.done.push(task_id);
+    }
+
+    fn next_task_id(&mut self) -> u64 {
+        to_task_id(self.tasks.len() + 1) as u64
+    }
+
+    /* sorting */
+
+    fn sort_tasks(&mut self) {
+
+        let mut overdue = mem::replace(&mut self.overdue, Vec::new());
+        self.sort_section(&Section::Overdue, &mut overdue);
+        self.overdue = overdue;
+
+        let mut inbox = mem::replace(&mut self.inbox, Vec::new());
+        self.sort_section(&Section::Inbox, &mut inbox);
+        self.inbox = inbox;
+
+        let mut deferred = mem::replace(&mut self.deferred, Vec::new());
+        self.sort_section(&Section::Deferred, &mut deferred);
+        self.deferred = deferred;
+
+        let mut done = mem::replace(&mut self.done, Vec::new());
+        self.sort_section(&Section::Done, &mut done);
+        self.done = done;
+    }
+
+    fn sort_section(&self, section: &Section, task_ids: &mut Vec<u64>) {
+
+        let sort_orders = self.section_sort_orders(section);
+
+        // sort is stable; ties remain in order of appearance
+        task_ids.sort_by(|a, b| {
+            let a: &Task = self.tasks.get(a).unwrap();
+            let b: &Task = self.tasks.get(b).unwrap();
+            compare_tasks(&sort_orders, a, b)
+        });
+    }
+
+    // actionable tasks (i.e. not deferred, incubated or done) ranked by: overdue, flagged,
+    // and then by priority.
+    //
+    // unlike the overdue section, deferred tasks that are overdue are not revealed.
+    fn next_actions(&self) -> Vec<u64> {
+
+        let mut next_actions: Vec<u64> = self.overdue.iter()
+            .chain(self.inbox.iter())
+            .cloned()
+            .filter(|task_id| {
+                let task: &Task = self.tasks.get(task_id).unwrap();
+                task.status != Some(Status::Incubate) && !self.should_defer(task)
+            })
+            .collect();
+
+        // sort is stable; ties remain in order of appearance
+        next_actions.sort_by(|a, b| {
+
+            let a: &Task = self.tasks.get(a).unwrap();
+            let b: &Task = self.tasks.get(b).unwrap();
+
+            let a_overdue = self.is_overdue(a);
+            let b_overdue = self.is_overdue(b);
+
+            if a_overdue != b_overdue {
+                return b_overdue.cmp(&a_overdue);
+            }
+
+            if a_overdue {
+                let ordering = compare_optional(a.due_at.map(|x| x.timestamp()), b.due_at.map(|x| x.timestamp()), false);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+
+            if a.flag != b.flag {
+                return b.flag.cmp(&a.flag);
+            }
+
+            return b.priority.cmp(&a.priority);
+        });
+
+        return next_actions;
+    }
+
+    fn section_sort_orders(&self, section: &Section) -> Vec<SortOrder> {
+
+        let mut section_sort_orders: Option<&Vec<SortOrder>> = None;
+        let mut sort_orders: Option<&Vec<SortOrder>> = None;
+
+        for sort_by in &self.sort_by {
+            match sort_by.section {
+                None => {
+                    sort_orders = Some(&sort_by.orders);
+                },
+                Some(ref sort_by_section) => {
+                    if sort_by_section == section {
+                        section_sort_orders = Some(&sort_by.orders);
+                    }
+                }
+            }
+        }
+
+        // sort orders for the given section take precedence
+        if let Some(sort_orders) = section_sort_orders.or(sort_orders) {
+            return sort_orders.clone();
+        }
+
+        // defaults
+
+        match *section {
+            Section::Overdue => {
+                if self.sort_overdue_by_priority {
+                    return vec![SortOrder(SortKey::Priority, false)];
+                }
+
+                // most recently due first
+                return vec![SortOrder(SortKey::Due, true)];
+            },
+            _ => {
+                return vec![SortOrder(SortKey::Priority, false)];
+            }
+        }
+    }
+
+    // NOTE: unused
+    // fn decode_priority(priority: i64) -> i64 {
+    //     -priority
+    // }
+}
+
+#[test]
+fn next_actions_test() {
+
+    let text = "task: low\n\
+        priority: 1\n\
+        \n\
+        task: flagged\n\
+        flag: true\n\
+        \n\
+        task: high\n\
+        priority: 5\n\
+        \n\
+        task: overdue later\n\
+        due: May 3, 2016 17:00\n\
+        \n\
+        task: overdue earlier\n\
+        due: May 2, 2016 17:00\n\
+        \n\
+        task: due soon\n\
+        due: May 5, 2016 17:00\n\
+        priority: 5\n\
+        \n\
+        task: also high\n\
+        priority: 5\n\
+        \n\
+        task: incubated\n\
+        status: incubate\n\
+        priority: 10\n\
+        \n\
+        task: deferred\n\
+        defer: May 10, 2016\n\
+        priority: 10\n\
+        \n\
+        task: deferred overdue\n\
+        defer: May 10, 2016\n\
+        due: May 1, 2016 17:00\n\
+        \n\
+        task: finished\n\
+        status: done\n\
+        priority: 10\n";
+
+    let now = NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 0, 0);
+
+    // overdue first (earliest due first), then flagged, then by priority;
+    // ties remain in order of appearance
+    let mut journal = parse_test_journal("next-actions", text, now);
+    journal.categorize_tasks();
+    journal.sort_tasks();
+
+    // deferred tasks that are overdue are in the overdue section, but aren't next actions
+    assert!(test_task_titles(&journal, &journal.overdue).contains(&"deferred overdue".to_owned()));
+
+    assert_eq!(test_task_titles(&journal, &journal.next_actions()), vec![
+        "overdue earlier".to_owned(),
+        "overdue later".to_owned(),
+        "flagged".to_owned(),
+        "high".to_owned(),
+        "due soon".to_owned(),
+        "also high".to_owned(),
+        "low".to_owned()
+    ]);
+
+    // tasks due soon rank as overdue when within --due-within
+    let mut journal = parse_test_journal("next-actions", text, now);
+    journal.due_within = Duration::days(2);
+    journal.categorize_tasks();
+    journal.sort_tasks();
+
+    assert_eq!(test_task_titles(&journal, &journal.next_actions()), vec![
+        "overdue earlier".to_owned(),
+        "overdue later".to_owned(),
+        "due soon".to_owned(),
+        "flagged".to_owned(),
+        "high".to_owned(),
+        "also high".to_owned(),
+        "low".to_owned()
+    ]);
+}
+
+#[test]
+fn clock_test() {
+
+    let text = "task: pay rent\n\
+        due: May 4, 2016 17:00\n\
+        \n\
+        task: file taxes\n\
+        defer: May 6, 2016\n\
+        \n\
+        task: buy milk\n\
+        status: done\n\
+        done: May 3, 2016 09:00\n";
+
+    let sections = |now: NaiveDateTime| {
+
+        let journal = parse_test_journal("clock", text, now);
+        let file_stats = journal.file_stats.values().next().unwrap();
+
+        (test_task_titles(&journal, &file_stats.overdue_tasks),
+            test_task_titles(&journal, &file_stats.inbox_tasks),
+            test_task_titles(&journal, &file_stats.deferred_tasks),
+            journal.pulse.keys().cloned().collect::<Vec<i64>>())
+    };
+
+    // Wednesday morning
+    let (overdue, inbox, deferred, pulse) = sections(NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 0, 0));
+    assert_eq!(overdue, Vec::<String>::new());
+    assert_eq!(inbox, vec!["pay rent".to_owned()]);
+    assert_eq!(deferred, vec!["file taxes".to_owned()]);
+    assert_eq!(pulse, vec![1]);
+
+    // Friday evening
+    let (overdue, inbox, deferred, pulse) = sections(NaiveDate::from_ymd(2016, 5, 6).and_hms(18, 0, 0));
+    assert_eq!(overdue, vec!["pay rent".to_owned()]);
+    assert_eq!(inbox, vec!["file taxes".to_owned()]);
+    assert_eq!(deferred, Vec::<String>::new());
+    assert_eq!(pulse, vec![3]);
+
+    // done in the future relative to now; not part of the pulse
+    let (_, _, _, pulse) = sections(NaiveDate::from_ymd(2016, 5, 1).and_hms(0, 0, 0));
+    assert_eq!(pulse, Vec::<i64>::new());
+}
+
+#[test]
+fn include_untagged_test() {
+
+    let mut journal = GTD::new("/".to_owned());
+    journal.filter_by_only_tags = true;
+    journal.add_tag_only_filters(vec!["chore".to_owned()]);
+
+    let untagged = Task::new(1);
+
+    let mut tagged = Task::new(5);
+    tagged.tags = Some(vec!["chore".to_owned()]);
+
+    assert!(journal.should_hide_task(&untagged));
+    assert!(!journal.should_hide_task(&tagged));
+
+    journal.include_untagged = true;
+
+    assert!(!journal.should_hide_task(&untagged));
+    assert!(!journal.should_hide_task(&tagged));
+
+    let mut journal = GTD::new("/".to_owned());
+    journal.only_untagged = true;
+
+    assert!(!journal.should_hide_task(&untagged));
+    assert!(journal.should_hide_task(&tagged));
+}
+
+/* diagnostics */
+
+#[derive(Debug, Clone, PartialEq)]
+enum ErrorFormat {
+    Human,
+    Short
+}
+
+impl ErrorFormat {
+    fn from_string(format: &str) -> Option<ErrorFormat> {
+        match format.trim().to_lowercase().as_str() {
+            "human" => Some(ErrorFormat::Human),
+            "short" => Some(ErrorFormat::Short),
+            _ => None
+        }
+    }
+}
+
+// attribute names and their aliases as recognized by task_block, view_block, and directives
+fn known_attributes() -> Vec<&'static str> {
+
+    let mut known_attributes: Vec<&'static str> = Vec::new();
+
+    for aliases in TASK_ATTRIBUTES.iter().chain(VIEW_ATTRIBUTES.iter()) {
+        known_attributes.extend_from_slice(aliases);
+    }
+
+    known_attributes.extend_from_slice(DIRECTIVES);
+
+    known_attributes
+}
+
+// directives that may follow `delete`
+const DELETABLE_DIRECTIVES: &'static [&'static str] = &[
+    DIRECTIVE_REQUIRE_EXCLUDE_STATUS,
+    DIRECTIVE_INJECT_PROJECT_PREFIX,
+    DIRECTIVE_ENSURE_PROJECT_PREFIX
+];
+
+// attributes that are not followed by `:`
+const BARE_ATTRIBUTES: &'static [&'static str] = &["current", DIRECTIVE_DELETE];
+
+#[derive(Debug)]
+struct Diagnostic {
+    path: String,
+    line_number: u64,
+    // the offending line; None if it could not be read back from the file
+    source: Option<String>,
+    // 1-based, in characters
+    column: usize,
+    // number of characters to underline, starting at column
+    span: usize,
+    message: String,
+    suggestion: Option<&'static str>
+}
+
+impl Diagnostic {
+
+    fn new(journal: &GTD, path: &str, line_number: u64, message: String) -> Diagnostic {
+        Diagnostic {
+            path: path.to_owned(),
+            line_number: line_number,
+            source: source_line(journal, path, line_number),
+            column: 1,
+            span: 1,
+            message: message,
+            suggestion: None
+        }
+    }
+
+    // a line that is not recognized by any parser
+    fn unparseable(journal: &GTD, path: &str, line_number: u64) -> Diagnostic {
+
+        let mut diagnostic = Diagnostic::new(journal, path, line_number, "Unable to parse line".to_owned());
+
+        let diagnosis = diagnostic.source.as_ref().map(|line| diagnose_line(line));
+
+        if let Some(LineDiagnosis(column, span, message, suggestion)) = diagnosis {
+
+            diagnostic.column = column;
+            diagnostic.span = span;
+            diagnostic.message = message;
+            diagnostic.suggestion = suggestion;
+        }
+
+        diagnostic
+    }
+
+    // a line whose attribute was recognized, but its value is invalid
+    fn invalid_value(journal: &GTD, path: &str, line_number: u64, reason: String) -> Diagnostic {
+
+        let mut diagnostic = Diagnostic::new(journal, path, line_number, reason);
+
+        let location = diagnostic.source.as_ref().map(|line| locate_value(line));
+
+        if let Some((column, span)) = location {
+            diagnostic.column = column;
+            diagnostic.span = span;
+        }
+
+        diagnostic
+    }
+
+    // a block that is invalid as a whole; its first line is underlined
+    fn invalid_block(journal: &GTD, path: &str, line_number: u64, reason: String) -> Diagnostic {
+
+        let mut diagnostic = Diagnostic::new(journal, path, line_number, reason);
+
+        let span = diagnostic.source.as_ref().map(|line| line.trim_right().chars().count());
+
+        if let Some(span) = span {
+            diagnostic.span = cmp::max(1, span);
+        }
+
+        diagnostic
+    }
+
+    // a line whose attribute is not allowed at its position
+    fn misplaced_attribute(journal: &GTD, path: &str, line_number: u64, reason: String) -> Diagnostic {
+
+        let mut diagnostic = Diagnostic::new(journal, path, line_number, reason);
+
+        let location = diagnostic.source.as_ref().map(|line| locate_attribute(line));
+
+        if let Some((column, span)) = location {
+            diagnostic.column = column;
+            diagnostic.span = span;
+        }
+
+        diagnostic
+    }
+
+    fn print(&self, error_format: &ErrorFormat) {
+        self.print_with_severity(error_format, false);
+    }
+
+    fn print_warning(&self, error_format: &ErrorFormat) {
+        self.print_with_severity(error_format, true);
+    }
+
+    fn print_with_severity(&self, error_format: &ErrorFormat, is_warning: bool) {
+
+        match *error_format {
+            ErrorFormat::Short => {
+
+                // file:line:col: message
+                let severity = if is_warning {
+                    "warning: "
+                } else {
+                    ""
+                };
+
+                match self.suggestion {
+                    None => {
+                        println!("{}:{}:{}: {}{}",
+                            self.path, self.line_number, self.column, severity, self.message);
+                    },
+                    Some(suggestion) => {
+                        println!("{}:{}:{}: {}{}; did you mean `{}`?",
+                            self.path, self.line_number, self.column, severity, self.message, suggestion);
+                    }
+                };
+            },
+            ErrorFormat::Human => {
+
+                let severity = if is_warning {
+                    "Warning"
+                } else {
+                    "Error"
+                };
+
+                println!("{}:{}:{} {} parsing at line {}: {}",
+                    self.path,
+                    self.line_number,
+                    self.column,
+                    severity,
+                    self.line_number,
+                    self.message);
+
+                let source = match self.source {
+                    None => {
+                        return;
+                    },
+                    Some(ref source) => source
+                };
+
+                let gutter = format!("{}", self.line_number);
+                let margin: String = gutter.chars().map(|_| ' ').collect();
+
+                // tabs are kept so that the carets line up with the source
+                let indent: String = source.chars()
+                    .take(self.column - 1)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                let carets: String = (0..self.span).map(|_| '^').collect();
+                let carets = if is_warning {
+                    carets.yellow().bold()
+                } else {
+                    carets.red().bold()
+                };
+
+                println!("{} |", margin);
+                println!("{} | {}", gutter, source);
+
+                match self.suggestion {
+                    None => {
+                        println!("{} | {}{}", margin, indent, carets);
+                    },
+                    Some(suggestion) => {
+                        println!("{} | {}{} {}",
+                            margin,
+                            indent,
+                            carets,
+                            format!("did you mean `{}`?", suggestion).bold());
+                    }
+                };
+            }
+        };
+    }
+}
+
+// column, span, message, and suggestion of an unparseable line
+#[derive(Debug, PartialEq)]
+struct LineDiagnosis(usize, usize, String, Option<&'static str>);
+
+fn diagnose_line(line: &str) -> LineDiagnosis {
+
+    let indentation = line.len() - line.trim_left().len();
+
+    if indentation > 0 {
+        return LineDiagnosis(1, cmp::max(1, line[..indentation].chars().count()),
+            "Lines shall not begin with whitespace, unless they continue a note".to_owned(), None);
+    }
+
+    match line.find(':') {
+        Some(colon) => {
+
+            let name = line[..colon].trim_right();
+            let name_span = cmp::max(1, name.chars().count());
+
+            if is_known_attribute(name) {
+                let (column, span) = locate_value(line);
+                let message = if line[colon + 1..].trim().len() <= 0 {
+                    format!("Missing value for `{}`", name.to_lowercase())
+                } else {
+                    format!("Invalid value for `{}`", name.to_lowercase())
+                };
+                return LineDiagnosis(column, span, message, None);
+            }
+
+            LineDiagnosis(1, name_span, format!("Unknown attribute `{}`", name),
+                suggest_name(name, &known_attributes()))
+        },
+        None => {
+
+            let word_end = line.find(|c: char| c.is_whitespace()).unwrap_or(line.len());
+            let word = &line[..word_end];
+            let word_span = cmp::max(1, word.chars().count());
+            let lowercase_word = word.to_lowercase();
+
+            if lowercase_word == DIRECTIVE_DELETE {
+
+                let rest = &line[word_end..];
+                let target = rest.trim();
+                let column = word.chars().count() + (rest.len() - rest.trim_left().len()) + 1;
+
+                return LineDiagnosis(column, cmp::max(1, target.chars().count()),
+                    format!("Unknown directive to delete `{}`", target),
+                    suggest_name(target, DELETABLE_DIRECTIVES));
+            }
+
+            if BARE_ATTRIBUTES.iter().any(|bare| *bare == lowercase_word) {
+                return LineDiagnosis(word.chars().count() + 2, cmp::max(1, line[word_end..].trim().chars().count()),
+                    format!("Unexpected text after `{}`", lowercase_word), None);
+            }
+
+            if is_known_attribute(word) {
+                return LineDiagnosis(word.chars().count() + 1, 1,
+                    format!("Expected `:` after `{}`", lowercase_word), None);
+            }
+
+            match suggest_name(word, &known_attributes()) {
+                None => {
+                    LineDiagnosis(1, word_span,
+                        "Expected an attribute followed by `:` (e.g. `title: ...`)".to_owned(), None)
+                },
+                suggestion => {
+                    LineDiagnosis(1, word_span, format!("Unknown attribute `{}`", word), suggestion)
+                }
+            }
+        }
+    }
+}
+
+fn is_known_attribute(name: &str) -> bool {
+    let name = name.trim().to_lowercase();
+    known_attributes().iter().any(|known| *known == name)
+}
+
+// column and span of the attribute name of a line
+fn locate_attribute(line: &str) -> (usize, usize) {
+
+    let name_end = line.find(':').unwrap_or(line.len());
+    let name = line[..name_end].trim();
+
+    match line.find(name) {
+        Some(start) if name.len() > 0 => {
+            (line[..start].chars().count() + 1, name.chars().count())
+        },
+        _ => (1, 1)
+    }
+}
+
+// column and span of the value of an `attribute: value` line
+fn locate_value(line: &str) -> (usize, usize) {
+
+    let colon = match line.find(':') {
+        None => {
+            return (1, cmp::max(1, line.chars().count()));
+        },
+        Some(colon) => colon
+    };
+
+    let rest = &line[colon + 1..];
+    let value = rest.trim();
+    let column = line[..colon + 1].chars().count() + (rest.len() - rest.trim_left().len()) + 1;
+
+    (column, cmp::max(1, value.chars().count()))
+}
+
+// suggest the known name closest to the given name, if it's close enough
+fn suggest_name(name: &str, known_names: &[&'static str]) -> Option<&'static str> {
+
+    let name = name.trim().to_lowercase();
+
+    if name.len() <= 0 {
+        return None;
+    }
+
+    let max_distance = cmp::max(1, name.chars().count() / 3);
+
+    let mut suggestion: Option<(usize, &'static str)> = None;
+
+    for known_name in known_names {
+
+        let distance = edit_distance(&name, known_name);
+
+        if distance > max_distance {
+            continue;
+        }
+
+        suggestion = match suggestion {
+            Some((best_distance, _)) if best_distance <= distance => suggestion,
+            _ => Some((distance, *known_name))
+        };
+    }
+
+    suggestion.map(|(_, known_name)| known_name)
+}
+
+// Levenshtein distance
+fn edit_distance(from: &str, to: &str) -> usize {
+
+    let to: Vec<char> = to.chars().collect();
+
+    let mut previous: Vec<usize> = (0..to.len() + 1).collect();
+
+    for (i, from_char) in from.chars().enumerate() {
+
+        let mut current: Vec<usize> = vec![i + 1];
+
+        for (j, to_char) in to.iter().enumerate() {
+
+            let substitution = previous[j] + if from_char == *to_char { 0 } else { 1 };
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+
+            current.push(cmp::min(substitution, cmp::min(insertion, deletion)));
+        }
+
+        previous = current;
+    }
+
+    previous[to.len()]
+}
+
+fn source_line(journal: &GTD, path: &str, line_number: u64) -> Option<String> {
+
+    if line_number <= 0 {
+        return None;
+    }
+
+    // documents opened in an editor may have unsaved changes
+    if let Some(text) = journal.documents.get(path) {
+        return text.lines()
+            .nth((line_number - 1) as usize)
+            .map(|line| line.trim_right_matches('\r').to_owned());
+    }
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => {
+            return None;
+        }
+    };
+
+    match BufReader::new(file).lines().nth((line_number - 1) as usize) {
+        Some(Ok(line)) => Some(line.trim_right_matches('\r').to_owned()),
+        _ => None
+    }
+}
+
+#[test]
+fn diagnostics_test() {
+
+    assert_eq!(edit_distance("stauts", "status"), 2);
+    assert_eq!(edit_distance("", "due"), 3);
+    assert_eq!(edit_distance("due", "due"), 0);
+
+    assert_eq!(suggest_name("stauts", &known_attributes()), Some("status"));
+    assert_eq!(suggest_name("Priorty", &known_attributes()), Some("priority"));
+    assert_eq!(suggest_name("require.stauts", &known_attributes()), Some("require.status"));
+    assert_eq!(suggest_name("xyz", &known_attributes()), None);
+
+    assert_eq!(diagnose_line("stauts: done"),
+        LineDiagnosis(1, 6, "Unknown attribute `stauts`".to_owned(), Some("status")));
+
+    assert_eq!(diagnose_line("due:   whenever"),
+        LineDiagnosis(8, 8, "Invalid value for `due`".to_owned(), None));
+
+    assert_eq!(diagnose_line("title buy milk"),
+        LineDiagnosis(6, 1, "Expected `:` after `title`".to_owned(), None));
+
+    assert_eq!(diagnose_line("  due: today"),
+        LineDiagnosis(1, 2, "Lines shall not begin with whitespace, unless they continue a note".to_owned(), None));
+
+    assert_eq!(diagnose_line("delete inject.projet.prefix"),
+        LineDiagnosis(8, 20, "Unknown directive to delete `inject.projet.prefix`".to_owned(),
+            Some("inject.project.prefix")));
+
+    assert_eq!(locate_value("tags: a, b"), (7, 4));
+    assert_eq!(locate_attribute("where: due < today"), (1, 5));
+}
+
+#[test]
+fn known_attributes_test() {
+
+    // every alias of a task block attribute is parsed, given a valid value
+    let task_values: Vec<(&[&str], &str)> = vec![
+        (TASK_CURRENT, ""),
+        (TASK_TITLE, ": buy milk"),
+        (TASK_ID, ": milk"),
+        (TASK_NOTE, ": whole milk"),
+        (TASK_TIME, ": 25 seconds"),
+        (TASK_PRIORITY, ": 5"),
+        (TASK_PROJECT, ": home / groceries"),
+        (TASK_FLAG, ": yes"),
+        (TASK_CREATED, ": May 4, 2016"),
+        (TASK_DONE, ": May 4, 2016"),
+        (TASK_CHAIN, ": May 4, 2016"),
+        (TASK_STATUS, ": done"),
+        (TASK_DUE, ": May 4, 2016"),
+        (TASK_DEFER, ": May 4, 2016"),
+        (TASK_CONTEXTS, ": phone"),
+        (TASK_TAGS, ": chore")
+    ];
+
+    assert_eq!(task_values.len(), TASK_ATTRIBUTES.len());
+
+    for &(aliases, value) in &task_values {
+        for alias in aliases {
+
+            let line = format!("{}{}\n", alias.to_uppercase(), value);
+
+            match parse_only(|i| task_block(i), line.as_bytes()) {
+                Ok(LineToken::Task(TaskBlock::Invalid(_))) | Err(_) => {
+                    assert!(false, "unable to parse: {}", line);
+                },
+                Ok(_) => {}
+            }
+        }
+    }
+
+    let view_values: Vec<(&[&str], &str)> = vec![
+        (VIEW_NAME, ": morning"),
+        (VIEW_WHERE, ": tag = chore"),
+        (VIEW_SHOW, ": overdue, inbox"),
+        (VIEW_SORT, ": priority")
+    ];
+
+    assert_eq!(view_values.len(), VIEW_ATTRIBUTES.len());
+
+    for &(aliases, value) in &view_values {
+        for alias in aliases {
+
+            let line = format!("{}{}\n", alias, value);
+
+            if parse_only(|i| view_block(i), line.as_bytes()).is_err() {
+                assert!(false, "unable to parse: {}", line);
+            }
+        }
+    }
+
+    let directive_values: Vec<(&str, &str)> = vec![
+        (DIRECTIVE_INCLUDE, ": other.gtd"),
+        (DIRECTIVE_DEFAULT_STATUS, ": done"),
+        (DIRECTIVE_REQUIRE_STATUS, ": yes"),
+        (DIRECTIVE_REQUIRE_EXCLUDE_STATUS, ": done"),
+        (DIRECTIVE_REQUIRE_PROJECT_PREFIX, ": home"),
+        (DIRECTIVE_REQUIRE_PROJECT, ": yes"),
+        (DIRECTIVE_INJECT_PROJECT_PREFIX, ": home"),
+        (DIRECTIVE_INJECT_STATUS, ": done"),
+        (DIRECTIVE_ENSURE_PROJECT_PREFIX, ": home"),
+        (DIRECTIVE_DELETE, " require.exclude.status")
+    ];
+
+    assert_eq!(directive_values.len(), DIRECTIVES.len());
+
+    for &(directive, value) in &directive_values {
+
+        let line = format!("{}{}\n", directive, value);
+
+        if parse_only(|i| directives(i), line.as_bytes()).is_err() {
+            assert!(false, "unable to parse: {}", line);
+        }
+    }
+
+    for directive in DELETABLE_DIRECTIVES {
+
+        let line = format!("{} {}\n", DIRECTIVE_DELETE, directive);
+
+        if parse_only(|i| directives(i), line.as_bytes()).is_err() {
+            assert!(false, "unable to parse: {}", line);
+        }
+    }
+}
+
+/* language server */
+
+// Language Server Protocol over stdio (https://microsoft.github.io/language-server-protocol/)
+
+const LSP_TEXT_DOCUMENT_SYNC_FULL: u64 = 1;
+const LSP_SEVERITY_ERROR: u64 = 1;
+const LSP_COMPLETION_KIND_PROPERTY: u64 = 10;
+const LSP_COMPLETION_KIND_VALUE: u64 = 12;
+const LSP_PARSE_ERROR: i64 = -32700;
+const LSP_METHOD_NOT_FOUND: i64 = -32601;
+
+// status values as recognized by parse_status
+const STATUS_VALUES: &'static [&'static str] = &["done", "incubate", "active"];
+
+struct LanguageServer<W: Write> {
+    // canonical path to the gtdtxt file given on the command line
+    root: String,
+    // text of documents opened in the editor, by canonical path
+    documents: HashMap<String, String>,
+    // journal of the last parse; used for completions and hovers
+    journal: GTD,
+    // files that diagnostics were published for; they're cleared when no longer reported
+    published: HashSet<String>,
+    shutdown: bool,
+    exit: bool,
+    // where messages to the client are written; i.e. stdout
+    output: W
+}
+
+impl<W: Write> LanguageServer<W> {
+
+    fn new(root: String, output: W) -> LanguageServer<W> {
+
+        let documents = HashMap::new();
+        let journal = parse_journal_leniently(&root, &documents);
+
+        LanguageServer {
+            root: root,
+            documents: documents,
+            journal: journal,
+            published: HashSet::new(),
+            shutdown: false,
+            exit: false,
+            output: output
+        }
+    }
+
+    // returns a response for requests; notifications have none
+    fn handle(&mut self, message: &Json) -> Option<Json> {
+
+        let method: String = match message.find("method").and_then(|method| method.as_string()) {
+            None => {
+                // responses from the client are not expected
+                return None;
+            },
+            Some(method) => method.to_owned()
+        };
+
+        let params: Json = message.find("params").cloned().unwrap_or(Json::Null);
+        let id: Option<Json> = message.find("id").cloned();
+
+        let result: Json = match method.as_str() {
+            "initialize" => {
+                lsp_capabilities()
+            },
+            "shutdown" => {
+                self.shutdown = true;
+                Json::Null
+            },
+            "exit" => {
+                self.exit = true;
+                return None;
+            },
+            "textDocument/didOpen" => {
+
+                let text = json_string(&params, &["textDocument", "text"]);
+
+                if let (Some(path), Some(text)) = (document_path(&params), text) {
+                    self.documents.insert(path, text.to_owned());
+                    self.update();
+                }
+
+                return None;
+            },
+            "textDocument/didChange" => {
+
+                // text documents are synced in full; the last change holds the whole text
+                let text = params.find("contentChanges")
+                    .and_then(|changes| changes.as_array())
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| json_string(change, &["text"]));
+
+                if let (Some(path), Some(text)) = (document_path(&params), text) {
+                    self.documents.insert(path, text.to_owned());
+                    self.update();
+                }
+
+                return None;
+            },
+            "textDocument/didSave" => {
+                self.update();
+                return None;
+            },
+            "textDocument/didClose" => {
+
+                if let Some(path) = document_path(&params) {
+                    self.documents.remove(&path);
+                    self.update();
+                }
+
+                return None;
+            },
+            "textDocument/completion" => {
+                self.completion(&params)
+            },
+            "textDocument/definition" => {
+                self.definition(&params)
+            },
+            "textDocument/hover" => {
+                self.hover(&params)
+            },
+            _ => {
+                return id.map(|id| {
+                    lsp_error(id, LSP_METHOD_NOT_FOUND, format!("Method not found: {}", method))
+                });
+            }
+        };
+
+        id.map(|id| {
+            json_object(vec![
+                ("jsonrpc", Json::String("2.0".to_owned())),
+                ("id", id),
+                ("result", result)
+            ])
+        })
+    }
+
+    // re-parse the journal and publish its diagnostics
+    fn update(&mut self) {
+
+        self.journal = parse_journal_leniently(&self.root, &self.documents);
+
+        let mut diagnostics: Vec<(String, Json)> = Vec::new();
+        let mut files: HashSet<String> = self.journal.file_stats.keys().cloned().collect();
+
+        collect_lsp_diagnostics(&self.journal, &mut diagnostics);
+
+        // opened documents that are not included by the journal are parsed on their own
+        let detached: Vec<String> = self.documents.keys()
+            .filter(|path| !files.contains(*path))
+            .cloned()
+            .collect();
+
+        for path in detached {
+
+            let journal = parse_journal_leniently(&path, &self.documents);
+
+            collect_lsp_diagnostics(&journal, &mut diagnostics);
+            files.extend(journal.file_stats.keys().cloned());
+        }
+
+        let mut diagnostics_by_file: HashMap<String, Vec<Json>> = HashMap::new();
+
+        for file in files.iter().chain(self.published.iter()) {
+            diagnostics_by_file.insert(file.clone(), Vec::new());
+        }
+
+        for (path, diagnostic) in diagnostics {
+            diagnostics_by_file.entry(path).or_insert_with(Vec::new).push(diagnostic);
+        }
+
+        self.published = files;
+
+        for (path, diagnostics) in diagnostics_by_file {
+
+            write_lsp_message(&mut self.output, &json_object(vec![
+                ("jsonrpc", Json::String("2.0".to_owned())),
+                ("method", Json::String("textDocument/publishDiagnostics".to_owned())),
+                ("params", json_object(vec![
+                    ("uri", Json::String(path_to_uri(&path))),
+                    ("diagnostics", Json::Array(diagnostics))
+                ]))
+            ]));
+        }
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+
+        let (path, line_number, character) = match document_position(params) {
+            None => {
+                return Json::Null;
+            },
+            Some(position) => position
+        };
+
+        let line = source_line(&self.journal, &path, line_number + 1).unwrap_or_else(String::new);
+        let prefix: String = line.chars().take(character as usize).collect();
+
+        Json::Array(completion_items(&self.journal, &prefix, line_number))
+    }
+
+    // go to the file of an `include:` directive
+    fn definition(&self, params: &Json) -> Json {
+
+        let (path, line_number, _) = match document_position(params) {
+            None => {
+                return Json::Null;
+            },
+            Some(position) => position
+        };
+
+        let line = match source_line(&self.journal, &path, line_number + 1) {
+            None => {
+                return Json::Null;
+            },
+            Some(line) => format!("{}\n", line)
+        };
+
+        let path_to_file: String = match parse_only(|i| directive_include(i), line.as_bytes()) {
+            Ok(Directive::Include(path_to_file)) => path_to_file,
+            _ => {
+                return Json::Null;
+            }
+        };
+
+        // included files are relative to the including file
+        let included = match Path::new(&path).parent() {
+            None => PathBuf::from(&path_to_file),
+            Some(parent_dir) => parent_dir.join(&path_to_file)
+        };
+
+        let included: String = match included.canonicalize() {
+            Ok(ref resolved) if resolved.is_file() => format!("{}", resolved.display()),
+            _ => {
+                return Json::Null;
+            }
+        };
+
+        json_object(vec![
+            ("uri", Json::String(path_to_uri(&included))),
+            ("range", lsp_range(0, 0, 0))
+        ])
+    }
+
+    // resolved datetimes of datetime attributes
+    fn hover(&self, params: &Json) -> Json {
+
+        let (path, line_number, _) = match document_position(params) {
+            None => {
+                return Json::Null;
+            },
+            Some(position) => position
+        };
+
+        let line = match source_line(&self.journal, &path, line_number + 1) {
+            None => {
+                return Json::Null;
+            },
+            Some(line) => format!("{}\n", line)
+        };
+
+        let contents: String = match parse_only(|i| task_block(i), line.as_bytes()) {
+            Ok(LineToken::Task(task_block_line)) => {
+                match task_block_line {
+                    TaskBlock::Due(due_at) => {
+                        format!("Due at: {}", report_datetime(&self.journal, &due_at))
+                    },
+                    TaskBlock::Defer(Defer::Forever) => {
+                        "Defer till: Forever".to_owned()
+                    },
+                    TaskBlock::Defer(Defer::Until(defer_till)) => {
+                        format!("Defer till: {}", report_datetime(&self.journal, &defer_till))
+                    },
+                    TaskBlock::Created(created_at) => {
+                        format!("Added at: {}", report_datetime(&self.journal, &created_at))
+                    },
+                    TaskBlock::Done(done_at) => {
+                        format!("Done at: {}", report_datetime(&self.journal, &done_at))
+                    },
+                    TaskBlock::Chain(chain_at) => {
+                        format!("Chain: {}", report_datetime(&self.journal, &chain_at))
+                    },
+                    TaskBlock::Invalid(reason) => reason,
+                    _ => {
+                        return Json::Null;
+                    }
+                }
+            },
+            _ => {
+                return Json::Null;
+            }
+        };
+
+        json_object(vec![
+            ("contents", json_object(vec![
+                ("kind", Json::String("plaintext".to_owned())),
+                ("value", Json::String(contents))
+            ])),
+            ("range", lsp_range(line_number, 0, line.trim_right().chars().count() as u64))
+        ])
+    }
+}
+
+fn run_language_server(path_to_file: String) {
+
+    let root: String = match Path::new(&path_to_file).canonicalize() {
+        Ok(ref resolved) if resolved.is_file() => format!("{}", resolved.display()),
+        _ => {
+            println!("Path is not a file: {}", path_to_file);
+            process::exit(1);
+        }
+    };
+
+    let mut server = LanguageServer::new(root, io::stdout());
+
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+
+    while let Some(body) = read_lsp_message(&mut stdin) {
+
+        match Json::from_str(&body) {
+            Ok(message) => {
+                if let Some(response) = server.handle(&message) {
+                    write_lsp_message(&mut server.output, &response);
+                }
+            },
+            Err(_) => {
+                write_lsp_message(&mut server.output,
+                    &lsp_error(Json::Null, LSP_PARSE_ERROR, "Unable to parse message".to_owned()));
+            }
+        };
+
+        if server.exit {
+            break;
+        }
+    }
+
+    process::exit(if server.shutdown { 0 } else { 1 });
+}
+
+// parse the journal without exiting on errors; these are collected as diagnostics instead
+fn parse_journal_leniently(root: &str, documents: &HashMap<String, String>) -> GTD {
+
+    let base_root = format!("{}", env::current_dir().unwrap().display());
+
+    let mut journal = GTD::new(base_root);
+    journal.lenient = true;
+    journal.diagnostics = Some(Vec::new());
+    journal.documents = documents.clone();
+
+    // a root that was deleted since, and that is not opened in the editor, would make parse_file exit
+    if !Path::new(root).is_file() && !documents.contains_key(root) {
+        return journal;
+    }
+
+    parse_file(None, root.to_owned(), &mut journal);
+
+    journal
+}
+
+// diagnostics of the journal, each with the path of its file
+fn collect_lsp_diagnostics(journal: &GTD, lsp_diagnostics: &mut Vec<(String, Json)>) {
+
+    let diagnostics = match journal.diagnostics {
+        None => {
+            return;
+        },
+        Some(ref diagnostics) => diagnostics
+    };
+
+    for diagnostic in diagnostics {
+
+        let message = match diagnostic.suggestion {
+            None => diagnostic.message.clone(),
+            Some(suggestion) => format!("{}; did you mean `{}`?", diagnostic.message, suggestion)
+        };
+
+        // lines and characters are zero-based
+        let line_number = cmp::max(1, diagnostic.line_number) - 1;
+        let start = (cmp::max(1, diagnostic.column) - 1) as u64;
+
+        lsp_diagnostics.push((diagnostic.path.clone(), json_object(vec![
+            ("range", lsp_range(line_number, start, start + diagnostic.span as u64)),
+            ("severity", Json::U64(LSP_SEVERITY_ERROR)),
+            ("source", Json::String("gtdtxt".to_owned())),
+            ("message", Json::String(message))
+        ])));
+    }
+}
+
+fn completion_items(journal: &GTD, prefix: &str, line_number: u64) -> Vec<Json> {
+
+    // continued notes
+    if prefix.starts_with(|c: char| c.is_whitespace()) {
+        return vec![];
+    }
+
+    let colon = match prefix.find(':') {
+        None => {
+
+            // attribute names and their aliases
+            let range = lsp_range(line_number, 0, prefix.chars().count() as u64);
+
+            return known_attributes().iter().map(|name| {
+
+                let new_text = if BARE_ATTRIBUTES.contains(name) {
+                    format!("{} ", name)
+                } else {
+                    format!("{}: ", name)
+                };
+
+                lsp_completion_item(name, LSP_COMPLETION_KIND_PROPERTY, new_text, range.clone())
+            }).collect();
+        },
+        Some(colon) => colon
+    };
+
+    let attribute = prefix[..colon].trim().to_lowercase();
+
+    let (values, is_list): (Vec<String>, bool) = match attribute.as_str() {
+        "project" |
+        "require.project.prefix" |
+        "inject.project.prefix" |
+        "ensure.project.prefix" => {
+            (journal_project_paths(journal), false)
+        },
+        "tags" | "tag" => {
+            (journal_list_values(journal, |task| task.tags.as_ref()), true)
+        },
+        "contexts" | "context" => {
+            (journal_list_values(journal, |task| task.contexts.as_ref()), true)
+        },
+        "status" |
+        "default.status" |
+        "require.status" |
+        "require.exclude.status" |
+        "inject.status" => {
+            (STATUS_VALUES.iter().map(|status| status.to_string()).collect(), false)
+        },
+        _ => {
+            return vec![];
+        }
+    };
+
+    // only the list item at the cursor is replaced
+    let value = &prefix[colon + 1..];
+    let item_start = if is_list {
+        value.rfind(',').map(|index| index + 1).unwrap_or(0)
+    } else {
+        0
+    };
+    let item = &value[item_start..];
+    let item_start = colon + 1 + item_start + (item.len() - item.trim_left().len());
+
+    let range = lsp_range(line_number,
+        prefix[..item_start].chars().count() as u64,
+        prefix.chars().count() as u64);
+
+    values.into_iter().map(|value| {
+        lsp_completion_item(&value, LSP_COMPLETION_KIND_VALUE, value.clone(), range.clone())
+    }).collect()
+}
+
+// project paths used in the journal, and their prefixes
+fn journal_project_paths(journal: &GTD) -> Vec<String> {
+
+    let mut project_paths: BTreeSet<String> = BTreeSet::new();
+
+    for task in journal.tasks.values() {
+        if let Some(ref project_path) = task.project {
+            for depth in 1..(project_path.len() + 1) {
+                project_paths.insert(project_path[..depth].join(" / "));
+            }
+        }
+    }
+
+    project_paths.into_iter().collect()
+}
+
+fn journal_list_values<F>(journal: &GTD, list: F) -> Vec<String>
+    where F: Fn(&Task) -> Option<&Vec<String>> {
+
+    let mut values: BTreeSet<String> = BTreeSet::new();
+
+    for task in journal.tasks.values() {
+        if let Some(items) = list(task) {
+            values.extend(items.iter().cloned());
+        }
+    }
+
+    values.into_iter().collect()
+}
+
+fn lsp_capabilities() -> Json {
+    json_object(vec![
+        ("capabilities", json_object(vec![
+            ("textDocumentSync", Json::U64(LSP_TEXT_DOCUMENT_SYNC_FULL)),
+            ("completionProvider", json_object(vec![
+                ("triggerCharacters", Json::Array(vec![
+                    Json::String(":".to_owned()),
+                    Json::String(",".to_owned()),
+                    Json::String("/".to_owned())
+                ]))
+            ])),
+            ("definitionProvider", Json::Boolean(true)),
+            ("hoverProvider", Json::Boolean(true))
+        ])),
+        ("serverInfo", json_object(vec![
+            ("name", Json::String("gtdtxt".to_owned())),
+            ("version", Json::String(format!("{}", version!())))
+        ]))
+    ])
+}
+
+fn lsp_completion_item(label: &str, kind: u64, new_text: String, range: Json) -> Json {
+    json_object(vec![
+        ("label", Json::String(label.to_owned())),
+        ("kind", Json::U64(kind)),
+        ("textEdit", json_object(vec![
+            ("range", range),
+            ("newText", Json::String(new_text))
+        ]))
+    ])
+}
+
+fn lsp_range(line_number: u64, start: u64, end: u64) -> Json {
+    json_object(vec![
+        ("start", json_object(vec![
+            ("line", Json::U64(line_number)),
+            ("character", Json::U64(start))
+        ])),
+        ("end", json_object(vec![
+            ("line", Json::U64(line_number)),
+            ("character", Json::U64(end))
+        ]))
+    ])
+}
+
+fn lsp_error(id: Json, code: i64, message: String) -> Json {
+    json_object(vec![
+        ("jsonrpc", Json::String("2.0".to_owned())),
+        ("id", id),
+        ("error", json_object(vec![
+            ("code", Json::I64(code)),
+            ("message", Json::String(message))
+        ]))
+    ])
+}
+
+fn json_object(members: Vec<(&str, Json)>) -> Json {
+
+    let mut object: BTreeMap<String, Json> = BTreeMap::new();
+
+    for (key, value) in members {
+        object.insert(key.to_owned(), value);
+    }
+
+    Json::Object(object)
+}
+
+fn json_string<'a>(json: &'a Json, path: &[&str]) -> Option<&'a str> {
+    json.find_path(path).and_then(|value| value.as_string())
+}
+
+// canonical path of the text document of the params
+fn document_path(params: &Json) -> Option<String> {
+
+    let path = match json_string(params, &["textDocument", "uri"]).and_then(uri_to_path) {
+        None => {
+            return None;
+        },
+        Some(path) => path
+    };
+
+    match Path::new(&path).canonicalize() {
+        Ok(resolved) => Some(format!("{}", resolved.display())),
+        Err(_) => Some(path)
+    }
+}
+
+// canonical path, line, and character of the text document position of the params
+fn document_position(params: &Json) -> Option<(String, u64, u64)> {
+
+    let line_number = params.find_path(&["position", "line"]).and_then(|line| line.as_u64());
+    let character = params.find_path(&["position", "character"]).and_then(|character| character.as_u64());
+
+    match (document_path(params), line_number, character) {
+        (Some(path), Some(line_number), Some(character)) => Some((path, line_number, character)),
+        _ => None
+    }
+}
+
+fn uri_to_path(uri: &str) -> Option<String> {
+
+    if !uri.starts_with("file://") {
+        return None;
+    }
+
+    let encoded = uri["file://".len()..].as_bytes();
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut index = 0;
+
+    while index < encoded.len() {
+
+        if encoded[index] == b'%' && index + 2 < encoded.len() {
+
+            let hex = String::from_utf8_lossy(&encoded[index + 1..index + 3]).into_owned();
+
+            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+
+        decoded.push(encoded[index]);
+        index += 1;
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+fn path_to_uri(path: &str) -> String {
+
+    let mut uri = "file://".to_owned();
+
+    for byte in path.bytes() {
+
+        let is_unreserved = (byte >= b'a' && byte <= b'z') ||
+            (byte >= b'A' && byte <= b'Z') ||
+            (byte >= b'0' && byte <= b'9') ||
+            byte == b'-' || byte == b'.' || byte == b'_' || byte == b'~' || byte == b'/';
+
+        if is_unreserved {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    uri
+}
+
+// body of the next message; None at the end of input
+fn read_lsp_message<R: BufRead>(reader: &mut R) -> Option<String> {
+
+    let mut content_length: usize = 0;
+
+    // headers are terminated by an empty line
+    loop {
+
+        let mut header = String::new();
+
+        match reader.read_line(&mut header) {
+            Ok(0) | Err(_) => {
+                return None;
+            },
+            Ok(_) => {}
+        };
+
+        let header = header.trim();
+
+        if header.len() <= 0 {
+            break;
+        }
+
+        let mut parts = header.splitn(2, ':');
+
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.trim().to_lowercase() == "content-length" {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let mut body: Vec<u8> = vec![0; content_length];
+
+    if reader.read_exact(&mut body).is_err() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+fn write_lsp_message<W: Write>(writer: &mut W, message: &Json) {
+
+    let body = message.to_string();
+
+    let _ = write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = writer.flush();
+}
+
+#[test]
+fn lsp_test() {
+
+    let path = "/path/to/my todo.gtd";
+    assert_eq!(path_to_uri(path), "file:///path/to/my%20todo.gtd".to_owned());
+    assert_eq!(uri_to_path(&path_to_uri(path)), Some(path.to_owned()));
+    assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+
+    let mut input = Cursor::new("Content-Length: 2\r\nContent-Type: application/vscode-jsonrpc\r\n\r\n{}".as_bytes());
+    assert_eq!(read_lsp_message(&mut input), Some("{}".to_owned()));
+    assert_eq!(read_lsp_message(&mut input), None);
+
+    let mut journal = GTD::new("/".to_owned());
+
+    let mut task = Task::new(1);
+    task.tags = Some(vec!["chore".to_owned(), "art".to_owned()]);
+    task.project = Some(vec!["work".to_owned(), "backend".to_owned()]);
+    journal.tasks.insert(1, task);
+
+    let items = completion_items(&journal, "sta", 4);
+    assert_eq!(items.len(), known_attributes().len());
+    assert_eq!(json_string(&items[0], &["textEdit", "newText"]), Some("current "));
+
+    let items = completion_items(&journal, "tags: chore, a", 4);
+    assert_eq!(items.len(), 2);
+    assert_eq!(json_string(&items[0], &["label"]), Some("art"));
+    assert_eq!(items[0].find_path(&["textEdit", "range", "start", "character"]).and_then(|c| c.as_u64()), Some(13));
+
+    let items = completion_items(&journal, "project: ", 4);
+    let labels: Vec<&str> = items.iter().filter_map(|item| json_string(item, &["label"])).collect();
+    assert_eq!(labels, vec!["work", "work / backend"]);
+
+    assert_eq!(completion_items(&journal, "  notes continued", 4).len(), 0);
+
+    // documents that are not saved to disk yet are parsed from their text
+    let root = env::temp_dir().join("gtdtxt-lsp-root.gtd");
+    let unsaved = env::temp_dir().join("gtdtxt-lsp-unsaved.gtd");
+    let _ = fs::remove_file(&unsaved);
+    File::create(&root).unwrap().write_all(b"title: root task\n").unwrap();
+
+    let root_path = format!("{}", root.canonicalize().unwrap().display());
+    let mut server = LanguageServer::new(root_path.clone(), Vec::new());
+    assert_eq!(server.journal.tasks.len(), 1);
+
+    let unsaved_path = format!("{}", unsaved.display());
+    let did_open = json_object(vec![
+        ("jsonrpc", Json::String("2.0".to_owned())),
+        ("method", Json::String("textDocument/didOpen".to_owned())),
+        ("params", json_object(vec![
+            ("textDocument", json_object(vec![
+                ("uri", Json::String(path_to_uri(&unsaved_path))),
+                ("text", Json::String("title: unsaved task\ndue: feb 31, 2016\n".to_owned()))
+            ]))
+        ]))
+    ]);
+
+    assert_eq!(server.handle(&did_open), None);
+    assert!(!server.exit);
+
+    let mut output = Cursor::new(server.output.clone());
+    let mut published: HashMap<String, usize> = HashMap::new();
+
+    while let Some(body) = read_lsp_message(&mut output) {
+
+        let message = Json::from_str(&body).unwrap();
+        assert_eq!(json_string(&message, &["method"]), Some("textDocument/publishDiagnostics"));
+
+        let uri = json_string(&message, &["params", "uri"]).unwrap().to_owned();
+        let diagnostics = message.find_path(&["params", "diagnostics"]).and_then(|x| x.as_array()).unwrap();
+
+        published.insert(uri, diagnostics.len());
+    }
+
+    assert_eq!(published.get(&path_to_uri(&root_path)), Some(&0));
+    assert_eq!(published.get(&path_to_uri(&unsaved_path)), Some(&1));
+
+    fs::remove_file(&root).unwrap();
+}
+
+/* writers */
+
+// datetime as written in task blocks; understood by parse_datetime
+fn block_datetime(datetime: &NaiveDateTime) -> String {
+    format!("{}", datetime.format("%B %-d, %Y %H:%M"))
+}
+
+// duration as written in a `time` attribute; e.g. 1h30m
+fn block_duration(seconds: u64) -> String {
+
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+
+    let mut duration = String::new();
+
+    if hours > 0 {
+        duration.push_str(&format!("{}h", hours));
+    }
+
+    if minutes > 0 {
+        duration.push_str(&format!("{}m", minutes));
+    }
+
+    if seconds > 0 || duration.len() <= 0 {
+        duration.push_str(&format!("{}s", seconds));
+    }
+
+    return duration;
+}
+
+// attributes of the task as written in a task block; notes may span several lines
+fn task_block_attributes(task: &Task) -> Vec<String> {
+
+    let mut attributes: Vec<String> = Vec::new();
+
+    if let Some(ref title) = task.title {
+        attributes.push(format!("task: {}", title));
+    }
+
+    if let Some(ref id) = task.id {
+        attributes.push(format!("id: {}", id));
+    }
+
+    if task.current {
+        attributes.push("current".to_owned());
+    }
+
+    if let Some(ref status) = task.status {
+        attributes.push(format!("status: {}", status.string().to_lowercase()));
+    }
+
+    if let Some(ref project_path) = task.project {
+        attributes.push(format!("project: {}", project_path.join(" / ")));
+    }
+
+    if let Some(ref contexts) = task.contexts {
+        attributes.push(format!("contexts: {}", contexts.join(", ")));
+    }
+
+    if let Some(ref tags) = task.tags {
+        attributes.push(format!("tags: {}", tags.join(", ")));
+    }
+
+    if task.priority != 0 {
+        attributes.push(format!("priority: {}", task.priority));
+    }
+
+    if task.flag {
+        attributes.push("flag: true".to_owned());
+    }
+
+    if let Some(ref due_at) = task.due_at {
+        attributes.push(format!("due: {}", block_datetime(due_at)));
+    }
+
+    match task.defer {
+        None => {},
+        Some(Defer::Forever) => {
+            attributes.push("defer: forever".to_owned());
+        },
+        Some(Defer::Until(ref defer_till)) => {
+            attributes.push(format!("defer: {}", block_datetime(defer_till)));
+        }
+    };
+
+    if let Some(ref created_at) = task.created_at {
+        attributes.push(format!("created: {}", block_datetime(created_at)));
+    }
+
+    if let Some(ref done_at) = task.done_at {
+        attributes.push(format!("done: {}", block_datetime(done_at)));
+    }
+
+    if let Some(ref note) = task.note {
+
+        // continued lines of notes are indented
+        let note: Vec<String> = note.trim().lines().enumerate().map(|(index, line)| {
+            let line = line.trim();
+            if index == 0 {
+                format!("notes: {}", line)
+            } else if line.len() <= 0 {
+                "".to_owned()
+            } else {
+                format!("    {}", line)
+            }
+        }).collect();
+
+        attributes.push(note.join("\n"));
+    }
+
+    return attributes;
+}
+
+// a task block attribute that spans the whole input
+fn task_block_attribute(i: Input<u8>) -> U8Result<TaskBlock> {
+    parse!{i;
+
+        let line = task_block();
+
+        eof();
+
+        ret {
+            match line {
+                LineToken::Task(task_block_line) => task_block_line,
+                _ => unsafe { debug_unreachable!() }
+            }
+        }
+    }
+}
+
+// attributes of the task block of the task; each is parsed back the same way a hand-written block is,
+// and shall yield the same task
+fn validate_task_block(task: &Task) -> Result<Vec<String>, String> {
+
+    if task.title.as_ref().map(|title| title.trim().len() <= 0).unwrap_or(true) {
+        return Err("Missing task title".to_owned());
+    }
+
+    let attributes = task_block_attributes(task);
+
+    let mut parsed_task = Task::new(1);
+
+    for attribute in &attributes {
+
+        let input = format!("{}\n", attribute);
+
+        let task_block_line = match parse_only(|i| task_block_attribute(i), input.as_bytes()) {
+            Ok(task_block_line) => task_block_line,
+            Err(_) => {
+                return Err(format!("Unable to parse task attribute: {}", attribute));
+            }
+        };
+
+        if let Err(reason) = apply_task_block(&mut parsed_task, task_block_line) {
+            return Err(format!("{}: {}", reason, attribute));
+        }
+    }
+
+    let parsed_attributes = task_block_attributes(&parsed_task);
+
+    for (index, attribute) in attributes.iter().enumerate() {
+        if parsed_attributes.get(index) != Some(attribute) {
+            return Err(format!("Unable to write task attribute as given: {}", attribute));
+        }
+    }
+
+    return Ok(attributes);
+}
+
+// append task blocks to the file; returns the line number of the first appended block.
+// nothing is written if a directive of the file (e.g. `require.project`) rejects any of the tasks.
+fn append_task_blocks(path_to_file: &str, blocks: &[Vec<String>]) -> Result<u64, String> {
+
+    let path = Path::new(path_to_file);
+
+    let mut existing = String::new();
+
+    if path.exists() {
+
+        if !path.is_file() {
+            return Err(format!("Path is not a file: {}", path_to_file));
+        }
+
+        let read = File::open(path).and_then(|mut file| file.read_to_string(&mut existing));
+
+        if read.is_err() {
+            return Err(format!("Unable to read file: {}", path_to_file));
+        }
+    }
+
+    let mut appended = String::new();
+
+    if existing.len() > 0 && !existing.ends_with('\n') {
+        appended.push('\n');
+    }
+
+    // blocks are separated by a blank line
+    let ends_with_blank_line = existing.lines().last().map(|line| line.trim().len() <= 0).unwrap_or(true);
+
+    if !ends_with_blank_line {
+        appended.push('\n');
+    }
+
+    let first_line: u64 = format!("{}{}", existing, appended).matches('\n').count() as u64 + 1;
+
+    for (index, block) in blocks.iter().enumerate() {
+
+        if index > 0 {
+            appended.push('\n');
+        }
+
+        for attribute in block {
+            appended.push_str(attribute);
+            appended.push('\n');
+        }
+    }
+
+    // dry run with the directives of the file
+    if path.is_file() {
+
+        let tracked_path = match path.canonicalize() {
+            Ok(resolved) => format!("{}", resolved.display()),
+            Err(_) => {
+                return Err(format!("Unable to resolve path: {}", path_to_file));
+            }
+        };
+
+        let mut documents: HashMap<String, String> = HashMap::new();
+        documents.insert(tracked_path.clone(), format!("{}{}", existing, appended));
+
+        let journal = parse_journal_leniently(&tracked_path, &documents);
+
+        if let Some(ref diagnostics) = journal.diagnostics {
+            for diagnostic in diagnostics {
+                if diagnostic.path == tracked_path && diagnostic.line_number >= first_line {
+                    return Err(format!("{}:{} {}", path_to_file, diagnostic.line_number, diagnostic.message));
+                }
+            }
+        }
+    }
+
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(appended.as_bytes()));
+
+    if written.is_err() {
+        return Err(format!("Unable to write to file: {}", path_to_file));
+    }
+
+    return Ok(first_line);
+}
+
+// add subcommand
+fn add_task_command(journal: &GTD, path_to_file: &str, matches: &ArgMatches) {
+
+    let mut task = Task::new(1);
+
+    task.title = matches.value_of("title").map(|title| title.trim().to_owned());
+    task.created_at = Some(journal.now);
+    task.flag = matches.is_present("flag");
+    task.note = matches.value_of("note").map(|note| note.trim().to_owned());
+
+    if let Some(project_path) = matches.value_of("project") {
+        match parse_only(|i| parse_string_lists(i, b'/'), project_path.as_bytes()) {
+            Ok(project_path) => {
+                if project_path.len() > 0 {
+                    task.project = Some(project_path);
+                }
+            },
+            Err(_) => {
+                println!("Unable to parse project path `--project`: {}", project_path);
+                process::exit(1);
+            }
+        }
+    }
+
+    for &(option, is_tags) in &[("tag", true), ("context", false)] {
+
+        let mut list: Vec<String> = Vec::new();
+
+        let values: Vec<&str> = match matches.values_of(option) {
+            Some(values) => values.collect(),
+            None => vec![]
+        };
+
+        for value in values {
+            match parse_only(|i| parse_string_lists(i, b','), value.as_bytes()) {
+                Ok(values) => {
+                    list.extend(values);
+                },
+                Err(_) => {
+                    println!("Unable to parse value to option `--{}`: {}", option, value);
+                    process::exit(1);
+                }
+            }
+        }
+
+        if list.len() <= 0 {
+            continue;
+        }
+
+        if is_tags {
+            task.tags = Some(list);
+        } else {
+            task.contexts = Some(list);
+        }
+    }
+
+    if let Some(priority) = matches.value_of("priority") {
+        match priority.trim().parse::<i64>() {
+            Ok(priority) => {
+                task.priority = priority;
+            },
+            Err(_) => {
+                println!("Invalid value to option `--priority`: {}", priority);
+                process::exit(1);
+            }
+        }
+    }
+
+    let now = journal.now;
+
+    if let Some(due_at) = matches.value_of("due") {
+        match parse_only(|i| parse_relative_datetime_option(i, true, now), due_at.as_bytes()) {
+            Ok(Ok(due_at)) => {
+                task.due_at = Some(due_at);
+            },
+            Ok(Err(reason)) => {
+                println!("Invalid value to option `--due`: {}", reason);
+                process::exit(1);
+            },
+            Err(_) => {
+                println!("Unable to parse value to option `--due`: {}", due_at);
+                process::exit(1);
+            }
+        }
+    }
+
+    if let Some(defer_till) = matches.value_of("defer") {
+
+        if defer_till.trim().to_lowercase() == "forever" {
+            task.defer = Some(Defer::Forever);
+        } else {
+            match parse_only(|i| parse_relative_datetime_option(i, false, now), defer_till.as_bytes()) {
+                Ok(Ok(defer_till)) => {
+                    task.defer = Some(Defer::Until(defer_till));
+                },
+                Ok(Err(reason)) => {
+                    println!("Invalid value to option `--defer`: {}", reason);
+                    process::exit(1);
+                },
+                Err(_) => {
+                    println!("Unable to parse value to option `--defer`: {}", defer_till);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    let block = match validate_task_block(&task) {
+        Ok(block) => block,
+        Err(reason) => {
+            println!("Unable to add task: {}", reason);
+            process::exit(1);
+        }
+    };
+
+    let path_to_inbox: &str = matches.value_of("inbox").unwrap_or(path_to_file);
+
+    match append_task_blocks(path_to_inbox, &[block]) {
+        Ok(line_number) => {
+            println!("Added task to {}:{}", path_to_inbox, line_number);
+        },
+        Err(reason) => {
+            println!("Unable to add task: {}", reason);
+            process::exit(1);
+        }
+    };
+}
+
+#[test]
+fn task_block_writer_test() {
+
+    let mut task = Task::new(1);
+    task.title = Some("buy milk".to_owned());
+    task.project = Some(vec!["personal".to_owned(), "food".to_owned()]);
+    task.tags = Some(vec!["grocery".to_owned(), "weekly".to_owned()]);
+    task.priority = -3;
+    task.due_at = Some(NaiveDate::from_ymd(2016, 2, 29).and_hms(23, 59, 0));
+    task.defer = Some(Defer::Until(NaiveDate::from_ymd(2016, 2, 28).and_hms(9, 5, 0)));
+    task.note = Some("2% milk\n\nfrom the corner store".to_owned());
+
+    assert_eq!(validate_task_block(&task), Ok(vec![
+        "task: buy milk".to_owned(),
+        "project: personal / food".to_owned(),
+        "tags: grocery, weekly".to_owned(),
+        "priority: -3".to_owned(),
+        "due: February 29, 2016 23:59".to_owned(),
+        "defer: February 28, 2016 09:05".to_owned(),
+        "notes: 2% milk\n\n    from the corner store".to_owned()
+    ]));
+
+    // tags may not contain the list delimiter
+    task.tags = Some(vec!["a, b".to_owned()]);
+    assert!(validate_task_block(&task).is_err());
+
+    task.tags = None;
+    task.title = Some("first line\nsecond line".to_owned());
+    assert!(validate_task_block(&task).is_err());
+
+    task.title = None;
+    assert!(validate_task_block(&task).is_err());
+}
+
+/* capture parser */
+
+// parse a quick-add capture line into a task.
+// e.g. call mom @phone +family/parents #weekly !5 due:fri defer:mon
+//
+// - @context, +project/path, #tag, !priority
+// - due:<datetime> and defer:<datetime|forever>; the datetime may span several words (e.g. due:tomorrow 5pm)
+// - any other word is part of the title; a leading backslash escapes a word (e.g. \#1)
+fn parse_capture_line(line: &str, now: NaiveDateTime) -> Result<Task, String> {
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    let mut task = Task::new(1);
+    task.created_at = Some(now);
+
+    let mut title: Vec<String> = Vec::new();
+    let mut contexts: Vec<String> = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+
+    let mut index = 0;
+
+    while index < words.len() {
+
+        let word = words[index];
+        index += 1;
+
+        if word.starts_with('\\') && word.len() > 1 {
+            title.push(word[1..].to_owned());
+            continue;
+        }
+
+        let datetime_option = strip_prefix_ignore_ascii_case(word, "due:")
+            .map(|value| (true, value))
+            .or_else(|| strip_prefix_ignore_ascii_case(word, "defer:").map(|value| (false, value)));
+
+        if let Some((is_due, value)) = datetime_option {
+
+            // datetime may continue onto the following words, up to the next marker
+            let mut value_words: Vec<&str> = Vec::new();
+
+            if value.len() > 0 {
+                value_words.push(value);
+            }
+
+            let mut end = index;
+            while end < words.len() && !is_capture_marker(words[end]) {
+                end += 1;
+            }
+
+            value_words.extend(&words[index..end]);
+
+            if !is_due && value.to_lowercase() == "forever" {
+                if task.defer.is_some() {
+                    return Err("Task cannot be deferred more than once.".to_owned());
+                }
+                task.defer = Some(Defer::Forever);
+                continue;
+            }
+
+            let option_name = if is_due { "due" } else { "defer" };
+
+            // longest run of words that is a datetime
+            let mut parsed: Option<(usize, Result<NaiveDateTime, String>)> = None;
+
+            for num_of_words in (1..(value_words.len() + 1)).rev() {
+
+                let candidate = value_words[..num_of_words].join(" ");
+
+                match parse_only(|i| parse_relative_datetime_option(i, is_due, now), candidate.as_bytes()) {
+                    Ok(result) => {
+                        parsed = Some((num_of_words, result));
+                        break;
+                    },
+                    Err(_) => {}
+                };
+            }
+
+            let (num_of_words, result) = match parsed {
+                Some(parsed) => parsed,
+                None => {
+                    return Err(format!("Unable to parse {} datetime: {}", option_name, value_words.join(" ")));
+                }
+            };
+
+            let datetime = match result {
+                Ok(datetime) => datetime,
+                Err(reason) => {
+                    return Err(format!("Invalid {} datetime: {}", option_name, reason));
+                }
+            };
+
+            // words of the datetime following the marker
+            index += if value.len() > 0 { num_of_words - 1 } else { num_of_words };
+
+            if is_due {
+                if task.due_at.is_some() {
+                    return Err("Task cannot have more than one due datetime.".to_owned());
+                }
+                task.due_at = Some(datetime);
+            } else {
+                if task.defer.is_some() {
+                    return Err("Task cannot be deferred more than once.".to_owned());
+                }
+                task.defer = Some(Defer::Until(datetime));
+            }
+
+            continue;
+        }
+
+        // split off the marker by character; words may begin with a multibyte character
+        let mut chars = word.chars();
+        let marker = chars.next().unwrap();
+        let value = chars.as_str();
+
+        if value.len() <= 0 {
+            title.push(word.to_owned());
+            continue;
+        }
+
+        match marker {
+            '@' => {
+                contexts.push(value.to_owned());
+            },
+            '#' => {
+                tags.push(value.to_owned());
+            },
+            '+' => {
+
+                if task.project.is_some() {
+                    return Err("Task cannot have more than one project.".to_owned());
+                }
+
+                match parse_only(|i| parse_string_lists(i, b'/'), value.as_bytes()) {
+                    Ok(ref project_path) if project_path.len() > 0 => {
+                        task.project = Some(project_path.clone());
+                    },
+                    _ => {
+                        return Err(format!("Unable to parse project path: {}", value));
+                    }
+                };
+            },
+            '!' if value.parse::<i64>().is_ok() => {
+                task.priority = value.parse::<i64>().unwrap();
+            },
+            _ => {
+                title.push(word.to_owned());
+            }
+        };
+    }
+
+    if title.len() <= 0 {
+        return Err("Missing task title".to_owned());
+    }
+
+    task.title = Some(title.join(" "));
+
+    if contexts.len() > 0 {
+        task.contexts = Some(contexts);
+    }
+
+    if tags.len() > 0 {
+        task.tags = Some(tags);
+    }
+
+    return Ok(task);
+}
+
+fn is_capture_marker(word: &str) -> bool {
+
+    if strip_prefix_ignore_ascii_case(word, "due:").is_some() ||
+        strip_prefix_ignore_ascii_case(word, "defer:").is_some() {
+        return true;
+    }
+
+    let mut chars = word.chars();
+
+    let marker = match chars.next() {
+        Some(marker) => marker,
+        None => {
+            return false;
+        }
+    };
+
+    let value = chars.as_str();
+
+    if value.len() <= 0 {
+        return false;
+    }
+
+    match marker {
+        '@' | '#' | '+' | '\\' => true,
+        '!' => value.parse::<i64>().is_ok(),
+        _ => false
+    }
+}
+
+// remainder of the word following the given ASCII prefix, compared case-insensitively
+fn strip_prefix_ignore_ascii_case<'a>(word: &'a str, prefix: &str) -> Option<&'a str> {
+
+    if word.len() < prefix.len() || !word.is_char_boundary(prefix.len()) {
+        return None;
+    }
+
+    if !word[..prefix.len()].eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+
+    return Some(&word[prefix.len()..]);
+}
+
+// capture subcommand
+fn capture_command(journal: &GTD, path_to_file: &str, matches: &ArgMatches) {
+
+    let lines: Vec<String> = match matches.values_of("lines") {
+        Some(lines) => lines.map(|line| line.to_owned()).collect(),
+        None => {
+            let stdin = io::stdin();
+            let lines: Vec<String> = stdin.lock().lines().map(|line| line.unwrap_or_else(|_| {
+                println!("Unable to read from stdin.");
+                process::exit(1);
+            })).collect();
+            lines
+        }
+    };
+
+    let mut blocks: Vec<Vec<String>> = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+
+        if line.trim().len() <= 0 {
+            continue;
+        }
+
+        let block = parse_capture_line(line, journal.now).and_then(|task| validate_task_block(&task));
+
+        match block {
+            Ok(block) => {
+                blocks.push(block);
+            },
+            Err(reason) => {
+                println!("Unable to capture task on line {}: {}", index + 1, reason);
+                println!("{}", line.trim());
+                process::exit(1);
+            }
+        };
+    }
+
+    if blocks.len() <= 0 {
+        println!("No tasks to capture.");
+        return;
+    }
+
+    let path_to_inbox: &str = matches.value_of("inbox").unwrap_or(path_to_file);
+
+    match append_task_blocks(path_to_inbox, &blocks) {
+        Ok(line_number) => {
+            println!("Captured {} to {}:{}", pluralize_tasks(blocks.len()), path_to_inbox, line_number);
+        },
+        Err(reason) => {
+            println!("Unable to capture tasks: {}", reason);
+            process::exit(1);
+        }
+    };
+}
+
+#[test]
+fn parse_capture_line_test() {
+
+    // Wednesday
+    let now = NaiveDate::from_ymd(2016, 5, 4).and_hms(10, 30, 0);
+
+    let task = parse_capture_line("call mom @phone +family/parents #weekly !5 due:fri defer:mon", now).unwrap();
+
+    assert_eq!(task.title, Some("call mom".to_owned()));
+    assert_eq!(task.contexts, Some(vec!["phone".to_owned()]));
+    assert_eq!(task.project, Some(vec!["family".to_owned(), "parents".to_owned()]));
+    assert_eq!(task.tags, Some(vec!["weekly".to_owned()]));
+    assert_eq!(task.priority, 5);
+    assert_eq!(task.due_at, Some(NaiveDate::from_ymd(2016, 5, 6).and_hms(23, 59, 0)));
+    assert_eq!(task.defer, Some(Defer::Until(NaiveDate::from_ymd(2016, 5, 9).and_hms(0, 0, 0))));
+    assert_eq!(task.created_at, Some(now));
+
+    // datetimes may span words; markers may appear anywhere
+    let task = parse_capture_line("!-2 pay rent due:tomorrow 5pm to landlord \\#2 defer:forever", now).unwrap();
+
+    assert_eq!(task.title, Some("pay rent to landlord #2".to_owned()));
+    assert_eq!(task.priority, -2);
+    assert_eq!(task.due_at, Some(NaiveDate::from_ymd(2016, 5, 5).and_hms(17, 0, 0)));
+    assert_eq!(task.defer, Some(Defer::Forever));
+
+    // words may begin with a multibyte character
+    let task = parse_capture_line("écrire rapport @bureau #été +équipe/réunion", now).unwrap();
+
+    assert_eq!(task.title, Some("écrire rapport".to_owned()));
+    assert_eq!(task.contexts, Some(vec!["bureau".to_owned()]));
+    assert_eq!(task.tags, Some(vec!["été".to_owned()]));
+    assert_eq!(task.project, Some(vec!["équipe".to_owned(), "réunion".to_owned()]));
+
+    let task = parse_capture_line("appeler à 5 heures é @téléphone due:fri", now).unwrap();
+
+    assert_eq!(task.title, Some("appeler à 5 heures é".to_owned()));
+    assert_eq!(task.contexts, Some(vec!["téléphone".to_owned()]));
+    assert_eq!(task.due_at, Some(NaiveDate::from_ymd(2016, 5, 6).and_hms(23, 59, 0)));
+
+    assert!(parse_capture_line("@phone #weekly", now).is_err());
+    assert!(parse_capture_line("call mom due:someday", now).is_err());
+    assert!(parse_capture_line("call mom due:feb 30, 2016", now).is_err());
+    assert!(parse_capture_line("call mom +a +b", now).is_err());
+}
+
+/* task block editor */
+
+// an attribute to write into an existing task block
+enum TaskBlockEdit {
+    // replace the attribute of the same kind that takes effect (i.e. the last one), or append it
+    Set(String),
+    // append the attribute to the end of the task block
+    Append(String)
+}
+
+// lines of the text, each with its line ending; joining them yields the text as is
+fn split_source_lines(text: &str) -> Vec<String> {
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut start = 0;
+
+    for (index, _) in text.match_indices('\n') {
+        lines.push(text[start..(index + 1)].to_owned());
+        start = index + 1;
+    }
+
+    if start < text.len() {
+        lines.push(text[start..].to_owned());
+    }
+
+    return lines;
+}
+
+fn line_ending(line: &str) -> &str {
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+// attributes of the same kind replace each other within a task block
+fn task_block_kind(task_block_line: &TaskBlock) -> &'static str {
+    match *task_block_line {
+        TaskBlock::Current => "current",
+        TaskBlock::Title(_) => "task",
+        TaskBlock::Created(_) => "created",
+        TaskBlock::Done(_) => "done",
+        TaskBlock::Chain(_) => "chain",
+        TaskBlock::Due(_) => "due",
+        TaskBlock::Defer(_) => "defer",
+        TaskBlock::Priority(_) => "priority",
+        TaskBlock::Time(_) => "time",
+        TaskBlock::Project(_) => "project",
+        TaskBlock::Status(_) => "status",
+        TaskBlock::Contexts(_) => "contexts",
+        TaskBlock::Tags(_) => "tags",
+        TaskBlock::Flag(_) => "flag",
+        TaskBlock::Note(_) => "notes",
+        TaskBlock::ID(_) => "id",
+        TaskBlock::Invalid(_) => "invalid"
+    }
+}
+
+// kind of the task block attribute on the given line, if any
+fn source_line_kind(line: &str) -> Option<&'static str> {
+
+    let input = format!("{}\n", line.trim_right_matches(|c| c == '\r' || c == '\n'));
+
+    match parse_only(|i| task_block_attribute(i), input.as_bytes()) {
+        Ok(task_block_line) => Some(task_block_kind(&task_block_line)),
+        Err(_) => None
+    }
+}
+
+// edit the task block of the task within the text of its source file.
+// every line other than the edited ones is kept as is.
+fn edit_task_block(text: &str, task: &Task, edits: &[TaskBlockEdit]) -> Result<String, String> {
+
+    let mut lines = split_source_lines(text);
+
+    let start = task.task_block_range_start as usize;
+    let mut end = task.task_block_range_end as usize;
+
+    if start < 1 || end < start || end > lines.len() {
+        return Err(format!("Task block {} is not within the file.", task.debug_range_string()));
+    }
+
+    // new lines use the line ending of the task block, or else of the file
+    let ending: String = lines[(start - 1)..end].iter()
+        .chain(lines.iter())
+        .map(|line| line_ending(line))
+        .find(|ending| ending.len() > 0)
+        .unwrap_or("\n")
+        .to_owned();
+
+    for edit in edits {
+
+        let (attribute, should_replace) = match *edit {
+            TaskBlockEdit::Set(ref attribute) => (attribute, true),
+            TaskBlockEdit::Append(ref attribute) => (attribute, false)
+        };
+
+        let kind = match source_line_kind(attribute) {
+            Some(kind) => kind,
+            None => {
+                return Err(format!("Unable to parse task attribute: {}", attribute));
+            }
+        };
+
+        if should_replace {
+
+            let replaced = (start..(end + 1)).rev().find(|&line_number| {
+                source_line_kind(&lines[line_number - 1]) == Some(kind)
+            });
+
+            if let Some(line_number) = replaced {
+                let replaced_ending = line_ending(&lines[line_number - 1]).to_owned();
+                lines[line_number - 1] = format!("{}{}", attribute, replaced_ending);
+                continue;
+            }
+        }
+
+        // the last line of the file may not have a line ending
+        if line_ending(&lines[end - 1]).len() <= 0 {
+            lines[end - 1].push_str(&ending);
+        }
+
+        lines.insert(end, format!("{}{}", attribute, ending));
+        end += 1;
+    }
+
+    return Ok(lines.concat());
+}
+
+// find the task given by an id, a file:line location, or a case-insensitive search of titles
+fn select_task(journal: &GTD, selector: &str) -> Result<u64, String> {
+
+    let selector = selector.trim();
+
+    let mut tasks: Vec<(&u64, &Task)> = journal.tasks.iter().collect();
+    tasks.sort_by(|&(_, a), &(_, b)| {
+        (&a.source_file, a.task_block_range_start).cmp(&(&b.source_file, b.task_block_range_start))
+    });
+
+    // id
+    let found: Vec<(&u64, &Task)> = tasks.iter()
+        .filter(|&&(_, task)| task.id.as_ref().map(|id| id == selector).unwrap_or(false))
+        .cloned()
+        .collect();
+
+    if found.len() > 0 {
+        return single_selected_task(journal, selector, found);
+    }
+
+    // file:line; titles may look like one too (e.g. meeting at 10:30), so a missing file falls through
+    let mut location_error: Option<String> = None;
+
+    if let Some(index) = selector.rfind(':') {
+
+        let (path, line_number) = (&selector[..index], &selector[(index + 1)..]);
+
+        let resolved = line_number.parse::<u64>().ok().map(|line_number| {
+            (Path::new(path).canonicalize(), line_number)
+        });
+
+        match resolved {
+            None => {},
+            Some((Err(_), _)) => {
+                location_error = Some(format!("Unable to find file: {}", path));
+            },
+            Some((Ok(resolved), line_number)) => {
+
+                let path = format!("{}", resolved.display());
+
+                let found: Vec<(&u64, &Task)> = tasks.iter()
+                    .filter(|&&(_, task)| {
+                        task.source_file.as_ref() == Some(&path) &&
+                        task.task_block_range_start <= line_number &&
+                        line_number <= task.task_block_range_end
+                    })
+                    .cloned()
+                    .collect();
+
+                if found.len() <= 0 {
+                    return Err(format!("No task found at: {}", selector));
+                }
+
+                return single_selected_task(journal, selector, found);
+            }
+        }
+    }
+
+    // title search
+    let search = selector.to_lowercase();
+
+    let found: Vec<(&u64, &Task)> = tasks.iter()
+        .filter(|&&(_, task)| {
+            task.title.as_ref().map(|title| title.to_lowercase().contains(&search)).unwrap_or(false)
+        })
+        .cloned()
+        .collect();
+
+    if found.len() <= 0 {
+        return Err(location_error.unwrap_or_else(|| format!("No task found matching: {}", selector)));
+    }
+
+    // prefer tasks that are not done
+    let not_done: Vec<(&u64, &Task)> = found.iter().filter(|&&(_, task)| !task.is_done()).cloned().collect();
+
+    if not_done.len() > 0 {
+        return single_selected_task(journal, selector, not_done);
+    }
+
+    return single_selected_task(journal, selector, found);
+}
+
+fn select_task_or_exit(journal: &GTD, selector: &str) -> u64 {
+    match select_task(journal, selector) {
+        Ok(task_key) => task_key,
+        Err(reason) => {
+            println!("{}", reason);
+            process::exit(1);
+        }
+    }
+}
+
+fn single_selected_task(journal: &GTD, selector: &str, found: Vec<(&u64, &Task)>) -> Result<u64, String> {
+
+    if found.len() == 1 {
+        return Ok(*found[0].0);
+    }
+
+    let mut reason = format!("Found {} tasks matching: {}", found.len(), selector);
+
+    for (_, task) in found {
+        reason.push_str(&format!("\n{:>11} {} ({}:{})",
+            "",
+            task.title.as_ref().unwrap(),
+            task_source_location(journal, task),
+            task.task_block_range_start));
+    }
+
+    return Err(reason);
+}
+
+// path of the source file of the task, relative to the current directory if possible
+fn task_source_location(journal: &GTD, task: &Task) -> String {
+
+    let path = task.source_file.clone().unwrap_or_else(String::new);
+
+    match Path::new(&path).strip_prefix(&journal.base_root) {
+        Err(_) => path.clone(),
+        Ok(path) => format!("./{}", path.display())
+    }
+}
+
+#[test]
+fn select_task_test() {
+
+    let mut journal = GTD::new("/".to_owned());
+
+    let mut task = Task::new(1);
+    task.title = Some("standup at 10:30".to_owned());
+    task.source_file = Some("/path/to/todo.gtd".to_owned());
+    journal.tasks.insert(1, task);
+
+    let mut task = Task::new(4);
+    task.title = Some("buy milk".to_owned());
+    task.source_file = Some("/path/to/todo.gtd".to_owned());
+    task.id = Some("milk".to_owned());
+    journal.tasks.insert(4, task);
+
+    assert_eq!(select_task(&journal, "milk"), Ok(4));
+    assert_eq!(select_task(&journal, "standup at 10:30"), Ok(1));
+    assert_eq!(select_task(&journal, "standup at 10:31"),
+        Err("Unable to find file: standup at 10".to_owned()));
+    assert_eq!(select_task(&journal, "groceries"),
+        Err("No task found matching: groceries".to_owned()));
+
+    assert_eq!(select_task(&journal, "u"), Err(format!("Found 2 tasks matching: u\n\
+        {:>11} standup at 10:30 (./path/to/todo.gtd:1)\n\
+        {:>11} buy milk (./path/to/todo.gtd:4)", "", "")));
+}
+
+// edit the task block of the selected task in its source file.
+// the journal is parsed again with the edited file, and nothing is written if that adds any parse errors,
+// or if the edited task does not pass the given check (e.g. directives may override the edited attributes).
+fn edit_selected_task(journal: &GTD, path_to_file: &str, task_key: u64, edits: &[TaskBlockEdit],
+    check: &Fn(&Task) -> Result<(), String>) -> Result<(), String> {
+
+    let task: &Task = journal.tasks.get(&task_key).unwrap();
+    let path = task.source_file.clone().unwrap();
+
+    let mut text = String::new();
+
+    let read = File::open(&path).and_then(|mut file| file.read_to_string(&mut text));
+
+    if read.is_err() {
+        return Err(format!("Unable to read file: {}", path));
+    }
+
+    let edited = match edit_task_block(&text, task, edits) {
+        Ok(edited) => edited,
+        Err(reason) => {
+            return Err(reason);
+        }
+    };
+
+    let root = match Path::new(path_to_file).canonicalize() {
+        Ok(resolved) => format!("{}", resolved.display()),
+        Err(_) => {
+            return Err(format!("Unable to resolve path: {}", path_to_file));
+        }
+    };
+
+    let mut documents: HashMap<String, String> = HashMap::new();
+    documents.insert(path.clone(), edited.clone());
+
+    let edited_journal = parse_journal_leniently(&root, &documents);
+
+    // warnings of the journal when parsed in lenient mode are already known
+    if edited_journal.num_of_warnings > journal.num_of_warnings {
+        if let Some(ref diagnostics) = edited_journal.diagnostics {
+
+            let diagnostic = diagnostics.iter()
+                .find(|diagnostic| diagnostic.path == path)
+                .or(diagnostics.first());
+
+            if let Some(diagnostic) = diagnostic {
+                return Err(format!("{}:{} {}", diagnostic.path, diagnostic.line_number, diagnostic.message));
+            }
+        }
+    }
+
+    // edits are within the task block, so it starts on the same line
+    let edited_task = edited_journal.tasks.values().find(|edited_task| {
+        edited_task.source_file.as_ref() == Some(&path) &&
+        edited_task.task_block_range_start == task.task_block_range_start
+    });
+
+    match edited_task {
+        None => {
+            return Err(format!("Task is missing after editing: {}:{}", path, task.task_block_range_start));
+        },
+        Some(edited_task) => {
+            if let Err(reason) = check(edited_task) {
+                return Err(reason);
+            }
+        }
+    };
+
+    let written = File::create(&path).and_then(|mut file| file.write_all(edited.as_bytes()));
+
+    if written.is_err() {
+        return Err(format!("Unable to write to file: {}", path));
+    }
+
+    return Ok(());
+}
+
+// the status of a task may be overridden by directives (e.g. inject.status)
+fn check_task_done(task: &Task) -> Result<(), String> {
+
+    if !task.is_done() {
+        return Err(format!("Task would not be done; its status is {}.",
+            task.status.as_ref().map(|status| status.string()).unwrap_or_else(|| Status::NotDone.string())));
+    }
+
+    return Ok(());
+}
+
+// done subcommand
+fn done_task_command(journal: &GTD, path_to_file: &str, matches: &ArgMatches) {
+
+    let task_key = select_task_or_exit(journal, matches.value_of("selector").unwrap());
+    let task: &Task = journal.tasks.get(&task_key).unwrap();
+
+    let location = format!("{}:{}", task_source_location(journal, task), task.task_block_range_start);
+
+    if task.is_done() {
+        println!("Task is already done: {} ({})", task.title.as_ref().unwrap(), location);
+        process::exit(1);
+    }
+
+    let edits = vec![
+        TaskBlockEdit::Set("status: done".to_owned()),
+        TaskBlockEdit::Set(format!("done: {}", block_datetime(&journal.now)))
+    ];
+
+    match edit_selected_task(journal, path_to_file, task_key, &edits, &check_task_done) {
+        Ok(_) => {
+            println!("Marked task as done: {} ({})", task.title.as_ref().unwrap(), location);
+        },
+        Err(reason) => {
+            println!("Unable to mark task as done: {}", reason);
+            process::exit(1);
+        }
+    };
+}
+
+#[test]
+fn done_task_test() {
+
+    let now = NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 0, 0);
+    let edits = vec![TaskBlockEdit::Set("status: done".to_owned())];
+
+    let text = "task: buy milk\n";
+    let journal = parse_test_journal("done", text, now);
+    let path = format!("{}", env::temp_dir().join("gtdtxt-done.gtd").display());
+    let task_key = *journal.tasks.keys().next().unwrap();
+
+    assert_eq!(edit_selected_task(&journal, &path, task_key, &edits, &check_task_done), Ok(()));
+
+    let mut edited = String::new();
+    File::open(&path).unwrap().read_to_string(&mut edited).unwrap();
+    assert_eq!(edited, "task: buy milk\nstatus: done\n".to_owned());
+
+    // inject.status overrides the status; the file is left as is
+    let text = "inject.status: active\n\ntask: buy milk\n";
+    let journal = parse_test_journal("done", text, now);
+    let task_key = *journal.tasks.keys().next().unwrap();
+
+    assert!(edit_selected_task(&journal, &path, task_key, &edits, &check_task_done).is_err());
+
+    let mut edited = String::new();
+    File::open(&path).unwrap().read_to_string(&mut edited).unwrap();
+    assert_eq!(edited, text.to_owned());
+}
+
+#[test]
+fn edit_task_block_test() {
+
+    let text = "// errands\r\ntask: buy milk\r\nstatus: active\r\nnotes: 2%\r\n    from the corner store\r\n\r\ntask: other";
+
+    let mut task = Task::new(2);
+    task.task_block_range_end = 5;
+
+    let edits = vec![
+        TaskBlockEdit::Set("status: done".to_owned()),
+        TaskBlockEdit::Set("done: May 4, 2016 10:30".to_owned()),
+        TaskBlockEdit::Append("chain: May 4, 2016 10:30".to_owned())
+    ];
+
+    assert_eq!(edit_task_block(text, &task, &edits), Ok(
+        "// errands\r\ntask: buy milk\r\nstatus: done\r\nnotes: 2%\r\n    from the corner store\r\n\
+        done: May 4, 2016 10:30\r\nchain: May 4, 2016 10:30\r\n\r\ntask: other".to_owned()
+    ));
+
+    // last line of the file without a line ending
+    let mut task = Task::new(7);
+    task.task_block_range_end = 7;
+
+    assert_eq!(edit_task_block(text, &task, &edits[1..2]), Ok(
+        "// errands\r\ntask: buy milk\r\nstatus: active\r\nnotes: 2%\r\n    from the corner store\r\n\r\n\
+        task: other\r\ndone: May 4, 2016 10:30\r\n".to_owned()
+    ));
+
+    assert_eq!(split_source_lines(text).concat(), text);
+
+    let mut task = Task::new(8);
+    task.task_block_range_end = 8;
+    assert!(edit_task_block(text, &task, &edits).is_err());
+}
+
+/* archive */
+
+// one-line comment (i.e. not a task separator) that is attached to the line below it
+fn is_comment_line(line: &str) -> bool {
+
+    let line = line.trim();
+
+    if !(line.starts_with("//") || line.starts_with('#') || line.starts_with(';')) {
+        return false;
+    }
+
+    let input = format!("{}\n", line);
+
+    parse_only(|i| task_seperators(i), input.as_bytes()).is_err()
+}
+
+// first line of the comments directly above the given line (i.e. without blank lines between them)
+fn attached_comments_start(lines: &[String], line_number: usize) -> usize {
+
+    let mut start = line_number;
+
+    while start > 1 && is_comment_line(&lines[start - 2]) {
+        start -= 1;
+    }
+
+    return start;
+}
+
+fn is_blank_line(lines: &[String], line_number: usize) -> bool {
+    line_number >= 1 && line_number <= lines.len() && lines[line_number - 1].trim().len() <= 0
+}
+
+// task blocks moved out of a file
+struct ArchivedBlocks {
+    // text of the file without the archived task blocks
+    text: String,
+    // each block with its attached comments, and the status and project path of the task stamped onto it
+    blocks: Vec<Vec<String>>
+}
+
+fn remove_task_blocks(text: &str, tasks: &[&Task]) -> Result<ArchivedBlocks, String> {
+
+    let lines = split_source_lines(text);
+    let mut removed: Vec<bool> = vec![false; lines.len()];
+    let mut blocks: Vec<Vec<String>> = Vec::new();
+
+    for task in tasks {
+
+        let start = task.task_block_range_start as usize;
+        let end = task.task_block_range_end as usize;
+
+        if start < 1 || end < start || end > lines.len() {
+            return Err(format!("Task block {} is not within the file.", task.debug_range_string()));
+        }
+
+        let comments_start = attached_comments_start(&lines, start);
+
+        // stamp the status and project path onto the task block; either may come from directives
+        // (e.g. default.status, inject.project.prefix) that do not apply in the archive file
+        let mut block_task = Task::new(1);
+        block_task.task_block_range_end = (end - start + 1) as u64;
+
+        let mut edits: Vec<TaskBlockEdit> = Vec::new();
+
+        if let Some(ref status) = task.status {
+            edits.push(TaskBlockEdit::Set(format!("status: {}", status.string().to_lowercase())));
+        }
+
+        if let Some(ref project_path) = task.project {
+            edits.push(TaskBlockEdit::Set(format!("project: {}", project_path.join(" / "))));
+        }
+
+        let block_text = match edit_task_block(&lines[(start - 1)..end].concat(), &block_task, &edits) {
+            Ok(block_text) => block_text,
+            Err(reason) => {
+                return Err(reason);
+            }
+        };
+
+        let mut block: Vec<String> = lines[(comments_start - 1)..(start - 1)].iter()
+            .map(|line| line.trim_right_matches(|c| c == '\r' || c == '\n').to_owned())
+            .collect();
+
+        block.extend(block_text.lines().map(|line| line.to_owned()));
+
+        blocks.push(block);
+
+        for line_number in comments_start..(end + 1) {
+            removed[line_number - 1] = true;
+        }
+
+        // avoid leaving consecutive blank lines behind
+        if is_blank_line(&lines, end + 1) && (comments_start == 1 || is_blank_line(&lines, comments_start - 1)) {
+            removed[end] = true;
+        }
+    }
+
+    let text: String = lines.iter().zip(removed.iter())
+        .filter(|&(_, &removed)| !removed)
+        .map(|(line, _)| line.as_str())
+        .collect::<Vec<&str>>()
+        .concat();
+
+    return Ok(ArchivedBlocks {
+        text: text,
+        blocks: blocks
+    });
+}
+
+// archive subcommand
+fn archive_command(journal: &GTD, path_to_file: &str, matches: &ArgMatches) {
+
+    let path_to_archive = matches.value_of("archive file").unwrap().trim();
+
+    let older_than: Option<NaiveDateTime> = matches.value_of("older-than").map(|days| {
+        journal.now - Duration::days(days.trim().parse::<i64>().unwrap())
+    });
+
+    // tasks of the archive file itself are left as is
+    let archive_path: Option<String> = Path::new(path_to_archive).canonicalize().ok()
+        .map(|resolved| format!("{}", resolved.display()));
+
+    let mut tasks_by_file: BTreeMap<String, Vec<&Task>> = BTreeMap::new();
+
+    for task in journal.tasks.values() {
+
+        if !task.is_done() {
+            continue;
+        }
+
+        if let Some(older_than) = older_than {
+            match task.done_at {
+                Some(done_at) if done_at <= older_than => {},
+                _ => {
+                    continue;
+                }
+            };
+        }
+
+        let source_file = task.source_file.clone().unwrap();
+
+        if Some(&source_file) == archive_path.as_ref() {
+            continue;
+        }
+
+        tasks_by_file.entry(source_file).or_insert_with(Vec::new).push(task);
+    }
+
+    if tasks_by_file.len() <= 0 {
+        println!("No done tasks to archive.");
+        return;
+    }
+
+    let mut documents: HashMap<String, String> = HashMap::new();
+    let mut blocks: Vec<Vec<String>> = Vec::new();
+    let mut num_of_tasks_by_file: Vec<(String, usize)> = Vec::new();
+
+    for (source_file, tasks) in tasks_by_file.iter_mut() {
+
+        tasks.sort_by_key(|task| task.task_block_range_start);
+
+        let mut text = String::new();
+
+        let read = File::open(source_file).and_then(|mut file| file.read_to_string(&mut text));
+
+        if read.is_err() {
+            println!("Unable to read file: {}", source_file);
+            process::exit(1);
+        }
+
+        match remove_task_blocks(&text, tasks) {
+            Ok(archived) => {
+                documents.insert(source_file.clone(), archived.text);
+                blocks.extend(archived.blocks);
+                num_of_tasks_by_file.push((task_source_location(journal, tasks[0]), tasks.len()));
+            },
+            Err(reason) => {
+                println!("Unable to archive tasks of {}: {}", source_file, reason);
+                process::exit(1);
+            }
+        };
+    }
+
+    // the journal shall still parse without the archived tasks
+    let root = format!("{}", Path::new(path_to_file).canonicalize().unwrap().display());
+    let archived_journal = parse_journal_leniently(&root, &documents);
+
+    if archived_journal.num_of_warnings > journal.num_of_warnings {
+        if let Some(diagnostic) = archived_journal.diagnostics.as_ref().and_then(|diagnostics| diagnostics.first()) {
+            println!("Unable to archive tasks: {}:{} {}", diagnostic.path, diagnostic.line_number, diagnostic.message);
+            process::exit(1);
+        }
+    }
+
+    if let Err(reason) = append_task_blocks(path_to_archive, &blocks) {
+        println!("Unable to archive tasks: {}", reason);
+        process::exit(1);
+    }
+
+    for (source_file, text) in &documents {
+
+        let written = File::create(source_file).and_then(|mut file| file.write_all(text.as_bytes()));
+
+        if written.is_err() {
+            println!("Unable to write to file: {}", source_file);
+            println!("Archived tasks were already appended to: {}", path_to_archive);
+            process::exit(1);
+        }
+    }
+
+    for (location, num_of_tasks) in num_of_tasks_by_file {
+        println!("Archived {} from {}", pluralize_tasks(num_of_tasks), location);
+    }
+
+    println!("Archived {} to {}", pluralize_tasks(blocks.len()), path_to_archive);
+}
+
+fn pluralize_tasks(num_of_tasks: usize) -> String {
+    if num_of_tasks == 1 {
+        "1 task".to_owned()
+    } else {
+        format!("{} tasks", num_of_tasks)
+    }
+}
+
+#[test]
+fn remove_task_blocks_test() {
+
+    let text = "inject.project.prefix: errands\n\
+        \n\
+        // weekly\n\
+        task: buy milk\n\
+        status: done\n\
+        \n\
+        ----\n\
+        task: call mom\n\
+        project: family\n\
+        status: done\n\
+        \n\
+        task: pay rent\n";
+
+    let mut first = Task::new(4);
+    first.task_block_range_end = 5;
+    first.project = Some(vec!["errands".to_owned()]);
+
+    let mut second = Task::new(8);
+    second.task_block_range_end = 10;
+    second.project = Some(vec!["errands".to_owned(), "family".to_owned()]);
+
+    let archived = remove_task_blocks(text, &[&first, &second]).unwrap();
+
+    assert_eq!(archived.text, "inject.project.prefix: errands\n\
+        \n\
+        ----\n\
+        \n\
+        task: pay rent\n");
+
+    assert_eq!(archived.blocks, vec![
+        vec![
+            "// weekly".to_owned(),
+            "task: buy milk".to_owned(),
+            "status: done".to_owned(),
+            "project: errands".to_owned()
+        ],
+        vec![
+            "task: call mom".to_owned(),
+            "project: errands / family".to_owned(),
+            "status: done".to_owned()
+        ]
+    ]);
+
+    // status from default.status is written out
+    let text = "default.status: done\n\ntask: file taxes\n";
+
+    let mut task = Task::new(3);
+    task.status = Some(Status::Done);
+
+    let archived = remove_task_blocks(text, &[&task]).unwrap();
+
+    assert_eq!(archived.text, "default.status: done\n\n");
+    assert_eq!(archived.blocks, vec![
+        vec![
+            "task: file taxes".to_owned(),
+            "status: done".to_owned()
+        ]
+    ]);
+}
+
+/* chain and timer */
+
+// chain subcommand
+fn chain_task_command(journal: &GTD, path_to_file: &str, matches: &ArgMatches) {
+
+    let task_key = select_task_or_exit(journal, matches.value_of("selector").unwrap());
+    let task: &Task = journal.tasks.get(&task_key).unwrap();
+
+    let location = format!("{}:{}", task_source_location(journal, task), task.task_block_range_start);
+
+    let edits = vec![
+        TaskBlockEdit::Append(format!("chain: {}", block_datetime(&journal.now)))
+    ];
+
+    match edit_selected_task(journal, path_to_file, task_key, &edits, &|_| Ok(())) {
+        Ok(_) => {
+            println!("Chained task: {} ({})", task.title.as_ref().unwrap(), location);
+        },
+        Err(reason) => {
+            println!("Unable to chain task: {}", reason);
+            process::exit(1);
+        }
+    };
+}
+
+// running timer of a task; kept in a state file next to the gtdtxt file
+#[derive(Debug, PartialEq)]
+struct Timer {
+    source_file: String,
+    line_number: u64,
+    title: String,
+    started_at: NaiveDateTime
+}
+
+const TIMER_DATETIME_FORMAT: &'static str = "%Y-%m-%d %H:%M:%S";
+
+impl Timer {
+
+    fn to_state(&self) -> String {
+        format!("file: {}\nline: {}\ntask: {}\nstarted: {}\n",
+            self.source_file,
+            self.line_number,
+            self.title,
+            self.started_at.format(TIMER_DATETIME_FORMAT))
+    }
+
+    fn from_state(state: &str) -> Option<Timer> {
+
+        let mut source_file: Option<String> = None;
+        let mut line_number: Option<u64> = None;
+        let mut title: Option<String> = None;
+        let mut started_at: Option<NaiveDateTime> = None;
+
+        for line in state.lines() {
+
+            let index = match line.find(':') {
+                Some(index) => index,
+                None => {
+                    continue;
+                }
+            };
+
+            let value = line[(index + 1)..].trim();
+
+            match line[..index].trim() {
+                "file" => {
+                    source_file = Some(value.to_owned());
+                },
+                "line" => {
+                    line_number = value.parse::<u64>().ok();
+                },
+                "task" => {
+                    title = Some(value.to_owned());
+                },
+                "started" => {
+                    started_at = NaiveDateTime::parse_from_str(value, TIMER_DATETIME_FORMAT).ok();
+                },
+                _ => {}
+            };
+        }
+
+        match (source_file, line_number, title, started_at) {
+            (Some(source_file), Some(line_number), Some(title), Some(started_at)) => {
+                Some(Timer {
+                    source_file: source_file,
+                    line_number: line_number,
+                    title: title,
+                    started_at: started_at
+                })
+            },
+            _ => None
+        }
+    }
+}
+
+fn timer_state_path(path_to_file: &str) -> String {
+    format!("{}.timer", path_to_file)
+}
+
+fn read_timer(path_to_state: &str) -> Result<Option<Timer>, String> {
+
+    if !Path::new(path_to_state).exists() {
+        return Ok(None);
+    }
+
+    let mut state = String::new();
+
+    let read = File::open(path_to_state).and_then(|mut file| file.read_to_string(&mut state));
+
+    if read.is_err() {
+        return Err(format!("Unable to read timer state file: {}", path_to_state));
+    }
+
+    match Timer::from_state(&state) {
+        Some(timer) => Ok(Some(timer)),
+        None => Err(format!("Malformed timer state file: {}", path_to_state))
+    }
+}
+
+// start subcommand
+fn start_timer_command(journal: &GTD, path_to_file: &str, matches: &ArgMatches) {
+
+    let path_to_state = timer_state_path(path_to_file);
+
+    match read_timer(&path_to_state) {
+        Ok(None) => {},
+        Ok(Some(timer)) => {
+            println!("A timer is already running for task: {} ({}:{})",
+                timer.title, timer.source_file, timer.line_number);
+            println!("Started at: {}", block_datetime(&timer.started_at));
+            process::exit(1);
+        },
+        Err(reason) => {
+            println!("{}", reason);
+            process::exit(1);
+        }
+    };
+
+    let task_key = select_task_or_exit(journal, matches.value_of("selector").unwrap());
+    let task: &Task = journal.tasks.get(&task_key).unwrap();
+
+    let timer = Timer {
+        source_file: task.source_file.clone().unwrap(),
+        line_number: task.task_block_range_start,
+        title: task.title.clone().unwrap(),
+        started_at: journal.now
+    };
+
+    let written = File::create(&path_to_state).and_then(|mut file| file.write_all(timer.to_state().as_bytes()));
+
+    if written.is_err() {
+        println!("Unable to write timer state file: {}", path_to_state);
+        process::exit(1);
+    }
+
+    println!("Started timer for task: {} ({}:{})",
+        timer.title,
+        task_source_location(journal, task),
+        timer.line_number);
+}
+
+// stop subcommand
+fn stop_timer_command(journal: &GTD, path_to_file: &str) {
+
+    let path_to_state = timer_state_path(path_to_file);
+
+    let timer = match read_timer(&path_to_state) {
+        Ok(Some(timer)) => timer,
+        Ok(None) => {
+            println!("No timer is running.");
+            process::exit(1);
+        },
+        Err(reason) => {
+            println!("{}", reason);
+            process::exit(1);
+        }
+    };
+
+    // the task block may have moved since the timer was started
+    let found: Vec<u64> = {
+
+        let mut found: Vec<(&u64, &Task)> = journal.tasks.iter()
+            .filter(|&(_, task)| {
+                task.source_file.as_ref() == Some(&timer.source_file) &&
+                task.title.as_ref() == Some(&timer.title)
+            })
+            .collect();
+
+        found.sort_by_key(|&(_, task)| {
+            (task.task_block_range_start as i64 - timer.line_number as i64).abs()
+        });
+
+        found.iter().map(|&(task_key, _)| *task_key).collect()
+    };
+
+    let task_key = match found.first() {
+        Some(task_key) => *task_key,
+        None => {
+            println!("Unable to find the task of the running timer: {} ({}:{})",
+                timer.title, timer.source_file, timer.line_number);
+            process::exit(1);
+        }
+    };
+
+    let elapsed = journal.now - timer.started_at;
+
+    if elapsed < Duration::zero() {
+        println!("Timer was started after the current time: {}", block_datetime(&timer.started_at));
+        process::exit(1);
+    }
+
+    let elapsed = block_duration(elapsed.num_seconds() as u64);
+
+    let edits = vec![
+        TaskBlockEdit::Append(format!("time: {}", elapsed))
+    ];
+
+    if let Err(reason) = edit_selected_task(journal, path_to_file, task_key, &edits, &|_| Ok(())) {
+        println!("Unable to stop timer: {}", reason);
+        process::exit(1);
+    }
+
+    if fs::remove_file(&path_to_state).is_err() {
+        println!("Unable to remove timer state file: {}", path_to_state);
+        process::exit(1);
+    }
+
+    let task: &Task = journal.tasks.get(&task_key).unwrap();
+
+    println!("Stopped timer for task: {} ({}:{})",
+        timer.title,
+        task_source_location(journal, task),
+        task.task_block_range_start);
+    println!("Time spent: {}", elapsed);
+}
+
+#[test]
+fn timer_test() {
+
+    let timer = Timer {
+        source_file: "/home/user/todo.gtd".to_owned(),
+        line_number: 12,
+        title: "write: report".to_owned(),
+        started_at: NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 5, 30)
+    };
+
+    assert_eq!(Timer::from_state(&timer.to_state()), Some(timer));
+    assert_eq!(Timer::from_state("file: todo.gtd\nline: 12\n"), None);
+
+    assert_eq!(block_duration(0), "0s");
+    assert_eq!(block_duration(45), "45s");
+    assert_eq!(block_duration(5400), "1h30m");
+    assert_eq!(block_duration(90061), "25h1m1s");
+
+    for seconds in &[0, 45, 5400, 90061] {
+        let input = format!("time: {}\n", block_duration(*seconds));
+        match parse_only(|i| task_block_attribute(i), input.as_bytes()) {
+            Ok(TaskBlock::Time(time)) => assert_eq!(time, *seconds),
+            _ => panic!("unable to parse: {}", input)
+        };
+    }
+}
+
+/* gtdtxt file parser */
+
+// for clippy
+#[allow(cyclomatic_complexity)]
+fn parse_file(parent_file: Option<String>, path_to_file_str: String, journal: &mut GTD) {
+
+    let path_to_file: &Path = Path::new(&path_to_file_str);
+
+    // documents opened in an editor may not be saved to disk yet
+    let is_unsaved_document = !path_to_file.is_file() && journal.documents.contains_key(&path_to_file_str);
+
+    if !path_to_file.is_file() && !is_unsaved_document {
+        // TODO: return Err(...)
+
+        match parent_file {
+            None => {},
+            Some(parent_file) => {
+                println!("In file: {}",
+                    parent_file
+                );
+            }
+        };
+
+        println!("Path is not a file: {}",
+            path_to_file_str
+        );
+        process::exit(1);
+    }
+
+    // fetch path to file
+    let tracked_path = match path_to_file.canonicalize() {
+        Ok(resolved) => {
+            let resolved: PathBuf = resolved;
+            format!("{}", resolved.display())
+        },
+        Err(_) if is_unsaved_document => path_to_file_str.clone(),
+        Err(e) => {
+            panic!("{:?}", e);
+        }
+    };
+
+    if journal.opened_files.contains(&tracked_path) {
+        println!("Cyclic includes detected; file already opened: {}", tracked_path);
+        process::exit(1);
+    }
+
+    // documents opened in an editor are parsed in place of the file on disk
+    let file: Box<Read> = match journal.documents.get(&tracked_path) {
+        Some(text) => Box::new(Cursor::new(text.clone().into_bytes())),
+        None => Box::new(File::open(path_to_file).expect("Failed to open file"))
+    };
+
+    // track this opened file to ensure we're not opening the same file twice
+    journal.opened_files.insert(tracked_path.clone());
+
+
+    // save current working directory
+    let old_working_directory = format!("{}", env::current_dir().unwrap().display());
+
+    // set new current working dir
+    let parent_dir: String = {
+        let parent_dir = Path::new(&tracked_path).parent().unwrap();
+        format!("{}", parent_dir.display())
+    };
+
+    // the directory of an unsaved document may not exist yet either
+    if !env::set_current_dir(&parent_dir).is_ok() && !is_unsaved_document {
+        println!("Unable to change working directory to: {}", parent_dir);
+        process::exit(1);
+    }
+
+    journal.file_stats.insert(tracked_path.clone(), FileStats::new());
+    journal.file_stats_stack.push(tracked_path.clone());
+
+    let mut num_of_lines_parsed = 0;
+
+    // parse gtdtxt file
+
+    let mut input = Source::new(file);
+
+    // file scoped directive switches
+    let mut directive_switch = LocalDirectiveSwitches::new();
+
+    // initial state
+    let mut previous_state: ParseState = ParseState::Start;
+
+    // in lenient mode, set when lines of a malformed block are being skipped
+    let mut recovering = false;
+    // the line that failed to parse is skipped, even if it would otherwise pass as a task boundary
+    let mut at_failed_line = false;
+
+    loop {
+
+        if recovering {
+
+            // the malformed block is discarded
+            previous_state = ParseState::Start;
+
+            let skip_boundary = at_failed_line;
+            let mut n = Numbering::new(LinesParsed::new(), |i| skip_malformed_line(i, skip_boundary));
+            let m = |i| n.parse(i);
+
+            match input.parse(m) {
+                Ok((lines_parsed, at_boundary)) => {
+                    num_of_lines_parsed += lines_parsed;
+                    recovering = !at_boundary;
+                    at_failed_line = false;
+                },
+                Err(StreamError::Retry) => {
+                    // Needed to refill buffer when necessary
+                },
+                Err(_) => {
+                    break;
+                }
+            };
+
+            continue;
+        }
+
+        let mut n = Numbering::new(LinesParsed::new(), line_token_parser);
+        // If we could implement FnMut for Numbering then we would be good, but we need to wrap now:
+        let m = |i| n.parse(i);
+
+        match input.parse(m) {
+            Ok((lines_parsed, line)) => {
+
+                // TODO: fix this... off by one hack fix :P
+                // amend behaviour of newline counting
+                let lines_parsed = if lines_parsed == 0 {
+                    1
+                } else {
+                    lines_parsed
+                };
+
+                // track the current line number
+                num_of_lines_parsed += lines_parsed;
+
+                match line {
+
+                    LineToken::Task(task_block_line) => {
+
+                        // mark this line as previous task block seen
+                        journal.previous_task_block_line = num_of_lines_parsed;
+
+                        let current_task: &mut Task = match previous_state {
+                            ParseState::Task(ref mut task) => {
+                                task
+                            },
+                            _ => {
+
+                                let state = mem::replace(&mut previous_state, ParseState::Start);
+                                flush_parse_state(state, journal, &directive_switch);
+
+                                let mut new_task: Task = Task::new(num_of_lines_parsed);
+                                new_task.source_file = Some(tracked_path.clone());
+                                previous_state = ParseState::Task(new_task);
+
+                                // TODO: possible to refactor this in a better way?
+                                match previous_state {
+                                    ParseState::Task(ref mut task) => {
+                                        task
+                                    },
+                                    _ => unsafe { debug_unreachable!() }
+                                }
+                            }
+                        };
+
+                        if let Err(reason) = apply_task_block(current_task, task_block_line) {
+
+                            let diagnostic = Diagnostic::invalid_value(journal, &tracked_path, num_of_lines_parsed, reason);
+
+                            if !journal.lenient {
+                                diagnostic.print(&journal.error_format);
+                                process::exit(1);
+                            }
+
+                            journal.add_warning(diagnostic);
+                            recovering = true;
+                        }
+
+                    },
+
+                    LineToken::View(view_line) => {
+
+                        if let ViewBlock::Name(name) = view_line {
+
+                            flush_parse_state(previous_state, journal, &directive_switch);
+
+                            let view = View::new(name);
+                            previous_state = ParseState::View(LineLocation(tracked_path.clone(), num_of_lines_parsed, view));
+
+                            continue;
+                        }
+
+                        let current_view: &mut View = match previous_state {
+                            ParseState::View(LineLocation(_, _, ref mut view)) => {
+                                view
+                            },
+                            _ => {
+
+                                let reason = "View attributes (e.g. `where:`, `show:`, `sort:`) \
+                                    shall follow a view name (i.e. `view: <name>`)".to_owned();
+                                let diagnostic = Diagnostic::misplaced_attribute(journal, &tracked_path, num_of_lines_parsed, reason);
+
+                                if !journal.lenient {
+                                    diagnostic.print(&journal.error_format);
+                                    process::exit(1);
+                                }
+
+                                journal.add_warning(diagnostic);
+                                recovering = true;
+                                continue;
+                            }
+                        };
+
+                        let result: Result<(), String> = match view_line {
+                            ViewBlock::Name(_) => unsafe { debug_unreachable!() },
+                            ViewBlock::Where(query) => {
+                                let now = journal.now;
+                                match parse_only(|i| parse_where(i, now), query.as_bytes()) {
+                                    Ok(Ok(filter_tree)) => {
+                                        current_view.add_where_filter_tree(filter_tree);
+                                        Ok(())
+                                    },
+                                    Ok(Err(reason)) => Err(reason),
+                                    Err(_) => Err(format!("Unable to parse query: {}", query))
+                                }
+                            },
+                            ViewBlock::Show(sections) => {
+                                current_view.set_sections(&sections)
+                            },
+                            ViewBlock::Sort(sort_by) => {
+                                current_view.add_sort_by(&sort_by)
+                            }
+                        };
+
+                        if let Err(reason) = result {
+
+                            let diagnostic = Diagnostic::invalid_value(journal, &tracked_path, num_of_lines_parsed, reason);
+
+                            if !journal.lenient {
+                                diagnostic.print(&journal.error_format);
+                                process::exit(1);
+                            }
+
+                            journal.add_warning(diagnostic);
+                            recovering = true;
+                        }
+
+                    },
+
+                    LineToken::Directive(directive_line) => {
+
+                        flush_parse_state(previous_state, journal, &directive_switch);
 
                         previous_state = ParseState::Directive;
 
-                        match directive_line {
-                            Directive::Include(path_to_file) => {
-                                parse_file(Some(tracked_path.clone()), path_to_file, journal);
-                            },
-                            Directive::DefaultStatus(result) => {
-                                directive_switch.default_status =
-                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
-                            },
-                            Directive::RequireStatus(result) => {
-                                directive_switch.require_status =
-                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
-                            },
-                            Directive::RequireExcludeStatus(result) => {
-                                directive_switch.require_exclude_status =
-                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
-                            },
-                            Directive::RequireExcludeStatusDelete => {
-                                directive_switch.require_exclude_status = None;
-                            },
-                            Directive::RequireProjectPrefix(result) => {
-                                directive_switch.require_project_prefix =
-                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
-                            },
-                            Directive::RequireProject(result) => {
-                                directive_switch.require_project = Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
-                            },
-                            Directive::InjectProjectPrefix(result) => {
-                                directive_switch.inject_project_prefix =
-                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
-                            },
-                            Directive::InjectProjectPrefixDelete => {
-                                directive_switch.inject_project_prefix = None;
-                            },
-                            Directive::InjectStatus(result) => {
-                                directive_switch.inject_status =
-                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
-                            },
-                            Directive::InjectStatusDelete => {
-                                directive_switch.inject_status = None;
-                            },
-                            Directive::EnsureProjectPrefix(result) => {
-                                directive_switch.ensure_project_prefix =
-                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
-                            },
-                            Directive::EnsureProjectPrefixDelete => {
-                                directive_switch.ensure_project_prefix = None;
-                            }
-                        };
+                        match directive_line {
+                            Directive::Include(path_to_file) => {
+
+                                match include_error(journal, &path_to_file) {
+                                    Some(reason) if journal.lenient => {
+                                        let diagnostic = Diagnostic::invalid_value(journal, &tracked_path,
+                                            num_of_lines_parsed, reason);
+                                        journal.add_warning(diagnostic);
+                                    },
+                                    _ => {
+                                        parse_file(Some(tracked_path.clone()), path_to_file, journal);
+                                    }
+                                };
+                            },
+                            Directive::DefaultStatus(result) => {
+                                directive_switch.default_status =
+                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
+                            },
+                            Directive::RequireStatus(result) => {
+                                directive_switch.require_status =
+                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
+                            },
+                            Directive::RequireExcludeStatus(result) => {
+                                directive_switch.require_exclude_status =
+                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
+                            },
+                            Directive::RequireExcludeStatusDelete => {
+                                directive_switch.require_exclude_status = None;
+                            },
+                            Directive::RequireProjectPrefix(result) => {
+                                directive_switch.require_project_prefix =
+                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
+                            },
+                            Directive::RequireProject(result) => {
+                                directive_switch.require_project = Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
+                            },
+                            Directive::InjectProjectPrefix(result) => {
+                                directive_switch.inject_project_prefix =
+                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
+                            },
+                            Directive::InjectProjectPrefixDelete => {
+                                directive_switch.inject_project_prefix = None;
+                            },
+                            Directive::InjectStatus(result) => {
+                                directive_switch.inject_status =
+                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
+                            },
+                            Directive::InjectStatusDelete => {
+                                directive_switch.inject_status = None;
+                            },
+                            Directive::EnsureProjectPrefix(result) => {
+                                directive_switch.ensure_project_prefix =
+                                    Some(LineLocation(tracked_path.clone(), num_of_lines_parsed, result));
+                            },
+                            Directive::EnsureProjectPrefixDelete => {
+                                directive_switch.ensure_project_prefix = None;
+                            }
+                        };
+
+                    },
+
+                    LineToken::PreBlock => {
+
+                        // println!("preblock");
+
+                        flush_parse_state(previous_state, journal, &directive_switch);
+
+                        previous_state = ParseState::PreBlock;
+
+                    },
+
+                    LineToken::TaskSeparator => {
+
+                        // println!("TaskSeparator");
+
+                        flush_parse_state(previous_state, journal, &directive_switch);
+
+                        // TODO: remove
+                        // match previous_state {
+                        //     ParseState::Task(task) => {
+                        //         journal.add_task(task, &directive_switch);
+                        //     },
+                        //     _ => {}
+                        // };
+
+                        previous_state = ParseState::TaskSeparator;
+                    }
+                };
+
+            },
+            Err(StreamError::Retry) => {
+                // Needed to refill buffer when necessary
+            },
+            Err(StreamError::EndOfInput) => {
+                break;
+            },
+            Err(_err) => {
+
+                let line_number = num_of_lines_parsed + 1;
+
+                if journal.lenient {
+                    let diagnostic = Diagnostic::unparseable(journal, &tracked_path, line_number);
+                    journal.add_warning(diagnostic);
+                    recovering = true;
+                    at_failed_line = true;
+                    continue;
+                }
+
+                // println!("{:?}", _err);
+
+                // match _err {
+                //     StreamError::ParseError(input, _) => {
+                //     // ParseError::Error(input, _) => {
+                //         println!("StreamError::ParseError {}",  String::from_utf8_lossy(input));
+                //     },
+                //     _ => {}
+                // };
+
+                if let ParseState::Task(task) = previous_state {
+                    if journal.error_format == ErrorFormat::Human {
+                        println!("Error occured when parsing a task.");
+                        println!("The following was captured:");
+                        _print_task(journal, &task, false);
+                    }
+                }
+
+                Diagnostic::unparseable(journal, &tracked_path, line_number).print(&journal.error_format);
+                // println!("Error parsing starting at line {} in file: {}", num_of_lines_parsed + 1, tracked_path);
+                process::exit(1);
+            }
+        }
+    };
+
+    // Parsing is finished, 'flush' any buffered state.
+
+    flush_parse_state(previous_state, journal, &directive_switch);
+
+    // TODO: remove... moved to LocalDirectiveSwitches::pass_validation(...)
+    // match journal.file_stats.get(&tracked_path) {
+    //     None => unsafe { debug_unreachable!() },
+    //     Some(file_stats) => {
+
+    //         match directive_switch.require_no_completed_tasks {
+    //             None => {},
+    //             Some(require_no_completed_tasks) => {
+
+    //                 let tasks = &file_stats.completed_tasks;
+
+    //                 if tasks.len() > 0 && require_no_completed_tasks {
+    //                     println!("Found {} completed tasks that are not supposed to be in file: {}",
+    //                         tasks.len(),
+    //                         tracked_path);
+
+    //                     let task: &Task = journal.tasks.get(tasks.first().unwrap()).unwrap();
+
+    //                     println!("Found a completed task at lines: {} to {}",
+    //                         task.task_block_range_start,
+    //                         task.task_block_range_end
+    //                     );
+    //                     println!("");
+
+    //                     _print_task(journal, task, false);
+
+    //                     process::exit(1);
+    //                 }
+    //             }
+    //         };
+
+    //     }
+    // }
+
+    journal.opened_files.remove(&tracked_path);
+
+    // restore current working dir
+    if !env::set_current_dir(&old_working_directory).is_ok() {
+        println!("Unable to change working directory to: {}", old_working_directory);
+        process::exit(1);
+    }
+
+}
+
+// parse the text as a gtdtxt file (written to the temp directory), evaluated at the given point in time
+#[cfg(test)]
+fn parse_test_journal(name: &str, text: &str, now: NaiveDateTime) -> GTD {
+
+    let path = env::temp_dir().join(format!("gtdtxt-{}.gtd", name));
+    File::create(&path).and_then(|mut file| file.write_all(text.as_bytes())).unwrap();
+
+    let mut journal = GTD::new(format!("{}", env::temp_dir().display()));
+    journal.now = now;
+
+    parse_file(None, format!("{}", path.display()), &mut journal);
+
+    journal
+}
+
+// titles of the given tasks of the journal
+#[cfg(test)]
+fn test_task_titles(journal: &GTD, task_ids: &[u64]) -> Vec<String> {
+    task_ids.iter().map(|task_id| {
+        journal.tasks.get(task_id).unwrap().title.clone().unwrap()
+    }).collect()
+}
+
+// apply an attribute of a task block to the task
+fn apply_task_block(task: &mut Task, task_block_line: TaskBlock) -> Result<(), String> {
+
+    match task_block_line {
+        TaskBlock::Current => {
+            task.current = true;
+        },
+        TaskBlock::Title(title) => {
+            task.title = Some(title);
+        },
+        TaskBlock::Note(note) => {
+            task.note = Some(note);
+        },
+        TaskBlock::Project(project) => {
+
+            if project.len() > 0 {
+                task.project = Some(project);
+            } else {
+                task.project = None;
+            }
+
+        },
+        TaskBlock::Created(created_at) => {
+            let created_at: NaiveDateTime = created_at;
+            task.created_at = Some(created_at);
+        },
+        TaskBlock::Done(done_at) => {
+            let done_at: NaiveDateTime = done_at;
+            task.done_at = Some(done_at);
+        },
+        TaskBlock::Chain(chain_at) => {
+            let chain_at: NaiveDateTime = chain_at;
+            match task.chains {
+                None => {
+
+                    let mut tree = BTreeMap::new();
+                    tree.insert(chain_at, true);
+
+                    task.chains = Some(tree);
+
+                },
+                Some(ref mut tree) => {
+                    tree.insert(chain_at, true);
+                }
+            };
+        },
+        TaskBlock::Status(status) => {
+
+            task.status = Some(status);
+        },
+        TaskBlock::Due(due_at) => {
+            let due_at: NaiveDateTime = due_at;
+            task.due_at = Some(due_at);
+        },
+        TaskBlock::Defer(defer) => {
+            task.defer = Some(defer);
+        },
+        TaskBlock::Contexts(contexts) => {
+
+            if contexts.len() > 0 {
+                task.contexts = Some(contexts);
+            } else {
+                task.contexts = None;
+            }
+        },
+        TaskBlock::Tags(tags) => {
+
+            if tags.len() > 0 {
+                task.tags = Some(tags);
+            } else {
+                task.tags = None;
+            }
+        },
+        TaskBlock::Time(time) => {
+            task.time += time;
+        },
+        TaskBlock::ID(id) => {
+
+            if id.len() > 0 {
+                task.id = Some(id);
+            } else {
+                task.id = None;
+            }
+        },
+        TaskBlock::Priority(priority) => {
+            task.priority = priority
+        },
+        TaskBlock::Flag(flag) => {
+            task.flag = flag;
+        },
+        TaskBlock::Invalid(reason) => {
+            return Err(reason);
+        }
+    };
+
+    return Ok(());
+}
+
+// reason why an included file cannot be parsed, if any
+fn include_error(journal: &GTD, path_to_file: &str) -> Option<String> {
+
+    let path = Path::new(path_to_file);
+
+    if !path.is_file() {
+        return Some(format!("Path is not a file: {}", path_to_file));
+    }
+
+    match path.canonicalize() {
+        Ok(resolved) => {
+
+            let resolved = format!("{}", resolved.display());
+
+            if journal.opened_files.contains(&resolved) {
+                return Some(format!("Cyclic includes detected; file already opened: {}", resolved));
+            }
+
+            return None;
+        },
+        Err(_) => {
+            return Some(format!("Unable to resolve path: {}", path_to_file));
+        }
+    }
+}
+
+// add any task or view that was being parsed to the journal
+fn flush_parse_state(previous_state: ParseState, journal: &mut GTD, directive_switch: &LocalDirectiveSwitches) {
+    match previous_state {
+        ParseState::Task(task) => {
+            journal.add_task(task, directive_switch);
+        },
+        ParseState::View(view) => {
+            journal.add_view(view);
+        },
+        _ => {}
+    }
+}
+
+/* parsers */
+
+// state machine:
+// Start = PreBlock | Task | View | Directive | TaskSeparator
+// PreBlock = PreBlock | Task | View | Directive | TaskSeparator
+// TaskSeparator = PreBlock | Task | View | Directive | TaskSeparator
+// Task = Task | PreBlock | TaskSeparator
+// View = View | PreBlock | TaskSeparator
+// Directive = Directive | PreBlock | TaskSeparator
+#[derive(Debug)]
+enum ParseState {
+    Start,
+    PreBlock,
+    Task(Task),
+    View(LineLocation<View>),
+    Directive,
+    TaskSeparator
+}
+
+#[derive(Debug)]
+enum LineToken {
+    Task(TaskBlock),
+    View(ViewBlock),
+    Directive(Directive),
+    PreBlock,
+    TaskSeparator
+}
+
+
+fn line_token_parser(input: Input<u8>) -> U8Result<LineToken> {
+
+    or(input,
+        |i| parse!{i;
+
+            // this line shall not begin with any whitespace
+            look_ahead(|i| satisfy(i, |c| !is_whitespace(c)));
+
+            let line: LineToken = task_seperators() <|>
+            task_block() <|>
+            view_block() <|>
+            directives();
+
+            ret line
+        },
+        pre_block
+    )
+
+}
+
+/* preblock */
+
+#[inline]
+fn __pre_block_comments(i: Input<u8>) -> U8Result<()> {
+    parse!{i;
+
+        comments_block();
+        skip_many(space_or_tab);
+
+        comments_one_line() <|>
+        terminating() <|>
+        __pre_block_comments();
+
+        ret ()
+    }
+}
+
+
+fn pre_block(i: Input<u8>) -> U8Result<LineToken> {
+    parse!{i;
+
+        skip_many(space_or_tab);
+
+        comments_one_line() <|>
+        __pre_block_comments() <|>
+        terminating();
+
+        ret LineToken::PreBlock;
+    }
+}
+
+// consume a line of a malformed block (lenient mode).
+// yields true without consuming anything at the next task boundary (i.e. blank line, comment, or task separator),
+// unless the line is to be skipped regardless.
+fn skip_malformed_line(input: Input<u8>, skip_boundary: bool) -> U8Result<bool> {
+
+    if skip_boundary {
+        return parse!{input;
+            let _line: Vec<u8> = many_till(any, terminating);
+            ret false
+        };
+    }
+
+    or(input,
+        |i| parse!{i;
+
+            look_ahead(|i| or(i, task_seperators, pre_block));
+
+            ret true
+        },
+        |i| parse!{i;
+
+            let _line: Vec<u8> = many_till(any, terminating);
+
+            ret false
+        }
+    )
+}
+
+#[test]
+fn skip_malformed_line_test() {
+
+    assert_eq!(parse_only(|i| skip_malformed_line(i, false), "stauts: done\ntask: a\n".as_bytes()).ok(), Some(false));
+    assert_eq!(parse_only(|i| skip_malformed_line(i, false), "\ntask: a\n".as_bytes()).ok(), Some(true));
+    assert_eq!(parse_only(|i| skip_malformed_line(i, false), "// comment\n".as_bytes()).ok(), Some(true));
+    assert_eq!(parse_only(|i| skip_malformed_line(i, false), "-----\n".as_bytes()).ok(), Some(true));
+    assert_eq!(parse_only(|i| skip_malformed_line(i, true), "-----\n".as_bytes()).ok(), Some(false));
+}
+
+
+/* task block */
+
+#[derive(Debug, PartialEq)]
+enum Defer {
+    Forever,
+    Until(NaiveDateTime)
+}
+
+// tokens from parser
+#[derive(Debug)]
+enum TaskBlock {
+    Current,
+    Title(String),
+    Created(NaiveDateTime),
+    Done(NaiveDateTime),
+    Chain(NaiveDateTime),
+    Due(NaiveDateTime),
+    Defer(Defer),
+    Priority(i64),
+    Time(TimeLength),
+    Project(Vec<String>),
+    Status(Status),
+    Contexts(Vec<String>),
+    Tags(Vec<String>),
+    Flag(bool),
+    Note(String),
+
+    ID(String),
+
+    // attribute was recognized, but its value is invalid (e.g. February 31)
+    Invalid(String)
+}
+
+// aliases of task block attributes. aliases are matched in order, so an alias shall come before
+// any other alias that it begins with (e.g. `notes` before `note`).
+const TASK_CURRENT: &'static [&'static str] = &["current"];
+const TASK_TITLE: &'static [&'static str] = &["task", "todo", "action", "item", "title"];
+const TASK_ID: &'static [&'static str] = &["id"];
+const TASK_NOTE: &'static [&'static str] = &["notes", "note", "description", "desc"];
+const TASK_TIME: &'static [&'static str] = &["time"];
+const TASK_PRIORITY: &'static [&'static str] = &["priority"];
+const TASK_PROJECT: &'static [&'static str] = &["project"];
+const TASK_FLAG: &'static [&'static str] = &["flag"];
+const TASK_CREATED: &'static [&'static str] = &["created at", "created", "date", "added at", "added"];
+const TASK_DONE: &'static [&'static str] = &["done at", "done", "completed", "complete"];
+const TASK_CHAIN: &'static [&'static str] = &["chain"];
+const TASK_STATUS: &'static [&'static str] = &["status"];
+const TASK_DUE: &'static [&'static str] = &["due"];
+const TASK_DEFER: &'static [&'static str] =
+    &["defer till", "defer until", "defer", "hide until", "hidden", "hide till", "hide"];
+const TASK_CONTEXTS: &'static [&'static str] = &["contexts", "context"];
+const TASK_TAGS: &'static [&'static str] = &["tags", "tag"];
+
+const TASK_ATTRIBUTES: &'static [&'static [&'static str]] = &[
+    TASK_CURRENT,
+    TASK_TITLE,
+    TASK_ID,
+    TASK_NOTE,
+    TASK_TIME,
+    TASK_PRIORITY,
+    TASK_PROJECT,
+    TASK_FLAG,
+    TASK_CREATED,
+    TASK_DONE,
+    TASK_CHAIN,
+    TASK_STATUS,
+    TASK_DUE,
+    TASK_DEFER,
+    TASK_CONTEXTS,
+    TASK_TAGS
+];
+
+fn task_block(i: Input<u8>) -> U8Result<LineToken> {
+
+    parse!{i;
+
+        let line: TaskBlock =
+            task_current() <|>
+            task_title() <|>
+            task_priority() <|>
+            task_project() <|>
+            task_flag() <|>
+            task_created() <|>
+            task_done() <|>
+            task_chain() <|>
+            task_status() <|>
+            task_due() <|>
+            task_defer() <|>
+            task_tags() <|>
+            task_contexts() <|>
+            task_time() <|>
+            task_id() <|>
+            task_note();
+
+        ret LineToken::Task(line)
+    }
+}
+
+fn task_current(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_CURRENT);
+
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret TaskBlock::Current
+    }
+
+}
+
+fn task_title(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_TITLE);
+
+        token(b':');
+
+        let line = non_empty_line();
+
+        ret {
+            let title: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
+            TaskBlock::Title(title)
+        }
+    }
+}
+
+fn task_note(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_NOTE);
+
+        token(b':');
+
+        skip_many(space_or_tab);
+
+        // first line
+        let line = or(
+            |i| parse!{i;
+                terminating();
+
+                ret {
+                    let line: Vec<u8> = vec![];
+                    line
+                }
+            },
+            // NOTE: this must be parsed last
+            non_empty_line
+        );
+
+        let other_lines: Vec<String> = many(
+            |i| or(i,
+                |i| parse!{i;
+
+                    space_or_tab();
+
+                    let line = non_empty_line();
+
+                    ret {
+                        let line: String = format!("{:>11} {}",
+                            "",
+                            String::from_utf8_lossy(line.as_slice()).trim()
+                        );
+                        line
+                    }
+                },
+                |i| parse!{i;
+
+                    // allow empty lines in note
+
+                    let nothing: Vec<()> = many(|i| parse!{i;
+                        let _nothing: Vec<()> = many_till(space_or_tab, end_of_line);
+                        ret ()
+                    });
+
+                    space_or_tab();
+
+                    let line = non_empty_line();
+
+                    ret {
+
+                        let filler = String::from_utf8(vec![b'\n'; nothing.len()]).ok().unwrap();
+
+                        let line: String = format!("{}{:>11} {}",
+                            filler,
+                            "",
+                            String::from_utf8_lossy(line.as_slice()).trim()
+                        );
+                        line
+                    }
+                }
+            )
+
+        );
+
+        ret {
+            let line: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
+            let other_lines = other_lines.join("\n");
+
+            let note = if other_lines.len() > 0 {
+                if line.len() > 0 {
+                    format!("{}\n{}", line, other_lines)
+                } else {
+                    // format!("{}", other_lines.trim())
+                    other_lines.trim().to_owned()
+                }
+
+            } else {
+                line.to_owned()
+            };
+
+            TaskBlock::Note(note)
+        }
+    }
+
+}
+
+fn task_time(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_TIME);
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let time: TimeLength = multiple_time_range();
+
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret TaskBlock::Time(time)
+    }
+}
+
+fn parse_priority_number(input: Input<u8>) -> U8Result<i64> {
+    parse!{input;
+        let priority: i64 = signed_decimal() <|> decimal();
+        ret priority
+    }
+}
+
+fn task_priority(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_PRIORITY);
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let priority: i64 = parse_priority_number();
+
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret TaskBlock::Priority(priority)
+    }
+}
+
+fn task_project(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_PROJECT);
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        let list = string_list(b'/');
+
+        ret TaskBlock::Project(list)
+    }
+}
+
+fn task_flag(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_FLAG);
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let input = bool_option_parser();
+
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret TaskBlock::Flag(input)
+    }
+}
+
+fn task_created(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_CREATED);
+
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let created_at = parse_datetime(false);
+
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret {
+            match created_at {
+                Ok(created_at) => TaskBlock::Created(created_at),
+                Err(reason) => TaskBlock::Invalid(reason)
+            }
+        }
+    }
+}
+
+fn task_done(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_DONE);
+
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let done_at = parse_datetime(false);
+
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret {
+            match done_at {
+                Ok(done_at) => TaskBlock::Done(done_at),
+                Err(reason) => TaskBlock::Invalid(reason)
+            }
+        }
+    }
+}
+
+fn task_chain(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_CHAIN);
+
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let chain_at = parse_datetime(false);
+
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret {
+            match chain_at {
+                Ok(chain_at) => TaskBlock::Chain(chain_at),
+                Err(reason) => TaskBlock::Invalid(reason)
+            }
+        }
+    }
+}
+
+fn parse_status(input: Input<u8>) -> U8Result<Status> {
+
+    or(input,
+        |i| parse!{i;
+
+            string_ignore_case("done".as_bytes()) <|>
+            string_ignore_case("complete".as_bytes()) <|>
+            string_ignore_case("finished".as_bytes()) <|>
+            string_ignore_case("finish".as_bytes()) <|>
+            string_ignore_case("fin".as_bytes());
+
+            ret Status::Done
+        },
+        |i| or(i,
+            |i| parse!{i;
+
+                string_ignore_case("hide".as_bytes()) <|>
+                string_ignore_case("hidden".as_bytes()) <|>
+                string_ignore_case("incubate".as_bytes()) <|>
+                string_ignore_case("later".as_bytes()) <|>
+                string_ignore_case("someday".as_bytes()) <|>
+                string_ignore_case("inactive".as_bytes()) <|>
+                string_ignore_case("not active".as_bytes());
+
+                ret Status::Incubate
+            },
+            |i| parse!{i;
+
+                string_ignore_case("active".as_bytes()) <|>
+                string_ignore_case("not done".as_bytes()) <|>
+                string_ignore_case("progress".as_bytes()) <|>
+                string_ignore_case("in progress".as_bytes()) <|>
+                string_ignore_case("in-progress".as_bytes()) <|>
+                string_ignore_case("pending".as_bytes()) <|>
+                string_ignore_case("is active".as_bytes());
+
+                ret Status::NotDone
+            }
+        )
+    )
+}
+
+fn task_status(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_STATUS);
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let status = parse_status();
+
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret TaskBlock::Status(status)
+    }
+}
+
+fn task_due(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_DUE);
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let due_at = parse_datetime(true);
+
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret {
+            match due_at {
+                Ok(due_at) => TaskBlock::Due(due_at),
+                Err(reason) => TaskBlock::Invalid(reason)
+            }
+        }
+    }
+}
+
+fn task_defer(input: Input<u8>) -> U8Result<TaskBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(TASK_DEFER);
+
+        token(b':');
+
+        look_ahead(non_empty_line);
+
+        skip_many(space_or_tab);
+
+        let defer = or(
+            |i| parse!{i;
+                string_ignore_case("forever".as_bytes());
+                ret {
+                    let defer: Result<Defer, String> = Ok(Defer::Forever);
+                    defer
+                }
+            },
+            |i| parse!{i;
+                let defer_till = parse_datetime(false);
+                ret defer_till.map(Defer::Until)
+            }
+        );
 
-                    },
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
 
-                    LineToken::PreBlock => {
+        ret {
+            match defer {
+                Ok(defer) => TaskBlock::Defer(defer),
+                Err(reason) => TaskBlock::Invalid(reason)
+            }
+        }
+    }
+}
 
-                        // println!("preblock");
+fn task_contexts(input: Input<u8>) -> U8Result<TaskBlock> {
 
-                        if let ParseState::Task(task) = previous_state {
-                            journal.add_task(task, &directive_switch);
-                        }
+    parse!{input;
 
-                        previous_state = ParseState::PreBlock;
+        string_ignore_case_any(TASK_CONTEXTS);
 
-                    },
+        token(b':');
 
-                    LineToken::TaskSeparator => {
+        look_ahead(non_empty_line);
 
-                        // println!("TaskSeparator");
+        let list = string_list(b',');
 
-                        if let ParseState::Task(task) = previous_state {
-                            journal.add_task(task, &directive_switch);
-                        }
+        ret TaskBlock::Contexts(list)
+    }
+}
 
-                        // TODO: remove
-                        // match previous_state {
-                        //     ParseState::Task(task) => {
-                        //         journal.add_task(task, &directive_switch);
-                        //     },
-                        //     _ => {}
-                        // };
+fn task_tags(input: Input<u8>) -> U8Result<TaskBlock> {
 
-                        previous_state = ParseState::TaskSeparator;
-                    }
-                };
+    parse!{input;
 
-            },
-            Err(StreamError::Retry) => {
-                // Needed to refill buffer when necessary
-            },
-            Err(StreamError::EndOfInput) => {
-                break;
-            },
-            Err(_err) => {
+        string_ignore_case_any(TASK_TAGS);
 
-                // println!("{:?}", _err);
+        token(b':');
 
-                // match _err {
-                //     StreamError::ParseError(input, _) => {
-                //     // ParseError::Error(input, _) => {
-                //         println!("StreamError::ParseError {}",  String::from_utf8_lossy(input));
-                //     },
-                //     _ => {}
-                // };
+        look_ahead(non_empty_line);
 
-                if let ParseState::Task(task) = previous_state {
-                    println!("Error occured when parsing a task.");
-                    println!("The following was captured:");
-                    _print_task(journal, &task, false);
-                }
+        let list = string_list(b',');
 
-                let line_number = num_of_lines_parsed + 1;
-                println!("{}:{} Error parsing at line {}", tracked_path, line_number, line_number);
-                // println!("Error parsing starting at line {} in file: {}", num_of_lines_parsed + 1, tracked_path);
-                process::exit(1);
-            }
-        }
-    };
+        ret TaskBlock::Tags(list)
+    }
+}
 
-    // Parsing is finished, 'flush' any buffered state.
+fn task_id(input: Input<u8>) -> U8Result<TaskBlock> {
 
-    if let ParseState::Task(task) = previous_state {
-        journal.add_task(task, &directive_switch);
-    }
+    parse!{input;
 
-    // TODO: remove... moved to LocalDirectiveSwitches::pass_validation(...)
-    // match journal.file_stats.get(&tracked_path) {
-    //     None => unsafe { debug_unreachable!() },
-    //     Some(file_stats) => {
+        string_ignore_case_any(TASK_ID);
+        token(b':');
 
-    //         match directive_switch.require_no_completed_tasks {
-    //             None => {},
-    //             Some(require_no_completed_tasks) => {
+        let line = non_empty_line();
 
-    //                 let tasks = &file_stats.completed_tasks;
+        ret {
+            let id: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
+            TaskBlock::ID(id)
+        }
+    }
+}
 
-    //                 if tasks.len() > 0 && require_no_completed_tasks {
-    //                     println!("Found {} completed tasks that are not supposed to be in file: {}",
-    //                         tasks.len(),
-    //                         tracked_path);
+/* view block */
 
-    //                     let task: &Task = journal.tasks.get(tasks.first().unwrap()).unwrap();
+// tokens from parser
+#[derive(Debug)]
+enum ViewBlock {
+    Name(String),
+    // query is parsed when the view block is evaluated; relative datetimes depend on the journal
+    Where(String),
+    Show(Vec<String>),
+    Sort(String)
+}
 
-    //                     println!("Found a completed task at lines: {} to {}",
-    //                         task.task_block_range_start,
-    //                         task.task_block_range_end
-    //                     );
-    //                     println!("");
+const VIEW_NAME: &'static [&'static str] = &["view"];
+const VIEW_WHERE: &'static [&'static str] = &["where"];
+const VIEW_SHOW: &'static [&'static str] = &["show"];
+const VIEW_SORT: &'static [&'static str] = &["sort"];
 
-    //                     _print_task(journal, task, false);
+const VIEW_ATTRIBUTES: &'static [&'static [&'static str]] = &[VIEW_NAME, VIEW_WHERE, VIEW_SHOW, VIEW_SORT];
 
-    //                     process::exit(1);
-    //                 }
-    //             }
-    //         };
+fn view_block(i: Input<u8>) -> U8Result<LineToken> {
 
-    //     }
-    // }
+    parse!{i;
 
-    journal.opened_files.remove(&tracked_path);
+        let line: ViewBlock =
+            view_name() <|>
+            view_where() <|>
+            view_show() <|>
+            view_sort();
 
-    // restore current working dir
-    if !env::set_current_dir(&old_working_directory).is_ok() {
-        println!("Unable to change working directory to: {}", old_working_directory);
-        process::exit(1);
+        ret LineToken::View(line)
     }
-
 }
 
-/* parsers */
+fn view_name(input: Input<u8>) -> U8Result<ViewBlock> {
 
-// state machine:
-// Start = PreBlock | Task | Directive | TaskSeparator
-// PreBlock = PreBlock | Task | Directive | TaskSeparator
-// TaskSeparator = PreBlock | Task | Directive | TaskSeparator
-// Task = Task | PreBlock | TaskSeparator
-// Directive = Directive | PreBlock | TaskSeparator
-#[derive(Debug)]
-enum ParseState {
-    Start,
-    PreBlock,
-    Task(Task),
-    Directive,
-    TaskSeparator
-}
+    parse!{input;
 
-#[derive(Debug)]
-enum LineToken {
-    Task(TaskBlock),
-    Directive(Directive),
-    PreBlock,
-    TaskSeparator
+        string_ignore_case_any(VIEW_NAME);
+        token(b':');
+
+        let line = non_empty_line();
+
+        ret {
+            let name: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
+            ViewBlock::Name(name)
+        }
+    }
 }
 
+fn view_where(input: Input<u8>) -> U8Result<ViewBlock> {
 
-fn line_token_parser(input: Input<u8>) -> U8Result<LineToken> {
+    parse!{input;
 
-    or(input,
-        |i| parse!{i;
+        string_ignore_case_any(VIEW_WHERE);
+        token(b':');
 
-            // this line shall not begin with any whitespace
-            look_ahead(|i| satisfy(i, |c| !is_whitespace(c)));
+        let line = non_empty_line();
 
-            let line: LineToken = task_seperators() <|>
-            task_block() <|>
-            directives();
+        ret {
+            let query: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
+            ViewBlock::Where(query)
+        }
+    }
+}
 
-            ret line
-        },
-        pre_block
-    )
+fn view_show(input: Input<u8>) -> U8Result<ViewBlock> {
+
+    parse!{input;
+
+        string_ignore_case_any(VIEW_SHOW);
+        token(b':');
+
+        let list = string_list(b',');
 
+        ret ViewBlock::Show(list)
+    }
 }
 
-/* preblock */
+fn view_sort(input: Input<u8>) -> U8Result<ViewBlock> {
 
-#[inline]
-fn __pre_block_comments(i: Input<u8>) -> U8Result<()> {
-    parse!{i;
+    parse!{input;
 
-        comments_block();
-        skip_many(space_or_tab);
+        string_ignore_case_any(VIEW_SORT);
+        token(b':');
 
-        comments_one_line() <|>
-        terminating() <|>
-        __pre_block_comments();
+        let line = non_empty_line();
 
-        ret ()
+        ret {
+            let sort_by: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
+            ViewBlock::Sort(sort_by)
+        }
     }
 }
 
+#[test]
+fn view_block_test() {
 
-fn pre_block(i: Input<u8>) -> U8Result<LineToken> {
-    parse!{i;
+    match parse_only(|i| view_block(i), "view: morning\n".as_bytes()) {
+        Ok(LineToken::View(ViewBlock::Name(name))) => {
+            assert_eq!(name, "morning".to_owned());
+        },
+        _ => {
+            assert!(false);
+        }
+    }
 
-        skip_many(space_or_tab);
+    match parse_only(|i| view_block(i), "WHERE: tag = chore\n".as_bytes()) {
+        Ok(LineToken::View(ViewBlock::Where(query))) => {
+            assert_eq!(query, "tag = chore".to_owned());
+        },
+        _ => {
+            assert!(false);
+        }
+    }
 
-        comments_one_line() <|>
-        __pre_block_comments() <|>
-        terminating();
+    match parse_only(|i| view_block(i), "show: overdue,, inbox\n".as_bytes()) {
+        Ok(LineToken::View(ViewBlock::Show(sections))) => {
+            let mut view = View::new("morning".to_owned());
+            assert!(view.set_sections(&sections).is_ok());
+            assert_eq!(view.sections_string(), Some("overdue, inbox".to_owned()));
+        },
+        _ => {
+            assert!(false);
+        }
+    }
 
-        ret LineToken::PreBlock;
+    match parse_only(|i| view_block(i), "sort: priority, unknown\n".as_bytes()) {
+        Ok(LineToken::View(ViewBlock::Sort(sort_by))) => {
+            let mut view = View::new("morning".to_owned());
+            assert!(view.add_sort_by(&sort_by).is_err());
+        },
+        _ => {
+            assert!(false);
+        }
     }
 }
 
+#[test]
+fn apply_view_test() {
 
-/* task block */
+    let mut view = View::new("morning".to_owned());
+    assert!(view.set_sections(&["inbox".to_owned(), "deferred".to_owned()]).is_ok());
+
+    let mut journal = GTD::new(format!("{}", env::temp_dir().display()));
+    journal.apply_view(&view);
+
+    assert!(journal.hide_overdue);
+    assert!(!journal.hide_inbox);
+    assert!(journal.show_deferred);
+    assert!(!journal.show_done);
+
+    // sections of flags remain
+    let mut journal = GTD::new(format!("{}", env::temp_dir().display()));
+    journal.show_done = true;
+    journal.hide_inbox = true;
+    journal.apply_view(&view);
+
+    assert!(journal.hide_overdue);
+    assert!(journal.hide_inbox);
+    assert!(journal.show_deferred);
+    assert!(journal.show_done);
+}
+
+/* directives */
 
 #[derive(Debug)]
-enum Defer {
-    Forever,
-    Until(NaiveDateTime)
+enum StatusDirective {
+    Require(bool),
+    Status(Status),
+    // TODO: implement
+    // Explicit(Status)
 }
 
-// tokens from parser
-#[derive(Debug)]
-enum TaskBlock {
-    Current,
-    Title(String),
-    Created(NaiveDateTime),
-    Done(NaiveDateTime),
-    Chain(NaiveDateTime),
-    Due(NaiveDateTime),
-    Defer(Defer),
-    Priority(i64),
-    Time(TimeLength),
-    Project(Vec<String>),
-    Status(Status),
-    Contexts(Vec<String>),
-    Tags(Vec<String>),
-    Flag(bool),
-    Note(String),
+// state for directives that are applied to all files
+// struct GlobalDirectiveSwitches {
+
+//     // require:project:prefix: /initial/path/to/project
+//     require_project_prefix: Option<ProjectPath>,
+
+//     // require:project: true/false/yes/no
+//     require_project: Option<bool>,
+
+//     // project:base: /initial/path/to/project
+//     project_base: Option<ProjectPath>
+// }
+
+// state for directives that are applied locally to files
+struct LocalDirectiveSwitches {
+
+    /* default:... directives */
+
+    // default:status: done/not done
+    default_status: Option<LineLocation<Status>>,
+
+    /* require:... directives */
+
+    // require:status: yes/no/true/false/done/not done/incubate
+    require_status: Option<LineLocation<StatusDirective>>,
+
+    // require:exclude:status: done/not done/incubate
+    require_exclude_status: Option<LineLocation<Status>>,
+
+    // require:project:prefix: /initial/path/to/project
+    //
+    // This enforces tasks to have a certain prefix
+    require_project_prefix: Option<LineLocation<ProjectPath>>,
 
-    // TODO: complete
-    ID(String)
-}
+    // require:project: true/false/yes/no
+    //
+    // This enforces tasks to have a project attribute
+    require_project: Option<LineLocation<bool>>,
 
-fn task_block(i: Input<u8>) -> U8Result<LineToken> {
+    /* inject:... directives */
 
-    parse!{i;
+    // inject:project:base: /initial/path/to/project
+    //
+    // This injects project path as suffix.
+    inject_project_prefix: Option<LineLocation<ProjectPath>>,
 
-        let line: TaskBlock =
-            task_current() <|>
-            task_title() <|>
-            task_priority() <|>
-            task_project() <|>
-            task_flag() <|>
-            task_created() <|>
-            task_done() <|>
-            task_chain() <|>
-            task_status() <|>
-            task_due() <|>
-            task_defer() <|>
-            task_tags() <|>
-            task_contexts() <|>
-            task_time() <|>
-            // TODO: complete
-            // task_id()
-            task_note();
+    inject_status: Option<LineLocation<(Status, EnumSet<Status>)>>,
 
-        ret LineToken::Task(line)
-    }
+    /* ensure:... directives */
+    ensure_project_prefix: Option<LineLocation<ProjectPath>>,
 }
 
-fn task_current(input: Input<u8>) -> U8Result<TaskBlock> {
+impl LocalDirectiveSwitches {
 
-    parse!{input;
+    fn new() -> Self {
 
-        string_ignore_case("current".as_bytes());
+        LocalDirectiveSwitches {
 
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
+            /* default:... directives */
 
-        ret TaskBlock::Current
-    }
+            default_status: None,
 
-}
+            /* ensure:... directives */
 
-fn task_title(input: Input<u8>) -> U8Result<TaskBlock> {
+            /* require:... directives */
 
-    parse!{input;
+            require_status: None,
+            require_exclude_status: None,
+            require_project_prefix: None,
+            require_project: None,
 
-        // aliases
-        string_ignore_case("task".as_bytes()) <|>
-        string_ignore_case("todo".as_bytes()) <|>
-        string_ignore_case("action".as_bytes()) <|>
-        string_ignore_case("item".as_bytes()) <|>
-        string_ignore_case("title".as_bytes());
+            /* inject:... directives */
 
-        token(b':');
+            inject_project_prefix: None,
 
-        let line = non_empty_line();
+            inject_status: None,
 
-        ret {
-            let title: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
-            TaskBlock::Title(title)
-        }
-    }
-}
+            /* ensure:... directives */
 
-fn task_note(input: Input<u8>) -> U8Result<TaskBlock> {
+            ensure_project_prefix: None,
 
-    parse!{input;
+        }
+    }
 
-        // aliases
-        string_ignore_case("notes".as_bytes()) <|>
-        string_ignore_case("note".as_bytes()) <|>
-        string_ignore_case("description".as_bytes()) <|>
-        string_ignore_case("desc".as_bytes());
+    // If necessary, transform the given task based on the directive switches
+    fn transform_task(&self, task: &mut Task, journal: &GTD) {
 
-        token(b':');
+        // default.status
+        if task.status.is_none() && self.default_status.is_some() {
 
-        skip_many(space_or_tab);
+            let status = self.default_status
+                .as_ref().unwrap()  // Option
+                .as_ref().unwrap(); // LineLocation
 
-        // first line
-        let line = or(
-            |i| parse!{i;
-                terminating();
+            task.status = Some(status.clone());
+        }
 
-                ret {
-                    let line: Vec<u8> = vec![];
-                    line
-                }
-            },
-            // NOTE: this must be parsed last
-            non_empty_line
-        );
+        // inject.project.prefix
+        if let Some(ref project_prefix) = self.inject_project_prefix {
 
-        let other_lines: Vec<String> = many(
-            |i| or(i,
-                |i| parse!{i;
+            let project_prefix: &ProjectPath = project_prefix.as_ref().unwrap();
 
-                    space_or_tab();
+            task.project = if task.project.is_some() {
+                let mut path = project_prefix.clone();
+                path.extend_from_slice(task.project.as_ref().unwrap());
+                Some(path)
+            } else {
+                Some(project_prefix.clone())
+            };
+        }
 
-                    let line = non_empty_line();
+        // inject.status
+        if let Some(ref inject_status) = self.inject_status {
 
-                    ret {
-                        let line: String = format!("{:>11} {}",
-                            "",
-                            String::from_utf8_lossy(line.as_slice()).trim()
-                        );
-                        line
-                    }
-                },
-                |i| parse!{i;
+            let &(ref status, ref exclude): &(Status, EnumSet<Status>) = inject_status.as_ref().unwrap();
 
-                    // allow empty lines in note
+            let ref task_status = match task.status {
+                None => Status::NotDone,
+                Some(ref status) => status.clone()
+            };
 
-                    let nothing: Vec<()> = many(|i| parse!{i;
-                        let _nothing: Vec<()> = many_till(space_or_tab, end_of_line);
-                        ret ()
-                    });
+            if !exclude.contains(task_status) {
+                task.status = Some(status.clone());
+            }
 
-                    space_or_tab();
+        }
 
-                    let line = non_empty_line();
+        // NOTE: this goes last
+        // ensure.project.prefix
+        if let Some(ref project_prefix) = self.ensure_project_prefix {
 
-                    ret {
+            let required_project_prefix = project_prefix.as_ref().unwrap();
 
-                        let filler = String::from_utf8(vec![b'\n'; nothing.len()]).ok().unwrap();
+            let has_required_project_prefix: bool = match task.project {
+                None => false,
+                Some(ref project_path) => {
 
-                        let line: String = format!("{}{:>11} {}",
-                            filler,
-                            "",
-                            String::from_utf8_lossy(line.as_slice()).trim()
-                        );
-                        line
-                    }
+                    // ensure task.project has project_path as prefix
+                    project_path_has_prefix(project_path, required_project_prefix)
                 }
-            )
+            };
 
-        );
+            if !has_required_project_prefix {
 
-        ret {
-            let line: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
-            let other_lines = other_lines.join("\n");
+                // wildcards cannot be injected; only the segments before the first wildcard are used
+                let required_project_prefix: ProjectPath = required_project_prefix.iter()
+                    .take_while(|segment| !is_project_path_wildcard(segment))
+                    .cloned()
+                    .collect();
 
-            let note = if other_lines.len() > 0 {
-                if line.len() > 0 {
-                    format!("{}\n{}", line, other_lines)
+                task.project = if task.project.is_some() {
+                    let mut path = required_project_prefix.clone();
+                    path.extend_from_slice(task.project.as_ref().unwrap());
+                    Some(path)
                 } else {
-                    // format!("{}", other_lines.trim())
-                    other_lines.trim().to_owned()
-                }
-
-            } else {
-                line.to_owned()
-            };
+                    Some(required_project_prefix.clone())
+                };
+            }
 
-            TaskBlock::Note(note)
         }
     }
 
-}
-
-fn task_time(input: Input<u8>) -> U8Result<TaskBlock> {
+    fn pass_validation(&self, task: &Task, journal: &mut GTD) -> bool {
 
-    parse!{input;
+        // require:exclude:status
+        if let Some(ref exclude) = self.require_exclude_status {
 
-        string_ignore_case("time".as_bytes());
-        token(b':');
+            let task_status: Status = if task.status.is_none() {
+                // tasks with no explicit task are 'not done' by default
+                Status::NotDone
+            } else {
+                task.status.as_ref().unwrap().clone()
+            };
 
-        look_ahead(non_empty_line);
+            let status_exclude = exclude.as_ref().unwrap();
 
-        skip_many(space_or_tab);
+            if *status_exclude == task_status {
 
-        let time: TimeLength = multiple_time_range();
+                if journal.lenient {
+                    journal.add_task_warning(task, format!("Task's `status` attribute should not be {} \
+                        (from directive `require:exclude:status` in: {})", status_exclude.string(), exclude.location()));
+                    return false;
+                }
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+                println!("From directive `require:exclude:status` in: {}", exclude.location());
 
-        ret TaskBlock::Time(time)
-    }
-}
+                println!("The following task's `status` attribute should not be {}:\n", status_exclude.string());
 
-fn parse_priority_number(input: Input<u8>) -> U8Result<i64> {
-    parse!{input;
-        let priority: i64 = signed_decimal() <|> decimal();
-        ret priority
-    }
-}
+                _print_task(journal, task, false);
+                process::exit(1);
+            }
+        }
 
-fn task_priority(input: Input<u8>) -> U8Result<TaskBlock> {
+        // require:status
+        if let Some(ref require_status) = self.require_status {
 
-    parse!{input;
+            match *require_status.as_ref().unwrap() {
 
-        string_ignore_case("priority".as_bytes());
-        token(b':');
+                StatusDirective::Require(require) => {
 
-        look_ahead(non_empty_line);
+                    if require && task.status.is_none() {
 
-        skip_many(space_or_tab);
+                        if journal.lenient {
+                            journal.add_task_warning(task, format!("Task is missing an explicit `status` attribute \
+                                (from directive `require:status` in: {})", require_status.location()));
+                            return false;
+                        }
 
-        let priority: i64 = parse_priority_number();
+                        println!("From directive `require:status` in: {}", require_status.location());
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+                        println!("The following task is missing an explicit `status` attribute:\n");
 
-        ret TaskBlock::Priority(priority)
-    }
-}
+                        _print_task(journal, task, false);
+                        process::exit(1);
+                    }
+                },
 
-fn task_project(input: Input<u8>) -> U8Result<TaskBlock> {
+                StatusDirective::Status(ref status) => {
 
-    parse!{input;
+                    let task_status: Status = if task.status.is_none() {
+                        // tasks with no explicit task are 'not done' by default
+                        Status::NotDone
+                    } else {
+                        task.status.as_ref().unwrap().clone()
+                    };
 
-        string_ignore_case("project".as_bytes());
-        token(b':');
+                    if *status != task_status {
 
-        look_ahead(non_empty_line);
+                        if journal.lenient {
+                            journal.add_task_warning(task, format!("Task's `status` attribute needs to be {} \
+                                (from directive `require:status` in: {})", status.string(), require_status.location()));
+                            return false;
+                        }
 
-        let list = string_list(b'/');
+                        println!("From directive `require:status` in: {}", require_status.location());
 
-        ret TaskBlock::Project(list)
-    }
-}
+                        println!("The following task's `status` attribute needs to be {}:\n", status.string());
 
-fn task_flag(input: Input<u8>) -> U8Result<TaskBlock> {
+                        _print_task(journal, task, false);
+                        process::exit(1);
+                    }
+                }
 
-    parse!{input;
+            }
+        }
 
-        string_ignore_case("flag".as_bytes());
-        token(b':');
+        // require:project:prefix
+        if let Some(ref require_project_prefix) = self.require_project_prefix {
 
-        look_ahead(non_empty_line);
+            let required_project_prefix = require_project_prefix.as_ref().unwrap();
 
-        skip_many(space_or_tab);
+            let has_required_project_prefix: bool = match task.project {
+                None => false,
+                Some(ref project_path) => {
 
-        let input = bool_option_parser();
+                    // ensure task.project has project_path as prefix
+                    project_path_has_prefix(project_path, required_project_prefix)
+                }
+            };
 
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
+            if !has_required_project_prefix {
 
-        ret TaskBlock::Flag(input)
-    }
-}
+                if journal.lenient {
+                    journal.add_task_warning(task, format!("Task's `project` attribute does not begin with the \
+                        required prefix: {} (from directive `require:project:prefix` in: {})",
+                        required_project_prefix.join(" / "), require_project_prefix.location()));
+                    return false;
+                }
 
-fn task_created(input: Input<u8>) -> U8Result<TaskBlock> {
+                println!("From directive `require:project:prefix` in: {}", require_project_prefix.location());
 
-    parse!{input;
+                println!("The following task's `project` attribute does not being with the required prefix: {}:\n",
+                    required_project_prefix.join(" / "));
 
-        string_ignore_case("created at".as_bytes()) <|>
-        string_ignore_case("created".as_bytes()) <|>
-        string_ignore_case("date".as_bytes()) <|>
-        string_ignore_case("added at".as_bytes()) <|>
-        string_ignore_case("added".as_bytes());
+                _print_task(journal, task, false);
+                process::exit(1);
+            }
 
-        token(b':');
+        }
 
-        look_ahead(non_empty_line);
+        // require:project
+        if let Some(ref require_project) = self.require_project {
+            if *require_project.as_ref().unwrap() && task.project.is_none() {
 
-        skip_many(space_or_tab);
+                if journal.lenient {
+                    journal.add_task_warning(task, format!("Task is missing an explicit `project` attribute \
+                        (from directive `require:project` in: {})", require_project.location()));
+                    return false;
+                }
 
-        let created_at = parse_datetime(false);
+                println!("From directive `require:project` in: {}", require_project.location());
 
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
+                println!("The following task is missing an explicit `project` attribute:\n");
 
-        ret TaskBlock::Created(created_at)
-    }
-}
+                _print_task(journal, task, false);
+                process::exit(1);
+            }
+        }
 
-fn task_done(input: Input<u8>) -> U8Result<TaskBlock> {
+        // ensure:project:prefix
+        // only the segments before a wildcard are injected; e.g. clients/*/frontend cannot be ensured by injecting
+        if let Some(ref ensure_project_prefix) = self.ensure_project_prefix {
 
-    parse!{input;
+            let ensured_project_prefix = ensure_project_prefix.as_ref().unwrap();
 
-        string_ignore_case("done at".as_bytes()) <|>
-        string_ignore_case("done".as_bytes()) <|>
-        string_ignore_case("completed".as_bytes()) <|>
-        string_ignore_case("complete".as_bytes());
+            let has_ensured_project_prefix: bool = match task.project {
+                None => false,
+                Some(ref project_path) => project_path_has_prefix(project_path, ensured_project_prefix)
+            };
 
-        token(b':');
+            if !has_ensured_project_prefix {
 
-        look_ahead(non_empty_line);
+                if journal.lenient {
+                    journal.add_task_warning(task, format!("Task's `project` attribute does not begin with the \
+                        ensured prefix: {} (from directive `ensure:project:prefix` in: {})",
+                        ensured_project_prefix.join(" / "), ensure_project_prefix.location()));
+                    return false;
+                }
 
-        skip_many(space_or_tab);
+                println!("From directive `ensure:project:prefix` in: {}", ensure_project_prefix.location());
 
-        let done_at = parse_datetime(false);
+                println!("The following task's `project` attribute does not begin with the ensured prefix: {}:\n",
+                    ensured_project_prefix.join(" / "));
 
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
+                _print_task(journal, task, false);
+                process::exit(1);
+            }
+        }
 
-        ret TaskBlock::Done(done_at)
+        return true;
     }
+
 }
 
-fn task_chain(input: Input<u8>) -> U8Result<TaskBlock> {
+#[test]
+fn ensure_project_prefix_test() {
 
-    parse!{input;
+    let to_path = |path: &str| -> Vec<String> {
+        path.split('/').map(|segment| segment.to_owned()).collect()
+    };
 
-        string_ignore_case("chain".as_bytes());
+    let mut journal = GTD::new("/".to_owned());
+    journal.lenient = true;
+    journal.diagnostics = Some(Vec::new());
 
-        token(b':');
+    let mut directive_switch = LocalDirectiveSwitches::new();
+    directive_switch.ensure_project_prefix =
+        Some(LineLocation("/path/to/todo.gtd".to_owned(), 1, to_path("clients/*")));
 
-        look_ahead(non_empty_line);
+    let mut task = Task::new(3);
+    task.project = Some(to_path("acme"));
 
-        skip_many(space_or_tab);
+    directive_switch.transform_task(&mut task, &journal);
+    assert_eq!(task.project, Some(to_path("clients/acme")));
+    assert!(directive_switch.pass_validation(&task, &mut journal));
 
-        let chain_at = parse_datetime(false);
+    // wildcards before the last segment cannot be injected
+    directive_switch.ensure_project_prefix =
+        Some(LineLocation("/path/to/todo.gtd".to_owned(), 1, to_path("clients/*/frontend")));
 
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
+    let mut task = Task::new(3);
+    task.project = Some(to_path("backend"));
 
-        ret TaskBlock::Chain(chain_at)
-    }
+    directive_switch.transform_task(&mut task, &journal);
+    assert_eq!(task.project, Some(to_path("clients/backend")));
+    assert!(!directive_switch.pass_validation(&task, &mut journal));
+    assert_eq!(journal.diagnostics.as_ref().map(|diagnostics| diagnostics.len()), Some(1));
 }
 
-fn parse_status(input: Input<u8>) -> U8Result<Status> {
-
-    or(input,
-        |i| parse!{i;
-
-            string_ignore_case("done".as_bytes()) <|>
-            string_ignore_case("complete".as_bytes()) <|>
-            string_ignore_case("finished".as_bytes()) <|>
-            string_ignore_case("finish".as_bytes()) <|>
-            string_ignore_case("fin".as_bytes());
+#[derive(Debug)]
+enum Directive {
 
-            ret Status::Done
-        },
-        |i| or(i,
-            |i| parse!{i;
+    // include: path/to/file
+    Include(String),
 
-                string_ignore_case("hide".as_bytes()) <|>
-                string_ignore_case("hidden".as_bytes()) <|>
-                string_ignore_case("incubate".as_bytes()) <|>
-                string_ignore_case("later".as_bytes()) <|>
-                string_ignore_case("someday".as_bytes()) <|>
-                string_ignore_case("inactive".as_bytes()) <|>
-                string_ignore_case("not active".as_bytes());
+    /* default:... directives */
 
-                ret Status::Incubate
-            },
-            |i| parse!{i;
+    DefaultStatus(Status),
 
-                string_ignore_case("active".as_bytes()) <|>
-                string_ignore_case("not done".as_bytes()) <|>
-                string_ignore_case("progress".as_bytes()) <|>
-                string_ignore_case("in progress".as_bytes()) <|>
-                string_ignore_case("in-progress".as_bytes()) <|>
-                string_ignore_case("pending".as_bytes()) <|>
-                string_ignore_case("is active".as_bytes());
+    /* require:... directives */
 
-                ret Status::NotDone
-            }
-        )
-    )
-}
+    RequireStatus(StatusDirective),
 
-fn task_status(input: Input<u8>) -> U8Result<TaskBlock> {
+    RequireExcludeStatus(Status),
+    RequireExcludeStatusDelete,
 
-    parse!{input;
+    RequireProjectPrefix(ProjectPath),
+    RequireProject(bool),
 
-        string_ignore_case("status".as_bytes());
-        token(b':');
+    /* inject:... directives */
 
-        look_ahead(non_empty_line);
+    InjectProjectPrefix(ProjectPath),
+    InjectProjectPrefixDelete,
 
-        skip_many(space_or_tab);
+    InjectStatus((Status, EnumSet<Status>)),
+    InjectStatusDelete,
 
-        let status = parse_status();
+    /* ensure:... directives */
 
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
+    EnsureProjectPrefix(ProjectPath),
+    EnsureProjectPrefixDelete
 
-        ret TaskBlock::Status(status)
-    }
 }
 
-fn task_due(input: Input<u8>) -> U8Result<TaskBlock> {
+const DIRECTIVE_INCLUDE: &'static str = "include";
+const DIRECTIVE_DEFAULT_STATUS: &'static str = "default.status";
+const DIRECTIVE_REQUIRE_STATUS: &'static str = "require.status";
+const DIRECTIVE_REQUIRE_EXCLUDE_STATUS: &'static str = "require.exclude.status";
+const DIRECTIVE_REQUIRE_PROJECT_PREFIX: &'static str = "require.project.prefix";
+const DIRECTIVE_REQUIRE_PROJECT: &'static str = "require.project";
+const DIRECTIVE_INJECT_PROJECT_PREFIX: &'static str = "inject.project.prefix";
+const DIRECTIVE_INJECT_STATUS: &'static str = "inject.status";
+const DIRECTIVE_ENSURE_PROJECT_PREFIX: &'static str = "ensure.project.prefix";
+const DIRECTIVE_DELETE: &'static str = "delete";
+
+const DIRECTIVES: &'static [&'static str] = &[
+    DIRECTIVE_INCLUDE,
+    DIRECTIVE_DEFAULT_STATUS,
+    DIRECTIVE_REQUIRE_STATUS,
+    DIRECTIVE_REQUIRE_EXCLUDE_STATUS,
+    DIRECTIVE_REQUIRE_PROJECT_PREFIX,
+    DIRECTIVE_REQUIRE_PROJECT,
+    DIRECTIVE_INJECT_PROJECT_PREFIX,
+    DIRECTIVE_INJECT_STATUS,
+    DIRECTIVE_ENSURE_PROJECT_PREFIX,
+    DIRECTIVE_DELETE
+];
 
-    parse!{input;
+fn directives(input: Input<u8>) -> U8Result<LineToken> {
 
-        string_ignore_case("due".as_bytes());
-        token(b':');
+    parse!{input;
 
-        look_ahead(non_empty_line);
+        let directive: Directive = directive_include() <|>
 
-        skip_many(space_or_tab);
+            /* default:... directives */
+            directive_default_status() <|>
 
-        let due_at = parse_datetime(true);
+            /* require:... directives */
 
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
+            directive_require_status() <|>
 
-        ret TaskBlock::Due(due_at)
-    }
-}
+            directive_require_exclude_status() <|>
 
-fn task_defer(input: Input<u8>) -> U8Result<TaskBlock> {
+            directive_require_project_prefix() <|>
+            directive_require_project() <|>
 
-    parse!{input;
+            /* inject:... directives */
 
-        string_ignore_case("defer till".as_bytes()) <|>
-        string_ignore_case("defer until".as_bytes()) <|>
-        string_ignore_case("defer".as_bytes()) <|>
-        string_ignore_case("hide until".as_bytes()) <|>
-        string_ignore_case("hidden".as_bytes()) <|>
-        string_ignore_case("hide till".as_bytes()) <|>
-        string_ignore_case("hide".as_bytes());
+            directive_inject_project_prefix() <|>
+            directive_inject_status() <|>
 
-        token(b':');
+            /* ensure:... directives */
 
-        look_ahead(non_empty_line);
+            directive_ensure_project_prefix() <|>
 
-        skip_many(space_or_tab);
+            /* deleting */
 
-        let defer = or(
-            |i| parse!{i;
-                string_ignore_case("forever".as_bytes());
-                ret Defer::Forever
-            },
-            |i| parse!{i;
-                let defer_till = parse_datetime(false);
-                ret Defer::Until(defer_till)
-            }
-        );
+            directive_require_exclude_status_delete() <|>
+            directive_inject_project_prefix_delete() <|>
+            directive_ensure_project_prefix_delete();
 
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
 
-        ret TaskBlock::Defer(defer)
+        ret {
+            LineToken::Directive(directive)
+        }
     }
 }
 
-fn task_contexts(input: Input<u8>) -> U8Result<TaskBlock> {
+fn directive_include(input: Input<u8>) -> U8Result<Directive> {
 
     parse!{input;
 
-        string_ignore_case("contexts".as_bytes()) <|>
-        string_ignore_case("context".as_bytes());
-
+        string_ignore_case(DIRECTIVE_INCLUDE.as_bytes());
         token(b':');
 
-        look_ahead(non_empty_line);
+        skip_many(space_or_tab);
 
-        let list = string_list(b',');
+        let line = non_empty_line();
 
-        ret TaskBlock::Contexts(list)
+        ret {
+            let path_to_file: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
+            Directive::Include(path_to_file)
+        }
     }
 }
 
-fn task_tags(input: Input<u8>) -> U8Result<TaskBlock> {
+fn directive_default_status(input: Input<u8>) -> U8Result<Directive> {
 
     parse!{input;
 
-        string_ignore_case("tags".as_bytes()) <|>
-        string_ignore_case("tag".as_bytes());
-
+        string_ignore_case(DIRECTIVE_DEFAULT_STATUS.as_bytes());
         token(b':');
 
-        look_ahead(non_empty_line);
+        skip_many(space_or_tab);
 
-        let list = string_list(b',');
+        let status = parse_status();
 
-        ret TaskBlock::Tags(list)
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+
+        ret Directive::DefaultStatus(status)
     }
 }
 
-fn task_id(input: Input<u8>) -> U8Result<TaskBlock> {
+fn directive_require_status(input: Input<u8>) -> U8Result<Directive> {
 
     parse!{input;
 
-        string_ignore_case("id".as_bytes());
+        string_ignore_case(DIRECTIVE_REQUIRE_STATUS.as_bytes());
         token(b':');
 
-        let line = non_empty_line();
+        skip_many(space_or_tab);
 
-        ret {
-            let id: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
-            TaskBlock::ID(id)
-        }
-    }
-}
+        let require_status = or(
+            |input| parse!{input;
+                let status = parse_status();
 
-/* directives */
+                ret StatusDirective::Status(status)
+            },
+            |input| parse!{input;
 
-#[derive(Debug)]
-enum StatusDirective {
-    Require(bool),
-    Status(Status),
-    // TODO: implement
-    // Explicit(Status)
-}
+                let require = bool_option_parser();
 
-// state for directives that are applied to all files
-// struct GlobalDirectiveSwitches {
+                ret StatusDirective::Require(require)
+            },
+        );
 
-//     // require:project:prefix: /initial/path/to/project
-//     require_project_prefix: Option<ProjectPath>,
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-//     // require:project: true/false/yes/no
-//     require_project: Option<bool>,
+        ret Directive::RequireStatus(require_status)
+    }
+}
 
-//     // project:base: /initial/path/to/project
-//     project_base: Option<ProjectPath>
-// }
+fn directive_require_exclude_status(input: Input<u8>) -> U8Result<Directive> {
 
-// state for directives that are applied locally to files
-struct LocalDirectiveSwitches {
+    parse!{input;
 
-    /* default:... directives */
+        string_ignore_case(DIRECTIVE_REQUIRE_EXCLUDE_STATUS.as_bytes());
+        token(b':');
 
-    // default:status: done/not done
-    default_status: Option<LineLocation<Status>>,
+        skip_many(space_or_tab);
 
-    /* require:... directives */
+        let status = parse_status();
 
-    // require:status: yes/no/true/false/done/not done/incubate
-    require_status: Option<LineLocation<StatusDirective>>,
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-    // require:exclude:status: done/not done/incubate
-    require_exclude_status: Option<LineLocation<Status>>,
+        ret Directive::RequireExcludeStatus(status)
+    }
+}
 
-    // require:project:prefix: /initial/path/to/project
-    //
-    // This enforces tasks to have a certain prefix
-    require_project_prefix: Option<LineLocation<ProjectPath>>,
+fn directive_require_exclude_status_delete(input: Input<u8>) -> U8Result<Directive> {
 
-    // require:project: true/false/yes/no
-    //
-    // This enforces tasks to have a project attribute
-    require_project: Option<LineLocation<bool>>,
+    parse!{input;
 
-    /* inject:... directives */
+        string_ignore_case(DIRECTIVE_DELETE.as_bytes());
 
-    // inject:project:base: /initial/path/to/project
-    //
-    // This injects project path as suffix.
-    inject_project_prefix: Option<LineLocation<ProjectPath>>,
+        space_or_tab();
+        skip_many(space_or_tab);
 
-    inject_status: Option<LineLocation<(Status, EnumSet<Status>)>>,
+        string_ignore_case(DIRECTIVE_REQUIRE_EXCLUDE_STATUS.as_bytes());
 
-    /* ensure:... directives */
-    ensure_project_prefix: Option<LineLocation<ProjectPath>>,
-}
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-impl LocalDirectiveSwitches {
+        ret Directive::RequireExcludeStatusDelete
+    }
+}
 
-    fn new() -> Self {
+fn directive_require_project_prefix(input: Input<u8>) -> U8Result<Directive> {
 
-        LocalDirectiveSwitches {
+    parse!{input;
 
-            /* default:... directives */
+        string_ignore_case(DIRECTIVE_REQUIRE_PROJECT_PREFIX.as_bytes());
+        token(b':');
 
-            default_status: None,
+        skip_many(space_or_tab);
 
-            /* ensure:... directives */
+        let project_prefix = string_list(b'/');
 
-            /* require:... directives */
+        ret Directive::RequireProjectPrefix(project_prefix)
+    }
+}
 
-            require_status: None,
-            require_exclude_status: None,
-            require_project_prefix: None,
-            require_project: None,
+fn directive_require_project(input: Input<u8>) -> U8Result<Directive> {
 
-            /* inject:... directives */
+    parse!{input;
 
-            inject_project_prefix: None,
+        string_ignore_case(DIRECTIVE_REQUIRE_PROJECT.as_bytes());
+        token(b':');
 
-            inject_status: None,
+        skip_many(space_or_tab);
 
-            /* ensure:... directives */
+        let result = bool_option_parser();
 
-            ensure_project_prefix: None,
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-        }
+        ret Directive::RequireProject(result)
     }
+}
 
-    // If necessary, transform the given task based on the directive switches
-    fn transform_task(&self, task: &mut Task, journal: &GTD) {
+fn directive_inject_project_prefix(input: Input<u8>) -> U8Result<Directive> {
 
-        // default.status
-        if task.status.is_none() && self.default_status.is_some() {
+    parse!{input;
 
-            let status = self.default_status
-                .as_ref().unwrap()  // Option
-                .as_ref().unwrap(); // LineLocation
+        string_ignore_case(DIRECTIVE_INJECT_PROJECT_PREFIX.as_bytes());
+        token(b':');
 
-            task.status = Some(status.clone());
-        }
+        skip_many(space_or_tab);
 
-        // inject.project.prefix
-        if let Some(ref project_prefix) = self.inject_project_prefix {
+        let project_path_inject = string_list(b'/');
 
-            let project_prefix: &ProjectPath = project_prefix.as_ref().unwrap();
+        ret Directive::InjectProjectPrefix(project_path_inject)
+    }
+}
 
-            task.project = if task.project.is_some() {
-                let mut path = project_prefix.clone();
-                path.extend_from_slice(task.project.as_ref().unwrap());
-                Some(path)
-            } else {
-                Some(project_prefix.clone())
-            };
-        }
+fn directive_inject_project_prefix_delete(input: Input<u8>) -> U8Result<Directive> {
 
-        // inject.status
-        if let Some(ref inject_status) = self.inject_status {
+    parse!{input;
 
-            let &(ref status, ref exclude): &(Status, EnumSet<Status>) = inject_status.as_ref().unwrap();
+        string_ignore_case(DIRECTIVE_DELETE.as_bytes());
 
-            let ref task_status = match task.status {
-                None => Status::NotDone,
-                Some(ref status) => status.clone()
-            };
+        space_or_tab();
+        skip_many(space_or_tab);
 
-            if !exclude.contains(task_status) {
-                task.status = Some(status.clone());
-            }
+        string_ignore_case(DIRECTIVE_INJECT_PROJECT_PREFIX.as_bytes());
 
-        }
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-        // NOTE: this goes last
-        // ensure.project.prefix
-        if let Some(ref project_prefix) = self.ensure_project_prefix {
+        ret Directive::InjectProjectPrefixDelete
+    }
+}
 
-            let required_project_prefix = project_prefix.as_ref().unwrap();
+fn directive_inject_status(input: Input<u8>) -> U8Result<Directive> {
 
-            let has_required_project_prefix: bool = match task.project {
-                None => false,
-                Some(ref project_path) => {
+    (parse!{input;
 
-                    // ensure task.project has project_path as prefix
+        string_ignore_case(DIRECTIVE_INJECT_STATUS.as_bytes());
+        token(b':');
 
-                    if project_path.len() < required_project_prefix.len() {
-                        false
-                    } else {
-                        project_path.starts_with(required_project_prefix)
-                    }
-                }
-            };
+        skip_many(space_or_tab);
 
-            if !has_required_project_prefix {
-                task.project = if task.project.is_some() {
-                    let mut path = required_project_prefix.clone();
-                    path.extend_from_slice(task.project.as_ref().unwrap());
-                    Some(path)
-                } else {
-                    Some(required_project_prefix.clone())
-                };
-            }
+        let status = parse_status();
 
-        }
-    }
+        let exclude = option(__directive_inject_status_exclude, {
+            let default: EnumSet<Status> = EnumSet::new();
+            default
+        });
 
-    fn pass_validation(&self, task: &Task, journal: &GTD) -> bool {
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-        // require:exclude:status
-        if let Some(ref exclude) = self.require_exclude_status {
+        ret {
+            let result: (Status, EnumSet<Status>) = (status, exclude);
+            result
 
-            let task_status: Status = if task.status.is_none() {
-                // tasks with no explicit task are 'not done' by default
-                Status::NotDone
-            } else {
-                task.status.as_ref().unwrap().clone()
-            };
+        }
+    }).bind(|i, above: (Status, EnumSet<Status>)| {
 
-            let status_exclude = exclude.as_ref().unwrap();
+        let (inject_status, exclude) = above;
 
-            if *status_exclude == task_status {
+        if exclude.contains(&inject_status) {
+            return i.incomplete(1);
+        } else {
+            let result = Directive::InjectStatus((inject_status, exclude));
+            return i.ret(result);
+        }
 
-                println!("From directive `require:exclude:status` in: {}", exclude.location());
+    })
+}
 
-                println!("The following task's `status` attribute should not be {}:\n", status_exclude.string());
+fn __directive_inject_status_exclude(input: Input<u8>) -> U8Result<EnumSet<Status>> {
+    parse!{input;
 
-                _print_task(journal, task, false);
-                process::exit(1);
-            }
-        }
+        // < >[but not ]<status[,...]>
 
-        // require:status
-        if let Some(ref require_status) = self.require_status {
+        space_or_tab();
+        skip_many(space_or_tab);
 
-            match *require_status.as_ref().unwrap() {
+        // [but not ]
+        option(|i| parse!{i;
 
-                StatusDirective::Require(require) => {
+            string_ignore_case("but".as_bytes());
 
-                    if require && task.status.is_none() {
+            space_or_tab();
+            skip_many(space_or_tab);
 
-                        println!("From directive `require:status` in: {}", require_status.location());
+            string_ignore_case("not".as_bytes());
 
-                        println!("The following task is missing an explicit `status` attribute:\n");
+            space_or_tab();
+            skip_many(space_or_tab);
 
-                        _print_task(journal, task, false);
-                        process::exit(1);
-                    }
-                },
+            ret {
+                ()
+            }
+        }, ());
 
-                StatusDirective::Status(ref status) => {
+        let result = parse_list_status();
 
-                    let task_status: Status = if task.status.is_none() {
-                        // tasks with no explicit task are 'not done' by default
-                        Status::NotDone
-                    } else {
-                        task.status.as_ref().unwrap().clone()
-                    };
+        // skip garbage
+        skip_many(|i| or(i,
+            |i| parse!{i;
+                token(b',');
+                ret {()}
+            },
+            space_or_tab
+        ));
 
-                    if *status != task_status {
+        ret result
+    }
+}
 
-                        println!("From directive `require:status` in: {}", require_status.location());
+fn directive_inject_status_delete(input: Input<u8>) -> U8Result<Directive> {
+
+    parse!{input;
 
-                        println!("The following task's `status` attribute needs to be {}:\n", status.string());
+        string_ignore_case(DIRECTIVE_DELETE.as_bytes());
 
-                        _print_task(journal, task, false);
-                        process::exit(1);
-                    }
-                }
+        space_or_tab();
+        skip_many(space_or_tab);
 
-            }
-        }
+        string_ignore_case(DIRECTIVE_INJECT_STATUS.as_bytes());
 
-        // require:project:prefix
-        if let Some(ref require_project_prefix) = self.require_project_prefix {
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-            let required_project_prefix = require_project_prefix.as_ref().unwrap();
+        ret Directive::InjectStatusDelete
+    }
+}
 
-            let has_required_project_prefix: bool = match task.project {
-                None => false,
-                Some(ref project_path) => {
+fn directive_ensure_project_prefix(input: Input<u8>) -> U8Result<Directive> {
 
-                    // ensure task.project has project_path as prefix
+    parse!{input;
 
-                    if project_path.len() < required_project_prefix.len() {
-                        false
-                    } else {
-                        project_path.starts_with(required_project_prefix)
-                    }
-                }
-            };
+        string_ignore_case(DIRECTIVE_ENSURE_PROJECT_PREFIX.as_bytes());
+        token(b':');
 
-            if !has_required_project_prefix {
+        skip_many(space_or_tab);
 
-                println!("From directive `require:project:prefix` in: {}", require_project_prefix.location());
+        let project_base = string_list(b'/');
 
-                println!("The following task's `project` attribute does not being with the required prefix: {}:\n",
-                    required_project_prefix.join(" / "));
+        ret Directive::EnsureProjectPrefix(project_base)
+    }
+}
 
-                _print_task(journal, task, false);
-                process::exit(1);
-            }
+fn directive_ensure_project_prefix_delete(input: Input<u8>) -> U8Result<Directive> {
 
-        }
+    parse!{input;
 
-        // require:project
-        if let Some(ref require_project) = self.require_project {
-            if *require_project.as_ref().unwrap() && task.project.is_none() {
+        string_ignore_case(DIRECTIVE_DELETE.as_bytes());
 
-                println!("From directive `require:project` in: {}", require_project.location());
+        space_or_tab();
+        skip_many(space_or_tab);
 
-                println!("The following task is missing an explicit `project` attribute:\n");
+        string_ignore_case(DIRECTIVE_ENSURE_PROJECT_PREFIX.as_bytes());
 
-                _print_task(journal, task, false);
-                process::exit(1);
-            }
-        }
+        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-        return true;
+        ret Directive::EnsureProjectPrefixDelete
     }
-
 }
 
-#[derive(Debug)]
-enum Directive {
+/* line parsers */
 
-    // include: path/to/file
-    Include(String),
+enum Line {
+    Empty,
+    NonEmpty(Vec<u8>)
+}
 
-    /* default:... directives */
+fn non_empty_line(i: Input<u8>) -> U8Result<Vec<u8>> {
+    parse_line(i)
+        .bind(parse_non_empty_line)
+}
 
-    DefaultStatus(Status),
+// TODO: bother moving as closure?
+fn parse_non_empty_line(i: Input<u8>, above: Line) -> U8Result<Vec<u8>> {
+    match above {
+        Line::Empty => {
+            // need at least one u8 token
+            i.incomplete(1)
+        },
+        Line::NonEmpty(line) => {
 
-    /* require:... directives */
+            if line.len() <= 0 {
+                return i.incomplete(1);
+            }
 
-    RequireStatus(StatusDirective),
+            i.ret(line)
+        }
+    }
+}
 
-    RequireExcludeStatus(Status),
-    RequireExcludeStatusDelete,
+fn parse_line(i: Input<u8>) -> U8Result<Line> {
 
-    RequireProjectPrefix(ProjectPath),
-    RequireProject(bool),
+    or(i,
+        |i| parse!{i;
+            terminating();
+            ret Line::Empty
+        },
+        |i| parse!{i;
 
-    /* inject:... directives */
+            // TODO: remove; out of scope for this parser
+            // lines with just whitespace are probably not interesting
+            // skip_many(whitespace);
 
-    InjectProjectPrefix(ProjectPath),
-    InjectProjectPrefixDelete,
+            let line: Vec<u8> = many_till(any, terminating);
+            ret Line::NonEmpty(line)
+        }
+    )
 
-    InjectStatus((Status, EnumSet<Status>)),
-    InjectStatusDelete,
+}
 
-    /* ensure:... directives */
+/* delimited parser */
 
-    EnsureProjectPrefix(ProjectPath),
-    EnsureProjectPrefixDelete
+// TODO: clean this up and implement. this isn't high-priority.
 
-}
+// Original
+// E --> T {, T}
+//
+// let line: Vec<u8> = many_till(any, terminating);
+// let line: Vec<u8> = many_till(any, delim);
 
-fn directives(input: Input<u8>) -> U8Result<LineToken> {
+// enum Delimited {
+//     Empty,
+//     NonEmpty(String)
+// }
 
-    parse!{input;
+// fn consume_up_to<'a, F, G>(input: Input<'a, u8>, mut consumer: F, ends_with: G) -> U8Result<'a, String>
+//     where
+//     F: FnMut(Input<'a, u8>) -> U8Result<u8>,
+//     G: FnOnce(Input<'a, u8>) -> U8Result<()> + Copy {
 
-        let directive: Directive = directive_include() <|>
+//     // let ends_with = |x| ends_with(x);
 
-            /* default:... directives */
-            directive_default_status() <|>
 
-            /* require:... directives */
 
-            directive_require_status() <|>
+//     or(input,
+//         |i| parse!{i;
+//             ends_with();
+//             ret Delimited::Empty
+//         },
+//         |i| parse!{i;
 
-            directive_require_exclude_status() <|>
+//             // lines with just whitespace are probably not interesting
+//             // TODO: consider space_or_tab?
+//             // skip_many(|i| whitespace(i));
 
-            directive_require_project_prefix() <|>
-            directive_require_project() <|>
+//             let line: Vec<u8> = many_till(|i| consumer(i), ends_with);
 
-            /* inject:... directives */
+//             ret {
+//                 let line: String = format!("{}", String::from_utf8_lossy(line.as_slice()));
 
-            directive_inject_project_prefix() <|>
-            directive_inject_status() <|>
+//                 if line.len() > 0 {
+//                     Delimited::NonEmpty(line)
+//                 } else {
+//                     Delimited::Empty
+//                 }
 
-            /* ensure:... directives */
+//             }
+//         }
+//     )
+//     .bind(|i, above| {
+//         match above {
+//             Delimited::Empty => {
+//                 // need at least one u8 token
+//                 i.incomplete(1)
+//             },
+//             Delimited::NonEmpty(line) => {
 
-            directive_ensure_project_prefix() <|>
+//                 if line.len() <= 0 {
+//                     return i.incomplete(1);
+//                 }
 
-            /* deleting */
+//                 i.ret(line)
+//             }
+//         }
+//     })
+// }
 
-            directive_require_exclude_status_delete() <|>
-            directive_inject_project_prefix_delete() <|>
-            directive_ensure_project_prefix_delete();
+/* task separator */
 
+fn task_seperators(input: Input<u8>) -> U8Result<LineToken> {
+    parse!{input;
+
+        parse_task_separator("-".as_bytes()) <|>
+        parse_task_separator("=".as_bytes()) <|>
+        parse_task_separator("_".as_bytes()) <|>
+        // TODO: necessary?
+        parse_task_separator("#".as_bytes()) <|>
+        parse_task_separator("/".as_bytes()) <|>
+        parse_task_separator(":".as_bytes()) <|>
+        parse_task_separator("~".as_bytes()) <|>
+        parse_task_separator("*".as_bytes());
 
         ret {
-            LineToken::Directive(directive)
+            LineToken::TaskSeparator
         }
     }
 }
 
-fn directive_include(input: Input<u8>) -> U8Result<Directive> {
+fn parse_task_separator<'a>(input: Input<'a, u8>, token: &[u8])
+-> SimpleResult<'a, u8, ()> {
 
     parse!{input;
 
-        string_ignore_case("include".as_bytes());
-        token(b':');
-
-        skip_many(space_or_tab);
-
-        let line = non_empty_line();
+        match_four_tokens(token);
+        skip_many(|i| string(i, token));
+        let _line: Vec<()> = many_till(space_or_tab, terminating);
 
-        ret {
-            let path_to_file: String = String::from_utf8_lossy(line.as_slice()).trim().to_owned();
-            Directive::Include(path_to_file)
-        }
+        ret ()
     }
 }
 
-fn directive_default_status(input: Input<u8>) -> U8Result<Directive> {
-
-    parse!{input;
+/* comments parsers */
 
-        string_ignore_case("default".as_bytes());
-        token(b'.');
-        string_ignore_case("status".as_bytes());
-        token(b':');
+fn comments_one_line(i: Input<u8>) -> U8Result<()> {
+    parse!{i;
 
-        skip_many(space_or_tab);
+        string("//".as_bytes()) <|>
+        string("#".as_bytes()) <|>
+        string(";".as_bytes());
 
-        let status = parse_status();
+        let _line: Vec<u8> = many_till(any, terminating);
+        ret ()
+    }
+}
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+fn comments_block(i: Input<u8>) -> U8Result<()> {
+    parse!{i;
+        string("/*".as_bytes());
 
-        ret Directive::DefaultStatus(status)
+        let _line: Vec<u8> = many_till(any, |i| string(i, "*/".as_bytes()));
+        ret ()
     }
 }
 
-fn directive_require_status(input: Input<u8>) -> U8Result<Directive> {
+/* list parsers */
 
+fn parse_list_status(input: Input<u8>) -> U8Result<EnumSet<Status>> {
     parse!{input;
+        let result = parse_list(parse_list_status_delimeter, parse_list_status_reducer);
+        ret result
+    }
+}
 
-        string_ignore_case("require".as_bytes());
-        token(b'.');
-        string_ignore_case("status".as_bytes());
-        token(b':');
-
-        skip_many(space_or_tab);
-
-        let require_status = or(
-            |input| parse!{input;
-                let status = parse_status();
-
-                ret StatusDirective::Status(status)
-            },
-            |input| parse!{input;
-
-                let require = bool_option_parser();
+fn parse_list_status_delimeter(input: Input<u8>) -> U8Result<()> {
+    parse!{input;
 
-                ret StatusDirective::Require(require)
+        or(
+            |i| parse!{i;
+                skip_many(space_or_tab);
+                token(b',');
+                ret {()}
             },
+            space_or_tab
         );
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+        skip_many(|i| or(i,
+            |i| parse!{i;
+                token(b',');
+                ret {()}
+            },
+            space_or_tab
+        ));
 
-        ret Directive::RequireStatus(require_status)
+        ret {()}
     }
 }
 
-fn directive_require_exclude_status(input: Input<u8>) -> U8Result<Directive> {
-
+fn parse_list_status_reducer(input: Input<u8>, accumulator: Rc<RefCell<EnumSet<Status>>>) -> U8Result<()> {
     parse!{input;
-
-        string_ignore_case("require".as_bytes());
-        token(b'.');
-        string_ignore_case("exclude".as_bytes());
-        token(b'.');
-        string_ignore_case("status".as_bytes());
-        token(b':');
-
-        skip_many(space_or_tab);
-
         let status = parse_status();
+        ret {
+            accumulator.borrow_mut().insert(status);
+            ()
+        }
+    }
+}
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+#[test]
+fn parse_list_status_test() {
 
-        ret Directive::RequireExcludeStatus(status)
+    match parse_only(|i| parse_list_status(i), "".as_bytes()) {
+        Ok(_) => {
+            assert!(false);
+        },
+        Err(_) => {
+            assert!(true);
+        }
     }
-}
 
-fn directive_require_exclude_status_delete(input: Input<u8>) -> U8Result<Directive> {
+    match parse_only(|i| parse_list_status(i), ",,,,".as_bytes()) {
+        Ok(_) => {
+            assert!(false);
+        },
+        Err(_) => {
+            assert!(true);
+        }
+    }
 
-    parse!{input;
+    match parse_only(|i| parse_list_status(i), ",,  ,, active".as_bytes()) {
+        Ok(_) => {
+            assert!(false);
+        },
+        Err(_) => {
+            assert!(true);
+        }
+    }
 
-        string_ignore_case("delete".as_bytes());
+    match parse_only(|i| parse_list_status(i), "active ,,  ,,,".as_bytes()) {
+        Ok(result) => {
 
-        space_or_tab();
-        skip_many(space_or_tab);
+            let mut set: EnumSet<Status> = EnumSet::new();
+            set.insert(Status::NotDone);
+
+            assert_eq!(result, set);
+        },
+        Err(_) => {
+            assert!(true);
+        }
+    }
 
-        string_ignore_case("require".as_bytes());
-        token(b'.');
-        string_ignore_case("exclude".as_bytes());
-        token(b'.');
-        string_ignore_case("status".as_bytes());
+    match parse_only(|i| parse_list_status(i), "active".as_bytes()) {
+        Ok(result) => {
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+            let mut set: EnumSet<Status> = EnumSet::new();
+            set.insert(Status::NotDone);
 
-        ret Directive::RequireExcludeStatusDelete
+            assert_eq!(result, set);
+        },
+        Err(_) => {
+            assert!(false);
+        }
     }
-}
 
-fn directive_require_project_prefix(input: Input<u8>) -> U8Result<Directive> {
+    match parse_only(|i| parse_list_status(i), "done,,,,, not done".as_bytes()) {
+        Ok(result) => {
 
-    parse!{input;
+            let mut set: EnumSet<Status> = EnumSet::new();
+            set.insert(Status::NotDone);
+            set.insert(Status::Done);
 
-        string_ignore_case("require".as_bytes());
-        token(b'.');
-        string_ignore_case("project".as_bytes());
-        token(b'.');
-        string_ignore_case("prefix".as_bytes());
-        token(b':');
+            assert_eq!(result, set);
+            // println!("{:?}", result);
+            // journal.filter_priority = Some(result);
+        },
+        Err(_) => {
+            assert!(false);
+        }
+    }
 
-        skip_many(space_or_tab);
+    match parse_only(|i| parse_list_status(i), "done    not done".as_bytes()) {
+        Ok(result) => {
 
-        let project_prefix = string_list(b'/');
+            let mut set: EnumSet<Status> = EnumSet::new();
+            set.insert(Status::NotDone);
+            set.insert(Status::Done);
 
-        ret Directive::RequireProjectPrefix(project_prefix)
+            assert_eq!(result, set);
+            // println!("{:?}", result);
+            // journal.filter_priority = Some(result);
+        },
+        Err(_) => {
+            assert!(false);
+        }
     }
 }
 
-fn directive_require_project(input: Input<u8>) -> U8Result<Directive> {
-
-    parse!{input;
+/* new delimeted list parser */
 
-        string_ignore_case("require".as_bytes());
-        token(b'.');
-        string_ignore_case("project".as_bytes());
-        token(b':');
+// TODO: remove
+// list(delim) = status rest(delim) | status
+// rest(delim) = delim status rest(delim) | delim status
 
-        skip_many(space_or_tab);
+// invariant:
+// - item does not consume eof (may lookahead eof to stop)
+// - item does not consume delim (may lookahead delim to stop)
+// - delim does not consume eof
+//
+// list(delim) = item rest(delim) | item
+// rest(delim) = delim item rest(delim) | delim item
 
-        let result = bool_option_parser();
+fn parse_list<'a, D, Delim: 'a, A, R>(input: Input<'a, u8>, delimeter: D, reducer: R)
+-> U8Result<'a, A>
+    where
+    D: FnOnce(Input<'a, u8>) -> U8Result<'a, Delim> + Copy,
+    R: FnOnce(Input<'a, u8>, Rc<RefCell<A>>) -> U8Result<'a, ()> + Copy,
+    A: Default {
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+    let accumulator: A = Default::default();
+    let initial_accumulator: Rc<RefCell<A>> = Rc::new(RefCell::new(accumulator));
 
-        ret Directive::RequireProject(result)
-    }
-}
+    or(input,
+        |input| parse!{input;
 
-fn directive_inject_project_prefix(input: Input<u8>) -> U8Result<Directive> {
+            reducer(initial_accumulator.clone());
 
-    parse!{input;
+            parse_list_rest(delimeter, initial_accumulator.clone(), reducer);
 
-        string_ignore_case("inject".as_bytes());
-        token(b'.');
-        string_ignore_case("project".as_bytes());
-        token(b'.');
-        string_ignore_case("prefix".as_bytes());
-        token(b':');
+            ret {
+                Rc::try_unwrap(initial_accumulator)
+                    .ok()
+                    .unwrap()
+                    .into_inner()
+            }
 
-        skip_many(space_or_tab);
+        },
+        |input| {
+            let accumulator: A = Default::default();
+            let initial_accumulator: Rc<RefCell<A>> = Rc::new(RefCell::new(accumulator));
+            parse!{input;
+                reducer(initial_accumulator.clone());
 
-        let project_path_inject = string_list(b'/');
+                ret {
+                    Rc::try_unwrap(initial_accumulator)
+                        .ok()
+                        .unwrap()
+                        .into_inner()
+                }
+            }
+        }
 
-        ret Directive::InjectProjectPrefix(project_path_inject)
-    }
+    )
 }
 
-fn directive_inject_project_prefix_delete(input: Input<u8>) -> U8Result<Directive> {
-
-    parse!{input;
+fn parse_list_rest<'a, D, Delim: 'a, A, R>(
+    input: Input<'a, u8>,
+    delimeter: D,
+    accumulator: Rc<RefCell<A>>,
+    reducer: R)
+-> U8Result<'a, ()>
+    where
+    D: FnOnce(Input<'a, u8>) -> U8Result<'a, Delim> + Copy,
+    R: FnOnce(Input<'a, u8>, Rc<RefCell<A>>) -> U8Result<'a, ()> + Copy,
+    A: Default {
 
-        string_ignore_case("delete".as_bytes());
 
-        space_or_tab();
-        skip_many(space_or_tab);
+    parse!{input;
 
-        string_ignore_case("inject".as_bytes());
-        token(b'.');
-        string_ignore_case("project".as_bytes());
-        token(b'.');
-        string_ignore_case("prefix".as_bytes());
+        delimeter();
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+        or(
+            |i| parse!{i;
+                reducer(accumulator.clone());
+                parse_list_rest(delimeter, accumulator.clone(), reducer);
+                ret {
+                    ()
+                }
+            },
+            |i| parse!{i;
+                reducer(accumulator.clone());
+                ret {
+                    ()
+                }
+            }
+        );
 
-        ret Directive::InjectProjectPrefixDelete
+        ret {
+            ()
+        }
     }
 }
 
-fn directive_inject_status(input: Input<u8>) -> U8Result<Directive> {
-
-    (parse!{input;
-
-        string_ignore_case("inject".as_bytes());
-        token(b'.');
-        string_ignore_case("status".as_bytes());
-        token(b':');
+// TODO: remove
 
-        skip_many(space_or_tab);
+// fn ___parse_list<'a, F, G, T: 'a, U>(input: Input<'a, u8>, delimeter: F, parse_item: G) -> U8Result<'a, Vec<U>>
+//     where
+//     F: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
+//     G: FnOnce(Input<'a, u8>) -> U8Result<'a, U> + Copy {
 
-        let status = parse_status();
+//     let start_list: Rc<RefCell<Vec<U>>> = Rc::new(RefCell::new(Vec::new()));
 
-        let exclude = option(__directive_inject_status_exclude, {
-            let default: EnumSet<Status> = EnumSet::new();
-            default
-        });
+//     or(input,
+//         |input| parse!{input;
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
+//             let item: U = parse_item();
 
-        ret {
-            let result: (Status, EnumSet<Status>) = (status, exclude);
-            result
+//             __parse_list_rest({
+//                 start_list.borrow_mut().push(item);
 
-        }
-    }).bind(|i, above: (Status, EnumSet<Status>)| {
+//                 start_list.clone()
+//             }, delimeter, parse_item);
 
-        let (inject_status, exclude) = above;
+//             ret {
+//                 Rc::try_unwrap(start_list)
+//                     .ok()
+//                     .unwrap()
+//                     .into_inner()
+//             }
+//         },
+//         |input| parse!{input;
 
-        if exclude.contains(&inject_status) {
-            return i.incomplete(1);
-        } else {
-            let result = Directive::InjectStatus((inject_status, exclude));
-            return i.ret(result);
-        }
+//             let item: U = parse_item();
+//             ret {
+//                 let mut list: Vec<U> = Vec::new();
+//                 list.push(item);
+//                 list
+//             }
+//         }
+//     )
+// }
 
-    })
-}
+// fn __parse_list_rest<'a, F, G, T: 'a, U: 'a>(
+//     input: Input<'a, u8>,
+//     current_list: Rc<RefCell<Vec<U>>>,
+//     delimeter: F,
+//     parse_item: G) -> U8Result<'a, ()>
+//     where
+//     F: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
+//     G: FnOnce(Input<'a, u8>) -> U8Result<'a, U> + Copy {
 
-fn __directive_inject_status_exclude(input: Input<u8>) -> U8Result<EnumSet<Status>> {
-    parse!{input;
+//     let current_list_1 = current_list.clone();
+//     let current_list_2 = current_list.clone();
 
-        // < >[but not ]<status[,...]>
+//     parse!{input;
 
-        space_or_tab();
-        skip_many(space_or_tab);
+//         delimeter();
 
-        // [but not ]
-        option(|i| parse!{i;
+//         or(
+//             |i| parse!{i;
 
-            string_ignore_case("but".as_bytes());
+//                 let item: U = parse_item();
 
-            space_or_tab();
-            skip_many(space_or_tab);
+//                 __parse_list_rest({
+//                     current_list_1.borrow_mut().push(item);
+//                     current_list_1
+//                 }, delimeter, parse_item);
 
-            string_ignore_case("not".as_bytes());
+//                 ret {
+//                     ()
+//                 }
+//             },
+//             |i| parse!{i;
 
-            space_or_tab();
-            skip_many(space_or_tab);
+//                 let item: U = parse_item();
 
-            ret {
-                ()
-            }
-        }, ());
+//                 ret {
+//                     current_list_2.borrow_mut().push(item);
+//                     ()
+//                 }
+//             }
+//         );
 
-        let result = parse_list_status();
+//         ret {
+//             ()
+//         }
+//     }
+// }
 
-        // skip garbage
-        skip_many(|i| or(i,
-            |i| parse!{i;
-                token(b',');
-                ret {()}
-            },
-            space_or_tab
-        ));
+/* delimited list parser */
 
+fn parse_string_lists(input: Input<u8>, delim: u8) -> U8Result<Vec<String>> {
+    parse!{input;
+        skip_many(space_or_tab);
+        let result = string_list(delim);
+        skip_many(space_or_tab);
+        eof();
         ret result
     }
 }
 
-fn directive_inject_status_delete(input: Input<u8>) -> U8Result<Directive> {
-
+fn string_list(input: Input<u8>, delim: u8) -> U8Result<Vec<String>> {
     parse!{input;
 
-        string_ignore_case("delete".as_bytes());
+        let line = non_empty_line();
 
-        space_or_tab();
-        skip_many(space_or_tab);
+        ret {
+            let line: Vec<u8> = line;
 
-        string_ignore_case("inject".as_bytes());
-        token(b'.');
-        string_ignore_case("status".as_bytes());
+            // TODO: eventually refactor using consume_up_to
+            let list: Vec<String> = String::from_utf8_lossy(line.as_slice())
+                .trim()
+                .split(delim as char).map(|c| c.trim().to_string())
+                .filter(|x| x.len() > 0)
+                .collect();
 
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
 
-        ret Directive::InjectStatusDelete
+            list
+        }
     }
 }
 
-fn directive_ensure_project_prefix(input: Input<u8>) -> U8Result<Directive> {
+/* misc parsers */
 
-    parse!{input;
+fn bool_option_parser(i: Input<u8>) -> U8Result<bool> {
+    or(i,
+        |i| parse!{i;
 
-        string_ignore_case("ensure".as_bytes());
-        token(b'.');
-        string_ignore_case("project".as_bytes());
-        token(b'.');
-        string_ignore_case("prefix".as_bytes());
-        token(b':');
+            string_ignore_case("yes".as_bytes()) <|>
+            string_ignore_case("y".as_bytes()) <|>
+            string_ignore_case("true".as_bytes());
 
-        skip_many(space_or_tab);
+            ret true
+        },
+        |i| parse!{i;
 
-        let project_base = string_list(b'/');
+            string_ignore_case("no".as_bytes()) <|>
+            string_ignore_case("n".as_bytes()) <|>
+            string_ignore_case("false".as_bytes());
 
-        ret Directive::EnsureProjectPrefix(project_base)
-    }
+            ret false
+        }
+    )
 }
 
-fn directive_ensure_project_prefix_delete(input: Input<u8>) -> U8Result<Directive> {
+fn match_four_tokens<'a>(input: Input<'a, u8>, token: &[u8])
+-> SimpleResult<'a, u8, ()> {
 
     parse!{input;
+        string(token);
+        string(token);
+        string(token);
+        string(token);
 
-        string_ignore_case("delete".as_bytes());
-
-        space_or_tab();
-        skip_many(space_or_tab);
-
-        string_ignore_case("ensure".as_bytes());
-        token(b'.');
-        string_ignore_case("project".as_bytes());
-        token(b'.');
-        string_ignore_case("prefix".as_bytes());
-
-        let _nothing: Vec<()> = many_till(space_or_tab, terminating);
-
-        ret Directive::EnsureProjectPrefixDelete
+        ret ()
     }
 }
 
-/* line parsers */
-
-enum Line {
-    Empty,
-    NonEmpty(Vec<u8>)
+fn whitespace(i: Input<u8>) -> U8Result<()> {
+    parse!{i;
+        satisfy(is_whitespace);
+        ret ()
+    }
 }
 
-fn non_empty_line(i: Input<u8>) -> U8Result<Vec<u8>> {
-    parse_line(i)
-        .bind(parse_non_empty_line)
+fn space_or_tab(input: Input<u8>) -> U8Result<()> {
+    parse!{input;
+        or(
+            |i| token(i, b' '),
+            |i| token(i, b'\t')
+        );
+        ret ()
+    }
 }
 
-// TODO: bother moving as closure?
-fn parse_non_empty_line(i: Input<u8>, above: Line) -> U8Result<Vec<u8>> {
-    match above {
-        Line::Empty => {
-            // need at least one u8 token
-            i.incomplete(1)
+fn non_terminating(i: Input<u8>) -> U8Result<u8> {
+
+    or(i,
+        |i| parse!{i;
+            terminating();
+            ret None
         },
-        Line::NonEmpty(line) => {
+        |i| parse!{i;
 
-            if line.len() <= 0 {
+            let something = any();
+
+            ret Some(something)
+        }
+    )
+    .bind(|i, above: Option<u8>| {
+
+        match above {
+            None => {
                 return i.incomplete(1);
+            },
+            Some(c) => {
+                return i.ret(c);
             }
-
-            i.ret(line)
         }
-    }
-}
 
-fn parse_line(i: Input<u8>) -> U8Result<Line> {
+    })
+}
 
+// match eof or various eol
+fn terminating(i: Input<u8>) -> U8Result<()> {
     or(i,
         |i| parse!{i;
-            terminating();
-            ret Line::Empty
+            end_of_line();
+            ret ()
         },
-        |i| parse!{i;
-
-            // TODO: remove; out of scope for this parser
-            // lines with just whitespace are probably not interesting
-            // skip_many(whitespace);
+        // NOTE: eof should be matched last
+        eof
+    )
+}
 
-            let line: Vec<u8> = many_till(any, terminating);
-            ret Line::NonEmpty(line)
-        }
+// Source: https://en.wikipedia.org/wiki/Newline#Unicode
+fn end_of_line(i: Input<u8>) -> U8Result<&[u8]> {
+    // TODO: bother to refactor using parse! macro with <|> operator?
+    or(i,
+        |i| parse!{i;
+            token(b'\r');
+            token(b'\n');
+            ret "\r\n".as_bytes()
+        },
+        |i| or(i,
+            |i| parse!{i;
+                token(b'\n');
+                ret "\n".as_bytes()
+            },
+            |i| or(i,
+                |i| parse!{i;
+                    token(b'\r');
+                    ret "\r".as_bytes()
+                },
+                |i| or(i,
+                    |i| parse!{i;
+                        string("\u{2028}".as_bytes());
+                        ret "\u{2028}".as_bytes()
+                    },
+                    |i| or(i,
+                        |i| parse!{i;
+                            string("\u{2029}".as_bytes());
+                            ret "\u{2029}".as_bytes()
+                        },
+                        |i| or(i,
+                            |i| parse!{i;
+                                string("\u{000B}".as_bytes());
+                                ret "\u{000B}".as_bytes()
+                            },
+                            |i| or(i,
+                                |i| parse!{i;
+                                    string("\u{000C}".as_bytes());
+                                    ret "\u{000C}".as_bytes()
+                                },
+                                |i| parse!{i;
+                                    string("\u{0085}".as_bytes());
+                                    ret "\u{0085}".as_bytes()
+                                }
+                            )
+                        )
+                    )
+                )
+            )
+        )
     )
-
 }
 
-/* delimited parser */
-
-// TODO: clean this up and implement. this isn't high-priority.
-
-// Original
-// E --> T {, T}
-//
-// let line: Vec<u8> = many_till(any, terminating);
-// let line: Vec<u8> = many_till(any, delim);
+fn string_ignore_case<'a>(i: Input<'a, u8>, s: &[u8])
+    -> SimpleResult<'a, u8, &'a [u8]> {
+    let b = i.buffer();
 
-// enum Delimited {
-//     Empty,
-//     NonEmpty(String)
-// }
+    if s.len() > b.len() {
+        return i.incomplete(s.len() - b.len());
+    }
 
-// fn consume_up_to<'a, F, G>(input: Input<'a, u8>, mut consumer: F, ends_with: G) -> U8Result<'a, String>
-//     where
-//     F: FnMut(Input<'a, u8>) -> U8Result<u8>,
-//     G: FnOnce(Input<'a, u8>) -> U8Result<()> + Copy {
+    let d = &b[..s.len()];
 
-//     // let ends_with = |x| ends_with(x);
+    for j in 0..s.len() {
 
+        if !(s[j]).eq_ignore_ascii_case(&(d[j])) {
+            return i.replace(&b[j..]).err(Error::expected(d[j]))
+        }
+    }
 
+    i.replace(&b[s.len()..]).ret(d)
+}
 
-//     or(input,
-//         |i| parse!{i;
-//             ends_with();
-//             ret Delimited::Empty
-//         },
-//         |i| parse!{i;
+// match any of the given strings, ignoring case; they're tried in order, as with `<|>`
+fn string_ignore_case_any<'a>(i: Input<'a, u8>, strings: &[&str])
+    -> SimpleResult<'a, u8, &'a [u8]> {
 
-//             // lines with just whitespace are probably not interesting
-//             // TODO: consider space_or_tab?
-//             // skip_many(|i| whitespace(i));
+    let (last, strings) = match strings.split_last() {
+        None => unsafe { debug_unreachable!("string_ignore_case_any: no strings given") },
+        Some(split) => split
+    };
 
-//             let line: Vec<u8> = many_till(|i| consumer(i), ends_with);
+    let b = i.buffer();
 
-//             ret {
-//                 let line: String = format!("{}", String::from_utf8_lossy(line.as_slice()));
+    for s in strings {
 
-//                 if line.len() > 0 {
-//                     Delimited::NonEmpty(line)
-//                 } else {
-//                     Delimited::Empty
-//                 }
+        let s = s.as_bytes();
 
-//             }
-//         }
-//     )
-//     .bind(|i, above| {
-//         match above {
-//             Delimited::Empty => {
-//                 // need at least one u8 token
-//                 i.incomplete(1)
-//             },
-//             Delimited::NonEmpty(line) => {
+        if s.len() > b.len() {
+            return i.incomplete(s.len() - b.len());
+        }
 
-//                 if line.len() <= 0 {
-//                     return i.incomplete(1);
-//                 }
+        if s.eq_ignore_ascii_case(&b[..s.len()]) {
+            return i.replace(&b[s.len()..]).ret(&b[..s.len()]);
+        }
+    }
 
-//                 i.ret(line)
-//             }
-//         }
-//     })
-// }
+    string_ignore_case(i, last.as_bytes())
+}
 
-/* task separator */
+fn signed_decimal(input: Input<u8>) -> U8Result<i64> {
 
-fn task_seperators(input: Input<u8>) -> U8Result<LineToken> {
     parse!{input;
+        let sign: i64 = or(
+            |i| parse!{i;
+                token(b'-');
+                ret -1
+            },
+            |i| parse!{i;
+                token(b'+');
+                ret 1
+            }
+        );
 
-        parse_task_separator("-".as_bytes()) <|>
-        parse_task_separator("=".as_bytes()) <|>
-        parse_task_separator("_".as_bytes()) <|>
-        // TODO: necessary?
-        parse_task_separator("#".as_bytes()) <|>
-        parse_task_separator("/".as_bytes()) <|>
-        parse_task_separator(":".as_bytes()) <|>
-        parse_task_separator("~".as_bytes()) <|>
-        parse_task_separator("*".as_bytes());
+        let num: i64 = decimal();
 
         ret {
-            LineToken::TaskSeparator
+            sign * num
         }
     }
 }
 
-fn parse_task_separator<'a>(input: Input<'a, u8>, token: &[u8])
--> SimpleResult<'a, u8, ()> {
-
-    parse!{input;
+/* inequality parsers */
 
-        match_four_tokens(token);
-        skip_many(|i| string(i, token));
-        let _line: Vec<()> = many_till(space_or_tab, terminating);
+#[derive(Debug, Clone)]
+enum Inequality {
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+    Equal
+}
 
-        ret ()
+fn satisfy_inequality<T: Ord>(inequality: &Inequality, left: T, right: T) -> bool {
+    match *inequality {
+        Inequality::GreaterThan => left > right,
+        Inequality::GreaterThanOrEqual => left >= right,
+        Inequality::LessThan => left < right,
+        Inequality::LessThanOrEqual => left <= right,
+        Inequality::Equal => left == right
     }
 }
 
-/* comments parsers */
+fn inequality_art(inequality: &Inequality) -> &'static str {
+    match *inequality {
+        Inequality::GreaterThan => ">",
+        Inequality::GreaterThanOrEqual => ">=",
+        Inequality::LessThan => "<",
+        Inequality::LessThanOrEqual => "<=",
+        Inequality::Equal => "=="
+    }
+}
 
-fn comments_one_line(i: Input<u8>) -> U8Result<()> {
-    parse!{i;
+/* filter trees */
 
-        string("//".as_bytes()) <|>
-        string("#".as_bytes()) <|>
-        string(";".as_bytes());
+// boolean expression of filters (e.g. >= 5 and (< 10 or not = 7))
+#[derive(Debug, Clone)]
+enum FilterTree<T> {
+    Union(Box<FilterTree<T>>, Box<FilterTree<T>>),
+    Intersection(Box<FilterTree<T>>, Box<FilterTree<T>>),
+    Negation(Box<FilterTree<T>>),
+    Leaf(T)
+}
 
-        let _line: Vec<u8> = many_till(any, terminating);
-        ret ()
+impl<T> FilterTree<T> {
+    fn is_leaf(&self) -> bool {
+        match *self {
+            FilterTree::Leaf(_) => true,
+            _ => false,
+        }
     }
 }
 
-fn comments_block(i: Input<u8>) -> U8Result<()> {
-    parse!{i;
-        string("/*".as_bytes());
+fn pretty_tree_art<T, F>(filter_tree: &FilterTree<T>, leaf_art: &F) -> String
+    where F: Fn(&T) -> String {
 
-        let _line: Vec<u8> = many_till(any, |i| string(i, "*/".as_bytes()));
-        ret ()
+    match *filter_tree {
+        FilterTree::Leaf(ref filter) => {
+            return leaf_art(filter);
+        },
+        FilterTree::Union(ref left_tree, ref right_tree) => {
+
+            return format!("{} or {}",
+                pretty_subtree_art(left_tree, leaf_art),
+                pretty_subtree_art(right_tree, leaf_art));
+        },
+        FilterTree::Intersection(ref left_tree, ref right_tree) => {
+
+            return format!("{} and {}",
+                pretty_subtree_art(left_tree, leaf_art),
+                pretty_subtree_art(right_tree, leaf_art));
+        },
+        FilterTree::Negation(ref tree) => {
+            return format!("not {}", pretty_subtree_art(tree, leaf_art));
+        }
     }
+
 }
 
-/* list parsers */
+fn pretty_subtree_art<T, F>(filter_tree: &FilterTree<T>, leaf_art: &F) -> String
+    where F: Fn(&T) -> String {
 
-fn parse_list_status(input: Input<u8>) -> U8Result<EnumSet<Status>> {
-    parse!{input;
-        let result = parse_list(parse_list_status_delimeter, parse_list_status_reducer);
-        ret result
+    if filter_tree.is_leaf() {
+        return pretty_tree_art(filter_tree, leaf_art);
     }
+
+    return format!("({})", pretty_tree_art(filter_tree, leaf_art));
 }
 
-fn parse_list_status_delimeter(input: Input<u8>) -> U8Result<()> {
-    parse!{input;
+fn satisfy_tree<T, F>(filter_tree: &FilterTree<T>, satisfy_leaf: &F) -> bool
+    where F: Fn(&T) -> bool {
 
-        or(
-            |i| parse!{i;
-                skip_many(space_or_tab);
-                token(b',');
-                ret {()}
-            },
-            space_or_tab
-        );
+    match *filter_tree {
+        FilterTree::Leaf(ref filter) => {
+            return satisfy_leaf(filter);
+        },
+        FilterTree::Union(ref left_tree, ref right_tree) => {
 
-        skip_many(|i| or(i,
-            |i| parse!{i;
-                token(b',');
-                ret {()}
-            },
-            space_or_tab
-        ));
+            if satisfy_tree(left_tree, satisfy_leaf) {
+                return true;
+            }
 
-        ret {()}
-    }
-}
+            if satisfy_tree(right_tree, satisfy_leaf) {
+                return true;
+            }
 
-fn parse_list_status_reducer(input: Input<u8>, accumulator: Rc<RefCell<EnumSet<Status>>>) -> U8Result<()> {
-    parse!{input;
-        let status = parse_status();
-        ret {
-            accumulator.borrow_mut().insert(status);
-            ()
-        }
-    }
-}
+            return false;
+        },
+        FilterTree::Intersection(ref left_tree, ref right_tree) => {
 
-#[test]
-fn parse_list_status_test() {
+            if !satisfy_tree(left_tree, satisfy_leaf) {
+                return false;
+            }
 
-    match parse_only(|i| parse_list_status(i), "".as_bytes()) {
-        Ok(_) => {
-            assert!(false);
+            if !satisfy_tree(right_tree, satisfy_leaf) {
+                return false;
+            }
+
+            return true;
         },
-        Err(_) => {
-            assert!(true);
+        FilterTree::Negation(ref tree) => {
+            return !satisfy_tree(tree, satisfy_leaf);
         }
     }
 
-    match parse_only(|i| parse_list_status(i), ",,,,".as_bytes()) {
-        Ok(_) => {
-            assert!(false);
+}
+
+// a leaf may be recognized by the parser, yet have an invalid value (e.g. February 31).
+// the first of such errors is reported.
+fn resolve_filter_tree<T>(filter_tree: FilterTree<Result<T, String>>) -> Result<FilterTree<T>, String> {
+
+    match filter_tree {
+        FilterTree::Leaf(filter) => {
+            filter.map(FilterTree::Leaf)
         },
-        Err(_) => {
-            assert!(true);
+        FilterTree::Union(left_tree, right_tree) => {
+            resolve_filter_tree(*left_tree).and_then(|left_tree| {
+                resolve_filter_tree(*right_tree).map(|right_tree| {
+                    FilterTree::Union(Box::new(left_tree), Box::new(right_tree))
+                })
+            })
+        },
+        FilterTree::Intersection(left_tree, right_tree) => {
+            resolve_filter_tree(*left_tree).and_then(|left_tree| {
+                resolve_filter_tree(*right_tree).map(|right_tree| {
+                    FilterTree::Intersection(Box::new(left_tree), Box::new(right_tree))
+                })
+            })
+        },
+        FilterTree::Negation(tree) => {
+            resolve_filter_tree(*tree).map(|tree| FilterTree::Negation(Box::new(tree)))
         }
     }
+}
+
+/*
+Source: http://www.engr.mun.ca/~theo/Misc/exp_parsing.htm#classic
+Source: https://en.wikipedia.org/wiki/Operator-precedence_parser#Precedence_climbing_method
+
+Original:
+E --> T {or T}
+T --> P {and P}
+P --> "(" E ")" | not P | leaf
+
+Expanded:
+E = T K | T
+K = or T K | or T
+T = P L | P
+L = and P L | and P
+P = "(" E ")" | not P | leaf
+
+Renamed variables:
+tree = maybe_predicate_intersect union | maybe_predicate_intersect
+union = or maybe_predicate_intersect union | or maybe_predicate_intersect
+maybe_predicate_intersect = predicate intersect | predicate
+intersect = and predicate intersect | and predicate
+predicate = "(" tree ")" | not predicate | leaf
+ */
+
+fn parse_filter_tree<'a, T, L>(input: Input<'a, u8>, leaf: L) -> U8Result<'a, FilterTree<T>>
+    where
+    L: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
+    T: Clone {
+
+    or(input,
+        |input| parse!{input;
+
+            let left_node = parse_filter_tree_maybe_predicate_intersect(leaf);
 
-    match parse_only(|i| parse_list_status(i), ",,  ,, active".as_bytes()) {
-        Ok(_) => {
-            assert!(false);
+            skip_many(space_or_tab);
+
+            let right_node = parse_filter_tree_union(leaf, left_node);
+            ret right_node
         },
-        Err(_) => {
-            assert!(true);
+        |input| parse!{input;
+
+            let right_node = parse_filter_tree_maybe_predicate_intersect(leaf);
+            ret right_node
         }
-    }
+    )
+}
 
-    match parse_only(|i| parse_list_status(i), "active ,,  ,,,".as_bytes()) {
-        Ok(result) => {
+fn parse_filter_tree_union<'a, T, L>(input: Input<'a, u8>, leaf: L, left_node: FilterTree<T>)
+-> U8Result<'a, FilterTree<T>>
+    where
+    L: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
+    T: Clone {
 
-            let mut set: EnumSet<Status> = EnumSet::new();
-            set.insert(Status::NotDone);
+    or(input,
+        |input| parse!{input;
 
-            assert_eq!(result, set);
-        },
-        Err(_) => {
-            assert!(true);
-        }
-    }
+            string("||".as_bytes()) <|>
+            string("|".as_bytes()) <|>
+            string_ignore_case("or".as_bytes());
 
-    match parse_only(|i| parse_list_status(i), "active".as_bytes()) {
-        Ok(result) => {
+            skip_many(space_or_tab);
 
-            let mut set: EnumSet<Status> = EnumSet::new();
-            set.insert(Status::NotDone);
+            let right_node = parse_filter_tree_maybe_predicate_intersect(leaf);
 
-            assert_eq!(result, set);
-        },
-        Err(_) => {
-            assert!(false);
-        }
-    }
+            skip_many(space_or_tab);
 
-    match parse_only(|i| parse_list_status(i), "done,,,,, not done".as_bytes()) {
-        Ok(result) => {
+            let tree = parse_filter_tree_union(leaf, {
+                let left_node = Box::new(left_node.clone());
+                let right_node = Box::new(right_node);
 
-            let mut set: EnumSet<Status> = EnumSet::new();
-            set.insert(Status::NotDone);
-            set.insert(Status::Done);
+                FilterTree::Union(left_node, right_node)
+            });
 
-            assert_eq!(result, set);
-            // println!("{:?}", result);
-            // journal.filter_priority = Some(result);
+            ret tree
         },
-        Err(_) => {
-            assert!(false);
-        }
-    }
+        |input| parse!{input;
 
-    match parse_only(|i| parse_list_status(i), "done    not done".as_bytes()) {
-        Ok(result) => {
+            string("||".as_bytes()) <|>
+            string("|".as_bytes()) <|>
+            string_ignore_case("or".as_bytes());
 
-            let mut set: EnumSet<Status> = EnumSet::new();
-            set.insert(Status::NotDone);
-            set.insert(Status::Done);
+            skip_many(space_or_tab);
 
-            assert_eq!(result, set);
-            // println!("{:?}", result);
-            // journal.filter_priority = Some(result);
+            let right_node = parse_filter_tree_maybe_predicate_intersect(leaf);
+
+            ret {
+                let left_node = Box::new(left_node.clone());
+                let right_node = Box::new(right_node);
+
+                FilterTree::Union(left_node, right_node)
+            }
         },
-        Err(_) => {
-            assert!(false);
-        }
-    }
+    )
 }
 
-/* new delimeted list parser */
+fn parse_filter_tree_maybe_predicate_intersect<'a, T, L>(input: Input<'a, u8>, leaf: L)
+-> U8Result<'a, FilterTree<T>>
+    where
+    L: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
+    T: Clone {
 
-// TODO: remove
-// list(delim) = status rest(delim) | status
-// rest(delim) = delim status rest(delim) | delim status
+    or(input,
+        |input| parse!{input;
 
-// invariant:
-// - item does not consume eof (may lookahead eof to stop)
-// - item does not consume delim (may lookahead delim to stop)
-// - delim does not consume eof
-//
-// list(delim) = item rest(delim) | item
-// rest(delim) = delim item rest(delim) | delim item
+            let left_node = parse_filter_tree_predicate(leaf);
 
-fn parse_list<'a, D, Delim: 'a, A, R>(input: Input<'a, u8>, delimeter: D, reducer: R)
--> U8Result<'a, A>
-    where
-    D: FnOnce(Input<'a, u8>) -> U8Result<'a, Delim> + Copy,
-    R: FnOnce(Input<'a, u8>, Rc<RefCell<A>>) -> U8Result<'a, ()> + Copy,
-    A: Default {
+            skip_many(space_or_tab);
 
-    let accumulator: A = Default::default();
-    let initial_accumulator: Rc<RefCell<A>> = Rc::new(RefCell::new(accumulator));
+            let right_node = parse_filter_tree_intersect(leaf, left_node);
+            ret right_node;
+
+        },
+        |input| parse!{input;
+
+            let right_node = parse_filter_tree_predicate(leaf);
+            ret right_node;
+        }
+    )
+
+}
+
+fn parse_filter_tree_intersect<'a, T, L>(input: Input<'a, u8>, leaf: L, left_node: FilterTree<T>)
+-> U8Result<'a, FilterTree<T>>
+    where
+    L: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
+    T: Clone {
 
     or(input,
         |input| parse!{input;
 
-            reducer(initial_accumulator.clone());
+            string("&&".as_bytes()) <|>
+            string("&".as_bytes()) <|>
+            string_ignore_case("and".as_bytes());
 
-            parse_list_rest(delimeter, initial_accumulator.clone(), reducer);
+            skip_many(space_or_tab);
 
-            ret {
-                Rc::try_unwrap(initial_accumulator)
-                    .ok()
-                    .unwrap()
-                    .into_inner()
-            }
+            let right_node = parse_filter_tree_predicate(leaf);
+
+            skip_many(space_or_tab);
+
+            let tree = parse_filter_tree_intersect(leaf, {
+                let left_node = Box::new(left_node.clone());
+                let right_node = Box::new(right_node);
+
+                FilterTree::Intersection(left_node, right_node)
+            });
 
+            ret tree
         },
-        |input| {
-            let accumulator: A = Default::default();
-            let initial_accumulator: Rc<RefCell<A>> = Rc::new(RefCell::new(accumulator));
-            parse!{input;
-                reducer(initial_accumulator.clone());
+        |input| parse!{input;
 
-                ret {
-                    Rc::try_unwrap(initial_accumulator)
-                        .ok()
-                        .unwrap()
-                        .into_inner()
-                }
-            }
-        }
+            string("&&".as_bytes()) <|>
+            string("&".as_bytes()) <|>
+            string_ignore_case("and".as_bytes());
+
+            skip_many(space_or_tab);
+
+            let right_node = parse_filter_tree_predicate(leaf);
+
+            ret {
+                let left_node = Box::new(left_node.clone());
+                let right_node = Box::new(right_node);
 
+                FilterTree::Intersection(left_node, right_node)
+            }
+        },
     )
 }
 
-fn parse_list_rest<'a, D, Delim: 'a, A, R>(
-    input: Input<'a, u8>,
-    delimeter: D,
-    accumulator: Rc<RefCell<A>>,
-    reducer: R)
--> U8Result<'a, ()>
+fn parse_filter_tree_predicate<'a, T, L>(input: Input<'a, u8>, leaf: L) -> U8Result<'a, FilterTree<T>>
     where
-    D: FnOnce(Input<'a, u8>) -> U8Result<'a, Delim> + Copy,
-    R: FnOnce(Input<'a, u8>, Rc<RefCell<A>>) -> U8Result<'a, ()> + Copy,
-    A: Default {
+    L: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
+    T: Clone {
 
+    or(input,
+        |input| parse!{input;
 
-    parse!{input;
+            token(b'(');
+            skip_many(space_or_tab);
 
-        delimeter();
+            let tree = parse_filter_tree(leaf);
 
-        or(
-            |i| parse!{i;
-                reducer(accumulator.clone());
-                parse_list_rest(delimeter, accumulator.clone(), reducer);
-                ret {
-                    ()
-                }
+            skip_many(space_or_tab);
+            token(b')');
+
+            ret tree
+
+        },
+        |input| or(input,
+            |input| parse!{input;
+
+                parse_filter_tree_negation();
+
+                let tree = parse_filter_tree_predicate(leaf);
+
+                ret FilterTree::Negation(Box::new(tree))
             },
-            |i| parse!{i;
-                reducer(accumulator.clone());
-                ret {
-                    ()
-                }
-            }
-        );
+            |input| parse!{input;
 
-        ret {
-            ()
-        }
-    }
-}
+                let filter = leaf();
 
-// TODO: remove
+                ret FilterTree::Leaf(filter)
 
-// fn ___parse_list<'a, F, G, T: 'a, U>(input: Input<'a, u8>, delimeter: F, parse_item: G) -> U8Result<'a, Vec<U>>
-//     where
-//     F: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
-//     G: FnOnce(Input<'a, u8>) -> U8Result<'a, U> + Copy {
+            }
+        )
+    )
+}
 
-//     let start_list: Rc<RefCell<Vec<U>>> = Rc::new(RefCell::new(Vec::new()));
+// not, !
+fn parse_filter_tree_negation(input: Input<u8>) -> U8Result<()> {
+    or(input,
+        |i| parse!{i;
 
-//     or(input,
-//         |input| parse!{input;
+            string_ignore_case("not".as_bytes());
 
-//             let item: U = parse_item();
+            // `not` must be followed by whitespace or a parenthesized expression
+            or(
+                |i| skip_many1(i, space_or_tab),
+                |i| parse!{i;
+                    look_ahead(|i| token(i, b'('));
+                    ret ()
+                }
+            );
 
-//             __parse_list_rest({
-//                 start_list.borrow_mut().push(item);
+            ret ()
+        },
+        |i| parse!{i;
 
-//                 start_list.clone()
-//             }, delimeter, parse_item);
+            token(b'!');
+            skip_many(space_or_tab);
 
-//             ret {
-//                 Rc::try_unwrap(start_list)
-//                     .ok()
-//                     .unwrap()
-//                     .into_inner()
-//             }
-//         },
-//         |input| parse!{input;
+            ret ()
+        }
+    )
+}
 
-//             let item: U = parse_item();
-//             ret {
-//                 let mut list: Vec<U> = Vec::new();
-//                 list.push(item);
-//                 list
-//             }
-//         }
-//     )
-// }
+/* priority filters */
 
-// fn __parse_list_rest<'a, F, G, T: 'a, U: 'a>(
-//     input: Input<'a, u8>,
-//     current_list: Rc<RefCell<Vec<U>>>,
-//     delimeter: F,
-//     parse_item: G) -> U8Result<'a, ()>
-//     where
-//     F: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy,
-//     G: FnOnce(Input<'a, u8>) -> U8Result<'a, U> + Copy {
+#[derive(Debug, Clone)]
+struct PriorityFilter(Inequality, Priority);
 
-//     let current_list_1 = current_list.clone();
-//     let current_list_2 = current_list.clone();
+type PriorityFilterTree = FilterTree<PriorityFilter>;
 
-//     parse!{input;
+fn priority_pretty_tree_art(filter_tree: &PriorityFilterTree) -> String {
 
-//         delimeter();
+    pretty_tree_art(filter_tree, &|filter: &PriorityFilter| {
 
-//         or(
-//             |i| parse!{i;
+        let &PriorityFilter(ref inequality, priority) = filter;
 
-//                 let item: U = parse_item();
+        format!("{} {}", inequality_art(inequality), priority)
+    })
 
-//                 __parse_list_rest({
-//                     current_list_1.borrow_mut().push(item);
-//                     current_list_1
-//                 }, delimeter, parse_item);
+}
 
-//                 ret {
-//                     ()
-//                 }
-//             },
-//             |i| parse!{i;
+fn priority_satisfy_tree(filter_tree: &PriorityFilterTree, task_priority: Priority) -> bool {
 
-//                 let item: U = parse_item();
+    satisfy_tree(filter_tree, &|filter: &PriorityFilter| {
 
-//                 ret {
-//                     current_list_2.borrow_mut().push(item);
-//                     ()
-//                 }
-//             }
-//         );
+        let &PriorityFilter(ref inequality, priority) = filter;
 
-//         ret {
-//             ()
-//         }
-//     }
-// }
+        satisfy_inequality(inequality, task_priority, priority)
+    })
 
-/* delimited list parser */
+}
+
+fn parse_show_priority(input: Input<u8>) -> U8Result<PriorityFilterTree> {
 
-fn parse_string_lists(input: Input<u8>, delim: u8) -> U8Result<Vec<String>> {
     parse!{input;
+
         skip_many(space_or_tab);
-        let result = string_list(delim);
+
+        let result = parse_filter_tree(parse_priority_filter);
+
         skip_many(space_or_tab);
         eof();
+
         ret result
     }
-}
-
-fn string_list(input: Input<u8>, delim: u8) -> U8Result<Vec<String>> {
-    parse!{input;
-
-        let line = non_empty_line();
-
-        ret {
-            let line: Vec<u8> = line;
 
-            // TODO: eventually refactor using consume_up_to
-            let list: Vec<String> = String::from_utf8_lossy(line.as_slice())
-                .trim()
-                .split(delim as char).map(|c| c.trim().to_string())
-                .filter(|x| x.len() > 0)
-                .collect();
+}
 
+fn parse_priority_filter(input: Input<u8>) -> U8Result<PriorityFilter> {
 
-            list
-        }
-    }
-}
+    or(input,
+        |input| parse!{input;
 
-/* misc parsers */
+            let operator = parse_inequality();
 
-fn bool_option_parser(i: Input<u8>) -> U8Result<bool> {
-    or(i,
-        |i| parse!{i;
+            skip_many(space_or_tab);
 
-            string_ignore_case("yes".as_bytes()) <|>
-            string_ignore_case("y".as_bytes()) <|>
-            string_ignore_case("true".as_bytes());
+            let priority = parse_priority_number();
 
-            ret true
+            ret PriorityFilter(operator, priority)
         },
-        |i| parse!{i;
+        |input| parse!{input;
 
-            string_ignore_case("no".as_bytes()) <|>
-            string_ignore_case("n".as_bytes()) <|>
-            string_ignore_case("false".as_bytes());
+            let priority = parse_priority_number();
 
-            ret false
+            ret PriorityFilter(Inequality::Equal, priority)
         }
     )
 }
 
-fn match_four_tokens<'a>(input: Input<'a, u8>, token: &[u8])
--> SimpleResult<'a, u8, ()> {
-
+fn parse_inequality(input: Input<u8>) -> U8Result<Inequality> {
     parse!{input;
-        string(token);
-        string(token);
-        string(token);
-        string(token);
 
-        ret ()
-    }
-}
+        let result = __parse_inequality(">=", Inequality::GreaterThanOrEqual) <|>
+            __parse_inequality(">", Inequality::GreaterThan) <|>
+            __parse_inequality("<=", Inequality::LessThanOrEqual) <|>
+            __parse_inequality("<", Inequality::LessThan) <|>
+            __parse_inequality("==", Inequality::Equal) <|>
+            __parse_inequality("=", Inequality::Equal);
 
-fn whitespace(i: Input<u8>) -> U8Result<()> {
-    parse!{i;
-        satisfy(is_whitespace);
-        ret ()
+        ret result
     }
 }
 
-fn space_or_tab(input: Input<u8>) -> U8Result<()> {
+fn __parse_inequality<'a>(input: Input<'a, u8>, needle: &str, output: Inequality) -> SimpleResult<'a, u8, Inequality> {
     parse!{input;
-        or(
-            |i| token(i, b' '),
-            |i| token(i, b'\t')
-        );
-        ret ()
+        string_ignore_case(needle.as_bytes());
+        ret output
     }
 }
 
-fn non_terminating(i: Input<u8>) -> U8Result<u8> {
-
-    or(i,
-        |i| parse!{i;
-            terminating();
-            ret None
-        },
-        |i| parse!{i;
+/* where filters */
 
-            let something = any();
+// e.g. tag = chore and (due < tomorrow or priority >= 5)
 
-            ret Some(something)
-        }
-    )
-    .bind(|i, above: Option<u8>| {
+#[derive(Debug, Clone, PartialEq)]
+enum TaskField {
+    Title,
+    Note,
+    Project,
+    Tag,
+    Context,
+    Status,
+    Priority,
+    Due,
+    Defer,
+    Created,
+    Done,
+    Time,
+    Flagged,
+    File
+}
 
-        match above {
-            None => {
-                return i.incomplete(1);
-            },
-            Some(c) => {
-                return i.ret(c);
-            }
+impl TaskField {
+    fn string(&self) -> &'static str {
+        match *self {
+            TaskField::Title => "title",
+            TaskField::Note => "note",
+            TaskField::Project => "project",
+            TaskField::Tag => "tag",
+            TaskField::Context => "context",
+            TaskField::Status => "status",
+            TaskField::Priority => "priority",
+            TaskField::Due => "due",
+            TaskField::Defer => "defer",
+            TaskField::Created => "created",
+            TaskField::Done => "done",
+            TaskField::Time => "time",
+            TaskField::Flagged => "flagged",
+            TaskField::File => "file"
         }
-
-    })
+    }
 }
 
-// match eof or various eol
-fn terminating(i: Input<u8>) -> U8Result<()> {
-    or(i,
-        |i| parse!{i;
-            end_of_line();
-            ret ()
-        },
-        // NOTE: eof should be matched last
-        eof
-    )
+#[derive(Debug, Clone)]
+enum WhereOperator {
+    Compare(Inequality),
+    NotEqual,
+    // ^=
+    StartsWith,
+    // ~
+    Contains,
+    In
 }
 
-// Source: https://en.wikipedia.org/wiki/Newline#Unicode
-fn end_of_line(i: Input<u8>) -> U8Result<&[u8]> {
-    // TODO: bother to refactor using parse! macro with <|> operator?
-    or(i,
-        |i| parse!{i;
-            token(b'\r');
-            token(b'\n');
-            ret "\r\n".as_bytes()
-        },
-        |i| or(i,
-            |i| parse!{i;
-                token(b'\n');
-                ret "\n".as_bytes()
-            },
-            |i| or(i,
-                |i| parse!{i;
-                    token(b'\r');
-                    ret "\r".as_bytes()
-                },
-                |i| or(i,
-                    |i| parse!{i;
-                        string("\u{2028}".as_bytes());
-                        ret "\u{2028}".as_bytes()
-                    },
-                    |i| or(i,
-                        |i| parse!{i;
-                            string("\u{2029}".as_bytes());
-                            ret "\u{2029}".as_bytes()
-                        },
-                        |i| or(i,
-                            |i| parse!{i;
-                                string("\u{000B}".as_bytes());
-                                ret "\u{000B}".as_bytes()
-                            },
-                            |i| or(i,
-                                |i| parse!{i;
-                                    string("\u{000C}".as_bytes());
-                                    ret "\u{000C}".as_bytes()
-                                },
-                                |i| parse!{i;
-                                    string("\u{0085}".as_bytes());
-                                    ret "\u{0085}".as_bytes()
-                                }
-                            )
-                        )
-                    )
-                )
-            )
-        )
-    )
+#[derive(Debug, Clone)]
+enum WhereValue {
+    Text(String),
+    Path(ProjectPath),
+    Status(Status),
+    Number(i64),
+    DateTime(NaiveDateTime),
+    // datetime given without a time of day; compared by calendar day
+    Date(NaiveDate),
+    Duration(TimeLength),
+    Bool(bool)
 }
 
-fn string_ignore_case<'a>(i: Input<'a, u8>, s: &[u8])
-    -> SimpleResult<'a, u8, &'a [u8]> {
-    let b = i.buffer();
+#[derive(Debug, Clone)]
+struct WhereFilter(TaskField, WhereOperator, Vec<WhereValue>);
 
-    if s.len() > b.len() {
-        return i.incomplete(s.len() - b.len());
-    }
+type WhereFilterTree = FilterTree<WhereFilter>;
 
-    let d = &b[..s.len()];
+fn where_pretty_tree_art(filter_tree: &WhereFilterTree) -> String {
+    pretty_tree_art(filter_tree, &where_pretty_filter_art)
+}
 
-    for j in 0..s.len() {
+fn where_pretty_filter_art(filter: &WhereFilter) -> String {
 
-        if !(s[j]).eq_ignore_ascii_case(&(d[j])) {
-            return i.replace(&b[j..]).err(Error::expected(d[j]))
-        }
-    }
+    let &WhereFilter(ref field, ref operator, ref values) = filter;
 
-    i.replace(&b[s.len()..]).ret(d)
-}
+    let values: Vec<String> = values.iter().map(|value| {
+        match *value {
+            WhereValue::Text(ref text) => {
 
-fn signed_decimal(input: Input<u8>) -> U8Result<i64> {
+                let is_bare = text.len() > 0 && text.bytes().all(is_where_bare_value);
 
-    parse!{input;
-        let sign: i64 = or(
-            |i| parse!{i;
-                token(b'-');
-                ret -1
+                if is_bare {
+                    text.clone()
+                } else {
+                    format!("\"{}\"", text)
+                }
             },
-            |i| parse!{i;
-                token(b'+');
-                ret 1
+            WhereValue::Path(ref path) => path.join("/"),
+            WhereValue::Status(ref status) => status.string().to_lowercase(),
+            WhereValue::Number(number) => format!("{}", number),
+            WhereValue::DateTime(ref date_time) => {
+                format!("{}", date_time.format("%B %-d, %Y %-l:%M %p"))
+            },
+            WhereValue::Date(ref date) => format!("{}", date.format("%B %-d, %Y")),
+            WhereValue::Duration(time) => Timerange::new(time).print(10),
+            WhereValue::Bool(flag) => {
+                if flag {
+                    "yes".to_owned()
+                } else {
+                    "no".to_owned()
+                }
             }
-        );
-
-        let num: i64 = decimal();
-
-        ret {
-            sign * num
         }
-    }
-}
-
-/* inequality parsers */
+    }).collect();
 
-#[derive(Debug, Clone)]
-enum Inequality {
-    GreaterThan,
-    GreaterThanOrEqual,
-    LessThan,
-    LessThanOrEqual,
-    Equal
-}
+    match *operator {
+        WhereOperator::Compare(ref inequality) => {
 
-#[derive(Debug, Clone)]
-struct PriorityFilter(Inequality, Priority);
+            let operator = match *inequality {
+                Inequality::Equal => "=",
+                _ => inequality_art(inequality)
+            };
 
-#[derive(Debug, Clone)]
-enum PriorityFilterTree {
-    Union(Box<PriorityFilterTree>, Box<PriorityFilterTree>),
-    Intersection(Box<PriorityFilterTree>, Box<PriorityFilterTree>),
-    Leaf(PriorityFilter)
+            format!("{} {} {}", field.string(), operator, values.join(", "))
+        },
+        WhereOperator::NotEqual => format!("{} != {}", field.string(), values.join(", ")),
+        WhereOperator::StartsWith => format!("{} ^= {}", field.string(), values.join(", ")),
+        WhereOperator::Contains => format!("{} ~ {}", field.string(), values.join(", ")),
+        WhereOperator::In => format!("{} in ({})", field.string(), values.join(", "))
+    }
 }
 
-impl PriorityFilterTree {
-    fn is_leaf(&self) -> bool {
-        match *self {
-            PriorityFilterTree::Leaf(_) => true,
-            _ => false,
-        }
-    }
+fn where_satisfy_tree(filter_tree: &WhereFilterTree, task: &Task, base_root: &str) -> bool {
+    satisfy_tree(filter_tree, &|filter: &WhereFilter| {
+        where_satisfy_filter(filter, task, base_root)
+    })
 }
 
-fn priority_pretty_tree_art(filter_tree: &PriorityFilterTree) -> String {
+// Comparisons against an attribute the task does not have are never satisfied; except for `!=`,
+// which is the negation of `=`.
+fn where_satisfy_filter(filter: &WhereFilter, task: &Task, base_root: &str) -> bool {
 
-    match *filter_tree {
-        PriorityFilterTree::Leaf(ref filter) => {
+    let &WhereFilter(ref field, ref operator, ref values) = filter;
 
-            let &PriorityFilter(ref inequality, priority) = filter;
+    if let WhereOperator::NotEqual = *operator {
 
-            let placeholder = match *inequality {
-                Inequality::GreaterThan => format!("> {}", priority),
-                Inequality::GreaterThanOrEqual => format!(">= {}", priority),
-                Inequality::LessThan => format!("< {}", priority),
-                Inequality::LessThanOrEqual => format!("<= {}", priority),
-                Inequality::Equal => format!("== {}", priority)
-            };
+        let equal_filter = WhereFilter(
+            field.clone(),
+            WhereOperator::Compare(Inequality::Equal),
+            values.clone());
 
-            return placeholder;
+        return !where_satisfy_filter(&equal_filter, task, base_root);
+    }
 
+    match *field {
+        TaskField::Title => {
+            match task.title {
+                None => false,
+                Some(ref title) => where_satisfy_text(operator, &[title.clone()], values)
+            }
         },
-        PriorityFilterTree::Union(ref left_tree, ref right_tree) => {
-
-            let left_tree = if left_tree.is_leaf() {
-                priority_pretty_tree_art(left_tree)
-            } else {
-                format!("({})", priority_pretty_tree_art(left_tree))
-            };
+        TaskField::Note => {
+            match task.note {
+                None => false,
+                Some(ref note) => where_satisfy_text(operator, &[note.clone()], values)
+            }
+        },
+        TaskField::Tag => {
+            match task.tags {
+                None => false,
+                Some(ref tags) => where_satisfy_text(operator, tags, values)
+            }
+        },
+        TaskField::Context => {
+            match task.contexts {
+                None => false,
+                Some(ref contexts) => where_satisfy_text(operator, contexts, values)
+            }
+        },
+        TaskField::File => {
+            match task.source_file {
+                None => false,
+                Some(ref source_file) => {
 
-            let right_tree = if right_tree.is_leaf() {
-                priority_pretty_tree_art(right_tree)
-            } else {
-                format!("({})", priority_pretty_tree_art(right_tree))
-            };
+                    // match against both the path relative to the working directory, and the absolute path
+                    let relative_path = match Path::new(source_file).strip_prefix(base_root) {
+                        Err(_) => source_file.clone(),
+                        Ok(path) => format!("{}", path.display())
+                    };
 
-            return format!("{} or {}", left_tree, right_tree);
+                    where_satisfy_text(operator, &[relative_path, source_file.clone()], values)
+                }
+            }
+        },
+        TaskField::Project => {
+            match task.project {
+                None => false,
+                Some(ref project_path) => where_satisfy_project(operator, project_path, values)
+            }
         },
-        PriorityFilterTree::Intersection(ref left_tree, ref right_tree) => {
+        TaskField::Status => {
 
-            let left_tree = if left_tree.is_leaf() {
-                priority_pretty_tree_art(left_tree)
-            } else {
-                format!("({})", priority_pretty_tree_art(left_tree))
+            let task_status = match task.status {
+                None => Status::NotDone,
+                Some(ref status) => status.clone()
             };
 
-            let right_tree = if right_tree.is_leaf() {
-                priority_pretty_tree_art(right_tree)
-            } else {
-                format!("({})", priority_pretty_tree_art(right_tree))
-            };
+            values.iter().any(|value| {
+                match *value {
+                    WhereValue::Status(ref status) => *status == task_status,
+                    _ => false
+                }
+            })
+        },
+        TaskField::Priority => {
+            values.iter().any(|value| {
+                match (operator, value) {
+                    (&WhereOperator::Compare(ref inequality), &WhereValue::Number(priority)) => {
+                        satisfy_inequality(inequality, task.priority, priority)
+                    },
+                    _ => false
+                }
+            })
+        },
+        TaskField::Time => {
+            values.iter().any(|value| {
+                match (operator, value) {
+                    (&WhereOperator::Compare(ref inequality), &WhereValue::Duration(time)) => {
+                        satisfy_inequality(inequality, task.time, time)
+                    },
+                    _ => false
+                }
+            })
+        },
+        TaskField::Due => where_satisfy_datetime(operator, task.due_at, values),
+        TaskField::Created => where_satisfy_datetime(operator, task.created_at, values),
+        TaskField::Done => where_satisfy_datetime(operator, task.done_at, values),
+        TaskField::Defer => {
+            match task.defer {
+                None => false,
+                Some(Defer::Until(defer_till)) => {
+                    where_satisfy_datetime(operator, Some(defer_till), values)
+                },
+                Some(Defer::Forever) => {
 
-            return format!("{} and {}", left_tree, right_tree);
+                    // a task deferred forever is deferred beyond any datetime
+                    match *operator {
+                        WhereOperator::Compare(Inequality::GreaterThan) |
+                        WhereOperator::Compare(Inequality::GreaterThanOrEqual) => true,
+                        _ => false
+                    }
+                }
+            }
+        },
+        TaskField::Flagged => {
+            values.iter().any(|value| {
+                match *value {
+                    WhereValue::Bool(flag) => task.flag == flag,
+                    _ => false
+                }
+            })
         }
     }
-
 }
 
-fn priority_satisfy_tree(filter_tree: &PriorityFilterTree, task_priority: Priority) -> bool {
-
-    match *filter_tree {
-        PriorityFilterTree::Leaf(ref filter) => {
-
-            let &PriorityFilter(ref inequality, priority) = filter;
-
-            let result = match *inequality {
-                Inequality::GreaterThan => task_priority > priority,
-                Inequality::GreaterThanOrEqual => task_priority >= priority,
-                Inequality::LessThan => task_priority < priority,
-                Inequality::LessThanOrEqual => task_priority <= priority,
-                Inequality::Equal => task_priority == priority
-            };
+// text comparisons are case-insensitive
+fn where_satisfy_text(operator: &WhereOperator, haystack: &[String], values: &[WhereValue]) -> bool {
 
-            return result;
+    let haystack: Vec<String> = haystack.iter().map(|text| text.to_lowercase()).collect();
 
-        },
-        PriorityFilterTree::Union(ref left_tree, ref right_tree) => {
+    values.iter().any(|value| {
 
-            if priority_satisfy_tree(left_tree, task_priority) {
-                return true;
+        let needle = match *value {
+            WhereValue::Text(ref text) => text.to_lowercase(),
+            _ => {
+                return false;
             }
+        };
 
-            if priority_satisfy_tree(right_tree, task_priority) {
-                return true;
+        haystack.iter().any(|text| {
+            match *operator {
+                WhereOperator::Compare(Inequality::Equal) | WhereOperator::In => *text == needle,
+                WhereOperator::StartsWith => text.starts_with(&needle),
+                WhereOperator::Contains => text.contains(&needle),
+                _ => false
             }
+        })
+    })
+}
 
-            return false;
-        },
-        PriorityFilterTree::Intersection(ref left_tree, ref right_tree) => {
+fn where_satisfy_project(operator: &WhereOperator, project_path: &[String], values: &[WhereValue]) -> bool {
 
-            if !priority_satisfy_tree(left_tree, task_priority) {
-                return false;
-            }
+    let project_path: Vec<String> = project_path.iter().map(|segment| segment.to_lowercase()).collect();
 
-            if !priority_satisfy_tree(right_tree, task_priority) {
+    values.iter().any(|value| {
+
+        let needle: Vec<String> = match *value {
+            WhereValue::Path(ref path) => path.iter().map(|segment| segment.to_lowercase()).collect(),
+            _ => {
                 return false;
             }
+        };
 
-            return true;
+        match *operator {
+            WhereOperator::Compare(Inequality::Equal) | WhereOperator::In => project_path == needle,
+            // project path begins with the given path segments
+            WhereOperator::StartsWith => project_path.starts_with(&needle),
+            WhereOperator::Contains => project_path.join("/").contains(&needle.join("/")),
+            _ => false
         }
-    }
+    })
+}
 
+// options of date range filters; lower bounds are inclusive, and upper bounds are exclusive.
+// tasks missing the attribute never satisfy them.
+fn date_range_filters() -> Vec<(&'static str, TaskField, Inequality)> {
+    vec![
+        ("created-after", TaskField::Created, Inequality::GreaterThanOrEqual),
+        ("created-before", TaskField::Created, Inequality::LessThan),
+        ("done-after", TaskField::Done, Inequality::GreaterThanOrEqual),
+        ("done-before", TaskField::Done, Inequality::LessThan),
+        ("due-after", TaskField::Due, Inequality::GreaterThanOrEqual),
+        ("due-before", TaskField::Due, Inequality::LessThan),
+        ("deferred-until-before", TaskField::Defer, Inequality::LessThan)
+    ]
 }
 
-fn parse_show_priority(input: Input<u8>) -> U8Result<PriorityFilterTree> {
+#[test]
+fn date_range_filters_test() {
+
+    let text = "task: early\n\
+        created: May 1, 2016 00:00\n\
+        due: May 4, 2016 17:00\n\
+        defer: May 5, 2016\n\
+        \n\
+        task: boundary\n\
+        created: May 3, 2016 12:00\n\
+        due: May 6, 2016 00:00\n\
+        defer: forever\n\
+        \n\
+        task: bare\n\
+        \n\
+        task: finished\n\
+        status: done\n\
+        done: May 2, 2016 10:00\n";
+
+    let now = NaiveDate::from_ymd(2016, 5, 4).and_hms(9, 0, 0);
+    let journal = parse_test_journal("date-range", text, now);
+
+    let mut task_ids: Vec<u64> = journal.tasks.keys().cloned().collect();
+    task_ids.sort();
+
+    // titles of tasks satisfying the date range filter of the option
+    let satisfying = |option_name: &str, date_time: &str| -> Vec<String> {
+
+        let (_, field, inequality) = date_range_filters().into_iter()
+            .find(|&(name, _, _)| name == option_name)
+            .unwrap();
+
+        let date_time = match parse_only(|i| parse_relative_datetime_option(i, false, now), date_time.as_bytes()) {
+            Ok(Ok(date_time)) => date_time,
+            _ => panic!("unable to parse: {}", date_time)
+        };
+
+        let filter = FilterTree::Leaf(WhereFilter(field,
+            WhereOperator::Compare(inequality),
+            vec![WhereValue::DateTime(date_time)]));
+
+        let found: Vec<u64> = task_ids.iter().cloned().filter(|task_id| {
+            where_satisfy_tree(&filter, journal.tasks.get(task_id).unwrap(), &journal.base_root)
+        }).collect();
+
+        test_task_titles(&journal, &found)
+    };
+
+    let none: Vec<String> = vec![];
+
+    // lower bounds are inclusive; upper bounds are exclusive
+    assert_eq!(satisfying("created-after", "May 3, 2016 12:00"), vec!["boundary".to_owned()]);
+    assert_eq!(satisfying("created-before", "May 3, 2016 12:00"), vec!["early".to_owned()]);
+    assert_eq!(satisfying("due-after", "May 6, 2016"), vec!["boundary".to_owned()]);
+    assert_eq!(satisfying("due-before", "May 6, 2016"), vec!["early".to_owned()]);
+    assert_eq!(satisfying("done-after", "May 2, 2016 10:00"), vec!["finished".to_owned()]);
+    assert_eq!(satisfying("done-before", "May 2, 2016 10:00"), none);
+    assert_eq!(satisfying("done-before", "yesterday"), vec!["finished".to_owned()]);
+
+    // tasks deferred forever are never deferred until before a datetime
+    assert_eq!(satisfying("deferred-until-before", "May 5, 2016 00:00"), none);
+    assert_eq!(satisfying("deferred-until-before", "May 5, 2016 00:01"), vec!["early".to_owned()]);
+
+    // tasks missing the attribute are hidden by either bound
+    assert_eq!(satisfying("created-after", "jan 1, 2000"), vec!["early".to_owned(), "boundary".to_owned()]);
+    assert_eq!(satisfying("created-before", "jan 1, 2100"), vec!["early".to_owned(), "boundary".to_owned()]);
+    assert_eq!(satisfying("due-after", "jan 1, 2000"), vec!["early".to_owned(), "boundary".to_owned()]);
+    assert_eq!(satisfying("due-before", "jan 1, 2100"), vec!["early".to_owned(), "boundary".to_owned()]);
+    assert_eq!(satisfying("done-after", "jan 1, 2000"), vec!["finished".to_owned()]);
+    assert_eq!(satisfying("deferred-until-before", "jan 1, 2100"), vec!["early".to_owned()]);
+}
+
+fn where_satisfy_datetime(operator: &WhereOperator, date_time: Option<NaiveDateTime>, values: &[WhereValue]) -> bool {
+
+    let date_time = match date_time {
+        None => {
+            return false;
+        },
+        Some(date_time) => date_time
+    };
+
+    values.iter().any(|value| {
+        match (operator, value) {
+            (&WhereOperator::Compare(ref inequality), &WhereValue::DateTime(ref given)) => {
+                satisfy_inequality(inequality, date_time.timestamp(), given.timestamp())
+            },
+            (&WhereOperator::Compare(ref inequality), &WhereValue::Date(ref given)) => {
+                satisfy_inequality(inequality, date_time.date(), *given)
+            },
+            _ => false
+        }
+    })
+}
+
+fn parse_where(input: Input<u8>, now: NaiveDateTime) -> U8Result<Result<WhereFilterTree, String>> {
 
     parse!{input;
 
         skip_many(space_or_tab);
 
-        let result = parse_priority_filter_tree();
+        let result = parse_filter_tree(move |i| parse_where_filter(i, now));
 
         skip_many(space_or_tab);
         eof();
 
-        ret result
+        ret resolve_filter_tree(result)
     }
 
 }
 
-/*
-Source: http://www.engr.mun.ca/~theo/Misc/exp_parsing.htm#classic
-Source: https://en.wikipedia.org/wiki/Operator-precedence_parser#Precedence_climbing_method
-
-Original:
-E --> T {or T}
-T --> P {and P}
-P --> "(" E ")" | leaf
+fn parse_where_filter(input: Input<u8>, now: NaiveDateTime) -> U8Result<Result<WhereFilter, String>> {
 
-Expanded:
-E = T K | T
-K = or T K | or T
-T = P L | P
-L = and P L | and P
-P = "(" E ")" | leaf
+    parse!{input;
 
-Renamed variables:
-tree = maybe_predicate_intersect union | maybe_predicate_intersect
-union = or maybe_predicate_intersect union | or maybe_predicate_intersect
-maybe_predicate_intersect = predicate intersect | predicate
-intersect = and predicate intersect | and predicate
-predicate = "(" tree ")" | leaf
- */
+        let field = parse_task_field();
 
-fn parse_priority_filter_tree(input: Input<u8>) -> U8Result<PriorityFilterTree> {
-    or(input,
-        |input| parse!{input;
+        skip_many(space_or_tab);
 
-            let left_node = parse_priority_filter_tree_maybe_predicate_intersect();
+        let filter = parse_where_condition(field, now);
 
-            skip_many(space_or_tab);
+        ret filter
+    }
+}
 
-            let right_node = parse_priority_filter_tree_union(left_node);
-            ret right_node
-        },
-        |input| parse!{input;
+fn parse_task_field(input: Input<u8>) -> U8Result<TaskField> {
+    parse!{input;
 
-            let right_node = parse_priority_filter_tree_maybe_predicate_intersect();
-            ret right_node
-        }
-    )
+        let field = resolve_task_field("title", TaskField::Title) <|>
+            resolve_task_field("task", TaskField::Title) <|>
+            resolve_task_field("notes", TaskField::Note) <|>
+            resolve_task_field("note", TaskField::Note) <|>
+            resolve_task_field("project", TaskField::Project) <|>
+            resolve_task_field("tags", TaskField::Tag) <|>
+            resolve_task_field("tag", TaskField::Tag) <|>
+            resolve_task_field("contexts", TaskField::Context) <|>
+            resolve_task_field("context", TaskField::Context) <|>
+            resolve_task_field("status", TaskField::Status) <|>
+            resolve_task_field("priority", TaskField::Priority) <|>
+            resolve_task_field("due", TaskField::Due) <|>
+            resolve_task_field("deferred", TaskField::Defer) <|>
+            resolve_task_field("defer", TaskField::Defer) <|>
+            resolve_task_field("created", TaskField::Created) <|>
+            resolve_task_field("done", TaskField::Done) <|>
+            resolve_task_field("time", TaskField::Time) <|>
+            resolve_task_field("flagged", TaskField::Flagged) <|>
+            resolve_task_field("flag", TaskField::Flagged) <|>
+            resolve_task_field("file", TaskField::File);
+
+        ret field
+    }
+}
+
+fn resolve_task_field<'a>(i: Input<'a, u8>, name: &str, field: TaskField) -> SimpleResult<'a, u8, TaskField> {
+    parse!{i;
+        string_ignore_case(name.as_bytes());
+        ret field
+    }
 }
 
-fn parse_priority_filter_tree_union(input: Input<u8>, left_node: PriorityFilterTree)
--> U8Result<PriorityFilterTree> {
-    or(input,
-        |input| parse!{input;
+fn parse_where_condition(input: Input<u8>, field: TaskField, now: NaiveDateTime)
+-> U8Result<Result<WhereFilter, String>> {
 
-            string("||".as_bytes()) <|>
-            string("|".as_bytes()) <|>
-            string_ignore_case("or".as_bytes());
+    match field {
+        TaskField::Title | TaskField::Note | TaskField::Tag | TaskField::Context | TaskField::File |
+        TaskField::Project => {
+            parse_where_text_condition(input, field)
+        },
+        TaskField::Status => {
+            parse!{input;
 
-            skip_many(space_or_tab);
+                let operator = parse_where_equality_operator();
 
-            let right_node = parse_priority_filter_tree_maybe_predicate_intersect();
+                skip_many(space_or_tab);
 
-            skip_many(space_or_tab);
+                let values = parse_where_values(operator.clone(), parse_status);
 
-            let tree = parse_priority_filter_tree_union({
-                let left_node = Box::new(left_node.clone());
-                let right_node = Box::new(right_node);
+                ret {
+                    let values = values.into_iter().map(WhereValue::Status).collect();
+                    Ok(WhereFilter(field, operator, values))
+                }
+            }
+        },
+        TaskField::Priority => {
+            parse!{input;
 
-                PriorityFilterTree::Union(left_node, right_node)
-            });
+                let operator = parse_where_inequality_operator();
 
-            ret tree
-        },
-        |input| parse!{input;
+                skip_many(space_or_tab);
 
-            string("||".as_bytes()) <|>
-            string("|".as_bytes()) <|>
-            string_ignore_case("or".as_bytes());
+                let priority = parse_priority_number();
 
-            skip_many(space_or_tab);
+                ret Ok(WhereFilter(field, operator, vec![WhereValue::Number(priority)]))
+            }
+        },
+        TaskField::Time => {
+            parse!{input;
 
-            let right_node = parse_priority_filter_tree_maybe_predicate_intersect();
+                let operator = parse_where_inequality_operator();
 
-            ret {
-                let left_node = Box::new(left_node.clone());
-                let right_node = Box::new(right_node);
+                skip_many(space_or_tab);
+
+                let time = multiple_time_range();
 
-                PriorityFilterTree::Union(left_node, right_node)
+                ret Ok(WhereFilter(field, operator, vec![WhereValue::Duration(time)]))
             }
         },
-    )
-}
+        TaskField::Due | TaskField::Defer | TaskField::Created | TaskField::Done => {
+            parse!{input;
 
-fn parse_priority_filter_tree_maybe_predicate_intersect(input: Input<u8>)
--> U8Result<PriorityFilterTree> {
+                let operator = parse_where_inequality_operator();
 
-    or(input,
-        |input| parse!{input;
+                skip_many(space_or_tab);
 
-            let left_node = parse_priority_filter_tree_predicate();
+                // the time of day is only defaulted when none is given
+                let end_of_day = look_ahead(|i| parse_relative_datetime(i, true, now));
+                let date_time = parse_relative_datetime(false, now);
 
-            skip_many(space_or_tab);
+                ret {
+                    date_time.map(|date_time| {
 
-            let right_node = parse_priority_filter_tree_intersect(left_node);
-            ret right_node;
+                        let value = match end_of_day {
+                            Ok(end_of_day) if end_of_day != date_time && end_of_day.date() == date_time.date() => {
+                                WhereValue::Date(date_time.date())
+                            },
+                            _ => WhereValue::DateTime(date_time)
+                        };
 
+                        WhereFilter(field, operator, vec![value])
+                    })
+                }
+            }
         },
-        |input| parse!{input;
+        TaskField::Flagged => {
+            or(input,
+                |i| parse!{i;
 
-            let right_node = parse_priority_filter_tree_predicate();
-            ret right_node;
-        }
-    )
+                    let operator = parse_where_equality_operator();
+
+                    skip_many(space_or_tab);
 
+                    let flag = bool_option_parser();
+
+                    ret Ok(WhereFilter(TaskField::Flagged, operator, vec![WhereValue::Bool(flag)]))
+                },
+                // e.g. `flagged` is shorthand for `flagged = yes`
+                |i| parse!{i;
+                    ret Ok(WhereFilter(TaskField::Flagged,
+                        WhereOperator::Compare(Inequality::Equal),
+                        vec![WhereValue::Bool(true)]))
+                }
+            )
+        }
+    }
 }
 
-fn parse_priority_filter_tree_intersect(input: Input<u8>, left_node: PriorityFilterTree)
--> U8Result<PriorityFilterTree> {
-    or(input,
-        |input| parse!{input;
+fn parse_where_text_condition(input: Input<u8>, field: TaskField) -> U8Result<Result<WhereFilter, String>> {
+    parse!{input;
 
-            string("&&".as_bytes()) <|>
-            string("&".as_bytes()) <|>
-            string_ignore_case("and".as_bytes());
+        let operator = __parse_where_operator("^=", WhereOperator::StartsWith) <|>
+            __parse_where_operator("~", WhereOperator::Contains) <|>
+            parse_where_equality_operator();
 
-            skip_many(space_or_tab);
+        skip_many(space_or_tab);
 
-            let right_node = parse_priority_filter_tree_predicate();
+        let values = parse_where_values(operator.clone(), parse_where_text_value);
 
-            skip_many(space_or_tab);
+        ret {
+            let values: Vec<WhereValue> = match field {
+                TaskField::Project => {
+                    values.into_iter().map(|value| {
+
+                        let path: ProjectPath = value
+                            .split('/')
+                            .map(|segment| segment.trim().to_string())
+                            .filter(|segment| segment.len() > 0)
+                            .collect();
+
+                        WhereValue::Path(path)
+                    }).collect()
+                },
+                _ => values.into_iter().map(WhereValue::Text).collect()
+            };
 
-            let tree = parse_priority_filter_tree_intersect({
-                let left_node = Box::new(left_node.clone());
-                let right_node = Box::new(right_node);
+            Ok(WhereFilter(field, operator, values))
+        }
+    }
+}
 
-                PriorityFilterTree::Intersection(left_node, right_node)
-            });
+// =, ==, !=, in
+fn parse_where_equality_operator(input: Input<u8>) -> U8Result<WhereOperator> {
+    parse!{input;
 
-            ret tree
-        },
-        |input| parse!{input;
+        let result = __parse_where_operator("!=", WhereOperator::NotEqual) <|>
+            __parse_where_operator("==", WhereOperator::Compare(Inequality::Equal)) <|>
+            __parse_where_operator("=", WhereOperator::Compare(Inequality::Equal)) <|>
+            __parse_where_operator("in", WhereOperator::In);
 
-            string("&&".as_bytes()) <|>
-            string("&".as_bytes()) <|>
-            string_ignore_case("and".as_bytes());
+        ret result
+    }
+}
 
-            skip_many(space_or_tab);
+// <, <=, >, >=, =, ==, !=
+fn parse_where_inequality_operator(input: Input<u8>) -> U8Result<WhereOperator> {
+    parse!{input;
 
-            let right_node = parse_priority_filter_tree_predicate();
+        let result = __parse_where_operator("!=", WhereOperator::NotEqual) <|>
+            parse_where_compare_operator();
 
-            ret {
-                let left_node = Box::new(left_node.clone());
-                let right_node = Box::new(right_node);
+        ret result
+    }
+}
+
+fn parse_where_compare_operator(input: Input<u8>) -> U8Result<WhereOperator> {
+    parse!{input;
+        let inequality = parse_inequality();
+        ret WhereOperator::Compare(inequality)
+    }
+}
 
-                PriorityFilterTree::Intersection(left_node, right_node)
-            }
-        },
-    )
+fn __parse_where_operator<'a>(input: Input<'a, u8>, needle: &str, output: WhereOperator)
+-> SimpleResult<'a, u8, WhereOperator> {
+    parse!{input;
+        string_ignore_case(needle.as_bytes());
+        ret output
+    }
 }
 
-fn parse_priority_filter_tree_predicate(input: Input<u8>) -> U8Result<PriorityFilterTree> {
-    or(input,
-        |input| parse!{input;
+// the `in` operator takes a parenthesized list of comma separated values; any other operator
+// takes a single value.
+fn parse_where_values<'a, T, P>(input: Input<'a, u8>, operator: WhereOperator, value: P)
+-> U8Result<'a, Vec<T>>
+    where
+    P: FnOnce(Input<'a, u8>) -> U8Result<'a, T> + Copy {
 
-            token(b'(');
-            skip_many(space_or_tab);
+    match operator {
+        WhereOperator::In => {
+            parse!{input;
 
-            let tree = parse_priority_filter_tree();
+                token(b'(');
+                skip_many(space_or_tab);
 
-            skip_many(space_or_tab);
-            token(b')');
+                let first = value();
 
-            ret tree
+                let rest: Vec<T> = many(|i| parse!{i;
 
-        },
-        |input| parse!{input;
+                    skip_many(space_or_tab);
+                    token(b',');
+                    skip_many(space_or_tab);
 
-            let filter = parse_priority_filter();
+                    let item = value();
 
-            ret PriorityFilterTree::Leaf(filter)
+                    ret item
+                });
 
+                skip_many(space_or_tab);
+                token(b')');
+
+                ret {
+                    let mut values = vec![first];
+                    values.extend(rest);
+                    values
+                }
+            }
         },
-    )
+        _ => {
+            parse!{input;
+                let item = value();
+                ret vec![item]
+            }
+        }
+    }
 }
 
-fn parse_priority_filter(input: Input<u8>) -> U8Result<PriorityFilter> {
-
+// "quoted value", 'quoted value', or bare_value
+fn parse_where_text_value(input: Input<u8>) -> U8Result<String> {
     or(input,
-        |input| parse!{input;
+        |i| parse!{i;
 
-            let operator = parse_inequality();
+            let quote = token(b'"') <|> token(b'\'');
 
-            skip_many(space_or_tab);
+            let value: Vec<u8> = many(|i| satisfy(i, |c| c != quote));
 
-            let priority = parse_priority_number();
+            token(quote);
 
-            ret PriorityFilter(operator, priority)
+            ret String::from_utf8_lossy(value.as_slice()).into_owned()
         },
-        |input| parse!{input;
+        |i| parse!{i;
 
-            let priority = parse_priority_number();
+            let value: Vec<u8> = many1(|i| satisfy(i, is_where_bare_value));
 
-            ret PriorityFilter(Inequality::Equal, priority)
+            ret String::from_utf8_lossy(value.as_slice()).into_owned()
         }
     )
 }
 
-fn parse_inequality(input: Input<u8>) -> U8Result<Inequality> {
-    parse!{input;
+fn is_where_bare_value(c: u8) -> bool {
+    match c {
+        b'(' | b')' | b',' | b'"' | b'\'' => false,
+        _ => !is_whitespace(c)
+    }
+}
 
-        let result = __parse_inequality(">=", Inequality::GreaterThanOrEqual) <|>
-            __parse_inequality(">", Inequality::GreaterThan) <|>
-            __parse_inequality("<=", Inequality::LessThanOrEqual) <|>
-            __parse_inequality("<", Inequality::LessThan) <|>
-            __parse_inequality("==", Inequality::Equal) <|>
-            __parse_inequality("=", Inequality::Equal);
+#[test]
+fn parse_where_test() {
+
+    let now = NaiveDate::from_ymd(2016, 10, 21).and_hms(17, 0, 0);
+
+    let valid = vec![
+        ("tag = chore and (due < tomorrow or priority >= 5)",
+            "tag = chore and (due < October 22, 2016 or priority >= 5)"),
+        ("project ^= work/backend", "project ^= work/backend"),
+        ("status in (done, incubate)", "status in (done, incubate)"),
+        ("not context = phone", "not context = phone"),
+        ("title ~ \"buy milk\" || !flagged", "title ~ \"buy milk\" or not flagged = yes"),
+        ("time >= 1h30m", "time >= 1 hour 30 minutes"),
+        ("created > oct 1, 2016 and done <= now", "created > October 1, 2016 and done <= October 21, 2016 5:00 PM"),
+        ("due = today 9:30", "due = October 21, 2016 9:30 AM")
+    ];
+
+    for &(input, expected) in &valid {
+        match parse_only(|i| parse_where(i, now), input.as_bytes()) {
+            Ok(Ok(tree)) => {
+                assert_eq!(where_pretty_tree_art(&tree), expected);
+            },
+            _ => {
+                assert!(false, "unable to parse: {}", input);
+            }
+        }
+    }
 
-        ret result
+    match parse_only(|i| parse_where(i, now), "due < feb 31, 2016".as_bytes()) {
+        Ok(Err(_)) => {},
+        _ => {
+            assert!(false, "expected invalid date");
+        }
     }
-}
 
-fn __parse_inequality<'a>(input: Input<'a, u8>, needle: &str, output: Inequality) -> SimpleResult<'a, u8, Inequality> {
-    parse!{input;
-        string_ignore_case(needle.as_bytes());
-        ret output
+    for input in &["", "tag", "priority ~ 5", "status < done", "title = milk and", "(tag = chore"] {
+        match parse_only(|i| parse_where(i, now), input.as_bytes()) {
+            Ok(Ok(_)) => {
+                assert!(false, "expected parse failure: {}", input);
+            },
+            _ => {}
+        }
+    }
+
+    let mut task = Task::new(1);
+    task.title = Some("Buy milk".to_owned());
+    task.tags = Some(vec!["chore".to_owned()]);
+    task.project = Some(vec!["work".to_owned(), "backend".to_owned(), "api".to_owned()]);
+    task.priority = 3;
+
+    let cases = vec![
+        ("tag = chore and (due < tomorrow or priority >= 5)", false),
+        ("tag = chore and (due != tomorrow or priority >= 5)", true),
+        ("project ^= work/backend", true),
+        ("project = work/backend", false),
+        ("status in (done, incubate)", false),
+        ("not context = phone", true),
+        ("title ~ MILK", true)
+    ];
+
+    for &(input, expected) in &cases {
+        match parse_only(|i| parse_where(i, now), input.as_bytes()) {
+            Ok(Ok(tree)) => {
+                assert_eq!(where_satisfy_tree(&tree, &task, "/"), expected, "evaluating: {}", input);
+            },
+            _ => {
+                assert!(false, "unable to parse: {}", input);
+            }
+        }
+    }
+
+    // dates without a time of day are compared by calendar day
+    let mut task = Task::new(1);
+    task.due_at = Some(NaiveDate::from_ymd(2016, 10, 21).and_hms(14, 0, 0));
+
+    let cases = vec![
+        ("due = today", true),
+        ("due != today", false),
+        ("due = tomorrow", false),
+        ("due <= today", true),
+        ("due > today", false),
+        ("due = today 9:30", false),
+        ("due > today 9:30", true)
+    ];
+
+    for &(input, expected) in &cases {
+        match parse_only(|i| parse_where(i, now), input.as_bytes()) {
+            Ok(Ok(tree)) => {
+                assert_eq!(where_satisfy_tree(&tree, &task, "/"), expected, "evaluating: {}", input);
+            },
+            _ => {
+                assert!(false, "unable to parse: {}", input);
+            }
+        }
     }
 }
 
@@ -5108,6 +13676,14 @@ fn parse_times_ranges(i: Input<u8>) -> U8Result<TimeLength> {
 }
 
 fn multiple_time_range(i: Input<u8>) -> U8Result<TimeLength> {
+    or(i,
+        iso8601_duration,
+        human_time_ranges
+    )
+}
+
+// e.g. 2 days 4 hrs, 1.5 h, 1h30m, 2w3d
+fn human_time_ranges(i: Input<u8>) -> U8Result<TimeLength> {
 
     parse!{i;
 
@@ -5147,18 +13723,46 @@ fn multiple_time_range(i: Input<u8>) -> U8Result<TimeLength> {
 fn time_range(i: Input<u8>) -> U8Result<u64> {
     parse!{i;
 
-        let range: u64 = decimal();
+        let range: f64 = time_range_number();
 
         skip_many(space_or_tab);
 
-        let multiplier = time_range_unit_minutes() <|>
+        // NOTE: months are parsed before minutes; otherwise `m` would match the prefix of `months`
+        let multiplier = time_range_unit_months() <|>
+            time_range_unit_minutes() <|>
             time_range_unit_hours() <|>
             time_range_unit_days() <|>
             time_range_unit_seconds() <|>
-            time_range_unit_weeks();
+            time_range_unit_weeks() <|>
+            time_range_unit_years();
+
+        ret {
+            (range * multiplier as f64).round() as u64
+        }
+    }
+}
+
+// whole number with an optional fractional part (e.g. 1.5)
+fn time_range_number(i: Input<u8>) -> U8Result<f64> {
+    parse!{i;
+
+        let whole: u64 = decimal();
+
+        let fraction: f64 = option(|i| parse!{i;
+
+            token(b'.');
+
+            let digits: Vec<u8> = many1(digit);
+
+            ret {
+                digits.iter().rev().fold(0f64, |fraction, &digit| {
+                    (fraction + (digit - b'0') as f64) / 10f64
+                })
+            }
+        }, 0f64);
 
         ret {
-            range * multiplier
+            whole as f64 + fraction
         }
     }
 }
@@ -5232,6 +13836,151 @@ fn time_range_unit_weeks(i: Input<u8>) -> U8Result<u64> {
     }
 }
 
+fn time_range_unit_months(i: Input<u8>) -> U8Result<u64> {
+    parse!{i;
+
+        string_ignore_case("months".as_bytes()) <|>
+        string_ignore_case("month".as_bytes()) <|>
+        string_ignore_case("mos".as_bytes()) <|>
+        string_ignore_case("mo".as_bytes());
+
+        // a month is 30 days; 2592000 seconds in a month
+        ret 2592000
+    }
+}
+
+fn time_range_unit_years(i: Input<u8>) -> U8Result<u64> {
+    parse!{i;
+
+        string_ignore_case("years".as_bytes()) <|>
+        string_ignore_case("year".as_bytes()) <|>
+        string_ignore_case("yrs".as_bytes()) <|>
+        string_ignore_case("yr".as_bytes()) <|>
+        string_ignore_case("y".as_bytes());
+
+        // a year is 365 days; 31536000 seconds in a year
+        ret 31536000
+    }
+}
+
+// ISO 8601 durations. e.g. PT1H30M, P2W, P1Y2M10DT2H30M
+//
+// Months and years have the same fixed lengths as above.
+fn iso8601_duration(i: Input<u8>) -> U8Result<TimeLength> {
+
+    parse!{i;
+
+        string_ignore_case("P".as_bytes());
+
+        let date_components: Vec<u64> = many(iso8601_date_component);
+
+        let time_components: Vec<u64> = option(|i| parse!{i;
+
+            string_ignore_case("T".as_bytes());
+
+            let time_components: Vec<u64> = many1(iso8601_time_component);
+
+            ret time_components
+        }, vec![]);
+
+        ret {
+            let mut components = date_components;
+            components.extend_from_slice(&time_components);
+            components
+        }
+    }
+    .bind(|i, components: Vec<u64>| {
+
+        // at least one component is required (i.e. `P` or `PT` are invalid)
+        if components.len() <= 0 {
+            return i.incomplete(1);
+        }
+
+        let time = components.iter().fold(0, |mut sum, &val| {sum += val; sum});
+        i.ret(time)
+    })
+}
+
+fn iso8601_date_component(i: Input<u8>) -> U8Result<u64> {
+    parse!{i;
+
+        let range: f64 = time_range_number();
+
+        let multiplier = resolve_time_unit("Y", 31536000) <|>
+            resolve_time_unit("M", 2592000) <|>
+            resolve_time_unit("W", 604800) <|>
+            resolve_time_unit("D", 86400);
+
+        ret {
+            (range * multiplier as f64).round() as u64
+        }
+    }
+}
+
+fn iso8601_time_component(i: Input<u8>) -> U8Result<u64> {
+    parse!{i;
+
+        let range: f64 = time_range_number();
+
+        let multiplier = resolve_time_unit("H", 3600) <|>
+            resolve_time_unit("M", 60) <|>
+            resolve_time_unit("S", 1);
+
+        ret {
+            (range * multiplier as f64).round() as u64
+        }
+    }
+}
+
+fn resolve_time_unit<'a>(i: Input<'a, u8>, unit: &str, seconds: u64) -> SimpleResult<'a, u8, u64> {
+    parse!{i;
+        string_ignore_case(unit.as_bytes());
+        ret seconds
+    }
+}
+
+#[test]
+fn parse_times_ranges_test() {
+
+    let cases: Vec<(&str, u64)> = vec![
+        ("25 min", 1500),
+        ("2 days 4 hrs", 187200),
+        ("1 hour and 30 minutes", 5400),
+        ("1.5 h", 5400),
+        ("0.25 hours", 900),
+        ("1h30m", 5400),
+        ("2w3d", 1468800),
+        ("1 month", 2592000),
+        ("2 mos 1 min", 5184060),
+        ("1 year", 31536000),
+        ("PT1H30M", 5400),
+        ("P1DT12H", 129600),
+        ("P2W", 1209600),
+        ("P1Y2M", 36720000),
+        ("PT0.5S", 1)
+    ];
+
+    for &(input, expected) in &cases {
+        match parse_only(|i| parse_times_ranges(i), input.as_bytes()) {
+            Ok(result) => {
+                assert_eq!(result, expected, "parsing: {}", input);
+            },
+            Err(_) => {
+                assert!(false, "unable to parse: {}", input);
+            }
+        }
+    }
+
+    for input in &["", "P", "PT", "1.h", "h30m", "PT1H30"] {
+        match parse_only(|i| parse_times_ranges(i), input.as_bytes()) {
+            Ok(_) => {
+                assert!(false, "expected parse failure: {}", input);
+            },
+            Err(_) => {}
+        }
+    }
+}
+
 /* datetime parsers */
 
 enum Meridiem {
@@ -5249,7 +13998,7 @@ struct Time {
 
 struct ParsedDate {
 
-    // between 1 and 31
+    // up to two digits; validated against the month when resolved
     day: u32,
 
     // between 1 and 12
@@ -5264,7 +14013,9 @@ struct ParsedDateTime {
     date: ParsedDate
 }
 
-fn __parse_datetime(i: Input<u8>, end_of_day: bool) -> U8Result<NaiveDateTime> {
+// Any datetime that is parsed is not necessarily a valid calendar date (e.g. February 31).
+// Such datetimes are resolved into an error describing the invalid date or time.
+fn __parse_datetime(i: Input<u8>, end_of_day: bool) -> U8Result<Result<NaiveDateTime, String>> {
 
     or(i,
         |i| parse!{i;
@@ -5321,59 +14072,301 @@ fn __parse_datetime(i: Input<u8>, end_of_day: bool) -> U8Result<NaiveDateTime> {
         )
     )
     .bind(|i, above: ParsedDateTime| {
+        i.ret(resolve_datetime(above))
+    })
+}
+
+fn resolve_datetime(parsed: ParsedDateTime) -> Result<NaiveDateTime, String> {
+
+    let ParsedDateTime { date, time } = parsed;
+
+    let resolved_date = match NaiveDate::from_ymd_opt(date.year, date.month, date.day) {
+        Some(resolved_date) => resolved_date,
+        None => {
+
+            let pretty_date = format!("{} {}, {}", month_name(date.month), date.day, date.year);
+
+            return match days_in_month(date.year, date.month) {
+                Some(num_of_days) => {
+                    Err(format!("{} is not a valid date; {} {} has {} days.",
+                        pretty_date,
+                        month_name(date.month),
+                        date.year,
+                        num_of_days))
+                },
+                None => {
+                    Err(format!("{} is not a valid date; the year is out of range.", pretty_date))
+                }
+            };
+        }
+    };
+
+    let resolved_time = match NaiveTime::from_hms_opt(time.hour, time.minute, 0) {
+        Some(resolved_time) => resolved_time,
+        None => {
+            return Err(format!("{:02}:{:02} is not a valid time.", time.hour, time.minute));
+        }
+    };
+
+    Ok(NaiveDateTime::new(resolved_date, resolved_time))
+}
+
+fn days_in_month(year: i32, month: u32) -> Option<i64> {
+
+    let first_day = match NaiveDate::from_ymd_opt(year, month, 1) {
+        None => {
+            return None;
+        },
+        Some(first_day) => first_day
+    };
+
+    let first_day_of_next_month = if month >= 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    };
 
-        let date = NaiveDate::from_ymd(above.date.year, above.date.month, above.date.day);
-        let time = NaiveTime::from_hms(above.time.hour, above.time.minute, 0);
-        let date_time = NaiveDateTime::new(date, time);
+    match first_day_of_next_month {
+        None => None,
+        Some(first_day_of_next_month) => {
+            Some((first_day_of_next_month - first_day).num_days())
+        }
+    }
+}
+
+fn month_name(month: u32) -> &'static str {
+    match month {
+        1 => "January",
+        2 => "February",
+        3 => "March",
+        4 => "April",
+        5 => "May",
+        6 => "June",
+        7 => "July",
+        8 => "August",
+        9 => "September",
+        10 => "October",
+        11 => "November",
+        12 => "December",
+        _ => "Unknown month"
+    }
+}
+
+fn parse_datetime(i: Input<u8>, end_of_day: bool) -> U8Result<Result<NaiveDateTime, String>> {
+    parse!{i;
+
+        let date_time = __parse_datetime(end_of_day);
+
+        let (should_add, time_span) = parse_datetime_offset();
+
+        ret apply_datetime_offset(date_time, should_add, time_span)
+    }
+}
+
+// parse datetime that may be relative to the given point in time.
+// e.g. now, today, tomorrow 5pm, fri, yesterday - 2 days
+fn parse_relative_datetime(i: Input<u8>, end_of_day: bool, now: NaiveDateTime)
+-> U8Result<Result<NaiveDateTime, String>> {
+    parse!{i;
+
+        let date_time = __parse_datetime(end_of_day) <|>
+            __parse_relative_datetime(end_of_day, now);
+
+        let (should_add, time_span) = parse_datetime_offset();
+
+        ret apply_datetime_offset(date_time, should_add, time_span)
+    }
+}
+
+fn __parse_relative_datetime(i: Input<u8>, end_of_day: bool, now: NaiveDateTime)
+-> U8Result<Result<NaiveDateTime, String>> {
+    or(i,
+        |i| parse!{i;
+            string_ignore_case("now".as_bytes());
+            ret Ok(now)
+        },
+        |i| parse!{i;
+
+            let days = parse_relative_day(now.date());
+
+            let time = option(|i| parse!{i;
+                skip_many1(space_or_tab);
+                let time = parse_time();
+                ret Some(time)
+            }, None);
+
+            ret {
+
+                let time = match time {
+                    Some(time) => time,
+                    None => {
+                        if end_of_day {
+                            Time {
+                                hour: 23,
+                                minute: 59
+                            }
+                        } else {
+                            Time {
+                                hour: 0,
+                                minute: 0
+                            }
+                        }
+                    }
+                };
+
+                match now.date().checked_add(Duration::days(days)) {
+                    None => Err("Datetime is out of range.".to_owned()),
+                    Some(date) => {
+                        match NaiveTime::from_hms_opt(time.hour, time.minute, 0) {
+                            None => Err(format!("{:02}:{:02} is not a valid time.", time.hour, time.minute)),
+                            Some(time) => Ok(NaiveDateTime::new(date, time))
+                        }
+                    }
+                }
+            }
+        }
+    )
+}
+
+// number of days from today.
+// a weekday is either today, or its next occurrence.
+fn parse_relative_day(i: Input<u8>, today: NaiveDate) -> U8Result<i64> {
+    or(i,
+        |i| parse!{i;
+
+            let days = resolve_relative_day("today", 0) <|>
+                resolve_relative_day("tomorrow", 1) <|>
+                resolve_relative_day("yesterday", -1);
+
+            ret days
+        },
+        |i| parse!{i;
+
+            let weekday = parse_weekday();
+
+            ret {
+                let today = today.weekday().num_days_from_monday();
+                ((weekday + 7 - today) % 7) as i64
+            }
+        }
+    )
+}
 
-        i.ret(date_time)
-    })
+fn resolve_relative_day<'a>(i: Input<'a, u8>, day: &str, ret_val: i64) -> SimpleResult<'a, u8, i64> {
+    parse!{i;
+        string_ignore_case(day.as_bytes());
+        ret ret_val
+    }
 }
 
-fn parse_datetime(i: Input<u8>, end_of_day: bool) -> U8Result<NaiveDateTime> {
+// days from monday
+fn parse_weekday(i: Input<u8>) -> U8Result<u32> {
     parse!{i;
 
-        let date_time = __parse_datetime(end_of_day);
+        let weekday = resolve_weekday("monday", 0) <|>
+            resolve_weekday("mon", 0) <|>
+            resolve_weekday("tuesday", 1) <|>
+            resolve_weekday("tues", 1) <|>
+            resolve_weekday("tue", 1) <|>
+            resolve_weekday("wednesday", 2) <|>
+            resolve_weekday("wed", 2) <|>
+            resolve_weekday("thursday", 3) <|>
+            resolve_weekday("thurs", 3) <|>
+            resolve_weekday("thur", 3) <|>
+            resolve_weekday("thu", 3) <|>
+            resolve_weekday("friday", 4) <|>
+            resolve_weekday("fri", 4) <|>
+            resolve_weekday("saturday", 5) <|>
+            resolve_weekday("sat", 5) <|>
+            resolve_weekday("sunday", 6) <|>
+            resolve_weekday("sun", 6);
+
+        ret weekday
+    }
+}
+
+fn resolve_weekday<'a>(i: Input<'a, u8>, weekday: &str, ret_val: u32) -> SimpleResult<'a, u8, u32> {
+    parse!{i;
+        string_ignore_case(weekday.as_bytes());
+        ret ret_val
+    }
+}
 
-        let (should_add, time_span) = option(
-            |i| parse!{i;
+// e.g. + 2 days, - 1 hour, add 5 min, subtract 1 week
+fn parse_datetime_offset(i: Input<u8>) -> U8Result<(bool, TimeLength)> {
+    option(i,
+        |i| parse!{i;
 
-                space_or_tab();
-                skip_many(space_or_tab);
+            space_or_tab();
+            skip_many(space_or_tab);
 
-                let should_add = option(
-                    |i| or(i,
-                        |i| parse!{i;
-                            string_ignore_case("+".as_bytes()) <|>
-                            string_ignore_case("add".as_bytes());
-                            ret true
-                        },
-                        |i| parse!{i;
-                            string_ignore_case("-".as_bytes()) <|>
-                            string_ignore_case("sub".as_bytes()) <|>
-                            string_ignore_case("subtract".as_bytes());
-                            ret false
-                        }),
-                    true
-                );
+            let should_add = option(
+                |i| or(i,
+                    |i| parse!{i;
+                        string_ignore_case("+".as_bytes()) <|>
+                        string_ignore_case("add".as_bytes());
+                        ret true
+                    },
+                    |i| parse!{i;
+                        string_ignore_case("-".as_bytes()) <|>
+                        string_ignore_case("sub".as_bytes()) <|>
+                        string_ignore_case("subtract".as_bytes());
+                        ret false
+                    }),
+                true
+            );
 
-                skip_many(space_or_tab);
+            skip_many(space_or_tab);
 
-                let time: TimeLength = multiple_time_range();
+            let time: TimeLength = multiple_time_range();
 
-                ret (should_add, time)
+            ret (should_add, time)
 
-            },
-            (true, 0)
-        );
+        },
+        (true, 0)
+    )
+}
 
-        ret {
-            if should_add {
-                date_time + Duration::seconds(time_span as i64)
-            } else {
-                date_time - Duration::seconds(time_span as i64)
-            }
+fn apply_datetime_offset(date_time: Result<NaiveDateTime, String>, should_add: bool, time_span: TimeLength)
+-> Result<NaiveDateTime, String> {
+
+    date_time.and_then(|date_time| {
+
+        let time_span = Duration::seconds(time_span as i64);
+
+        let result = if should_add {
+            date_time.checked_add(time_span)
+        } else {
+            date_time.checked_sub(time_span)
+        };
+
+        match result {
+            Some(result) => Ok(result),
+            None => Err("Datetime is out of range.".to_owned())
         }
+    })
+}
+
+// parse datetime given as a command-line option
+fn parse_datetime_option(i: Input<u8>, end_of_day: bool) -> U8Result<Result<NaiveDateTime, String>> {
+    parse!{i;
+        skip_many(space_or_tab);
+        let result = parse_datetime(end_of_day);
+        skip_many(space_or_tab);
+        eof();
+        ret result
+    }
+}
+
+// parse datetime, that may be relative to the given point in time, given as a command-line option
+fn parse_relative_datetime_option(i: Input<u8>, end_of_day: bool, now: NaiveDateTime)
+-> U8Result<Result<NaiveDateTime, String>> {
+    parse!{i;
+        skip_many(space_or_tab);
+        let result = parse_relative_datetime(end_of_day, now);
+        skip_many(space_or_tab);
+        eof();
+        ret result
     }
 }
 
@@ -5576,32 +14569,11 @@ fn military_time(i: Input<u8>) -> U8Result<Time> {
             }
         }
     )
-    .bind(|i, above:Time| {
-
-        // if 0 <= above.hour && above.hour <= 23 && 0 <= above.minute && above.minute <= 59  {
-        if above.hour <= 23 && above.minute <= 59  {
-            return i.ret(above);
-        }
-
-        // TODO: right usize?
-        return i.incomplete(1);
-    })
 }
 
+// hours out of range (e.g. 25:00) are reported by resolve_datetime
 fn parse_24_hour(i: Input<u8>) -> U8Result<u32> {
-
     up_to_two_digits(i)
-    .bind(|i, above:u32| {
-
-        // if 0 <= above && above <= 23 {
-        if above <= 23 {
-            return i.ret(above);
-        }
-
-        // TODO: right usize?
-        return i.incomplete(1);
-    })
-
 }
 
 fn parse_12_hour(i: Input<u8>) -> U8Result<u32> {
@@ -5619,20 +14591,9 @@ fn parse_12_hour(i: Input<u8>) -> U8Result<u32> {
 
 }
 
+// minutes out of range (e.g. 10:61) are reported by resolve_datetime
 fn parse_minute(i: Input<u8>) -> U8Result<u32> {
-
     two_digits(i)
-    .bind(|i, above:u32| {
-
-        // if 0 <= above && above <= 59 {
-        if above <= 59 {
-            return i.ret(above);
-        }
-
-        // TODO: right usize?
-        return i.incomplete(1);
-    })
-
 }
 
 fn parse_year(i: Input<u8>) -> U8Result<i32> {
@@ -5650,19 +14611,9 @@ fn parse_year(i: Input<u8>) -> U8Result<i32> {
 
 }
 
+// NOTE: day is validated against its month and year in resolve_datetime
 fn parse_day(i: Input<u8>) -> U8Result<u32> {
-
     up_to_two_digits(i)
-    .bind(|i, above:u32| {
-
-        if above <= 0 || above >= 32 {
-            // TODO: right usize?
-            return i.incomplete(1);
-        }
-
-        i.ret(above)
-    })
-
 }
 
 fn parse_months(i: Input<u8>) -> U8Result<u32> {
@@ -5760,6 +14711,106 @@ fn two_digits(i: Input<u8>) -> U8Result<u32> {
     }
 }
 
+#[test]
+fn parse_datetime_calendar_test() {
+
+    // leap years
+
+    match parse_only(|i| parse_datetime(i, false), "feb 29, 2016".as_bytes()) {
+        Ok(Ok(result)) => {
+            assert_eq!(result, NaiveDate::from_ymd(2016, 2, 29).and_hms(0, 0, 0));
+        },
+        _ => {
+            assert!(false);
+        }
+    }
+
+    match parse_only(|i| parse_datetime(i, true), "feb 29, 2000".as_bytes()) {
+        Ok(Ok(result)) => {
+            assert_eq!(result, NaiveDate::from_ymd(2000, 2, 29).and_hms(23, 59, 0));
+        },
+        _ => {
+            assert!(false);
+        }
+    }
+
+    match parse_only(|i| parse_datetime(i, false), "feb 29, 2015".as_bytes()) {
+        Ok(Err(_)) => {
+            assert!(true);
+        },
+        _ => {
+            assert!(false);
+        }
+    }
+
+    match parse_only(|i| parse_datetime(i, false), "feb 29, 1900 5pm".as_bytes()) {
+        Ok(Err(_)) => {
+            assert!(true);
+        },
+        _ => {
+            assert!(false);
+        }
+    }
+
+    // month lengths
+
+    match parse_only(|i| parse_datetime(i, false), "jan 31, 2016 17:30".as_bytes()) {
+        Ok(Ok(result)) => {
+            assert_eq!(result, NaiveDate::from_ymd(2016, 1, 31).and_hms(17, 30, 0));
+        },
+        _ => {
+            assert!(false);
+        }
+    }
+
+    match parse_only(|i| parse_datetime(i, false), "apr 30, 2016".as_bytes()) {
+        Ok(Ok(result)) => {
+            assert_eq!(result, NaiveDate::from_ymd(2016, 4, 30).and_hms(0, 0, 0));
+        },
+        _ => {
+            assert!(false);
+        }
+    }
+
+    for invalid in &["feb 31, 2016", "apr 31, 2016", "jun 31, 2016", "sep 31, 2016",
+        "nov 31, 2016", "jan 40, 2016", "jan 0, 2016", "feb 30, 2016 5pm"] {
+
+        match parse_only(|i| parse_datetime(i, false), invalid.as_bytes()) {
+            Ok(Err(_)) => {
+                assert!(true);
+            },
+            _ => {
+                assert!(false, "expected invalid date: {}", invalid);
+            }
+        }
+    }
+
+    assert_eq!(days_in_month(2016, 2), Some(29));
+    assert_eq!(days_in_month(2015, 2), Some(28));
+    assert_eq!(days_in_month(2016, 12), Some(31));
+    assert_eq!(days_in_month(2016, 13), None);
+
+    // times of day out of range are reported as such, rather than as parse failures
+    let cases: Vec<(&str, &str)> = vec![
+        ("may 4, 2016 25:00", "25:00 is not a valid time."),
+        ("may 4, 2016 10:61", "10:61 is not a valid time."),
+        ("may 4, 2016 10:61 pm", "22:61 is not a valid time."),
+        ("may 4, 2016 2500", "25:00 is not a valid time."),
+        ("may 4, 2016 961", "09:61 is not a valid time.")
+    ];
+
+    for &(input, expected) in &cases {
+        match parse_only(|i| parse_datetime(i, false), input.as_bytes()) {
+            Ok(Err(reason)) => {
+                assert_eq!(reason, expected, "parsing: {}", input);
+            },
+            _ => {
+                assert!(false, "expected invalid time: {}", input);
+            }
+        }
+    }
+}
+
 /* Filestats */
 
 #[derive(Debug)]
@@ -5864,16 +14915,6 @@ impl FileStats {
 
 /* helpers */
 
-fn count_tasks(inbox: &BTreeMap<i64, Vec<u64>>) -> u64 {
-
-    let mut count = 0;
-    for (_, inbox) in inbox.iter() {
-        count += inbox.len() as u64;
-    }
-
-    return count;
-}
-
 fn to_task_id(len: usize) -> i32 {
     len as i32
 }
@@ -5981,7 +15022,6 @@ impl Timerange {
     }
 }
 
-// TODO: refactor
 fn traverse(path: &mut [String], tree: &mut Tree) {
 
     if path.len() <= 0 {
@@ -5992,74 +15032,236 @@ fn traverse(path: &mut [String], tree: &mut Tree) {
         None => unsafe { debug_unreachable!() },
         Some((first, rest)) => {
 
-            if !tree.contains_key(first) {
-                tree.insert(first.clone(), NodeType::Leaf);
-            }
-
             if rest.len() <= 0 {
+                // path is a subpath of any other path that was added with the same prefix
+                tree.insert(first.clone(), NodeType::Leaf);
                 return;
-            } else {
+            }
 
-                let should_replace: bool = match tree.get_mut(first) {
-                    None => unsafe { debug_unreachable!("add_project_filter: NodeType not found") },
-                    Some(node_type) => {
-                        match *node_type {
-                            NodeType::Leaf => {
-                                true
-                            },
-                            NodeType::Node(_) => {
-                                false
-                            }
+            let should_insert: bool = match tree.get_mut(first) {
+                None => true,
+                Some(node_type) => {
+                    match *node_type {
+                        NodeType::Leaf => {
+                            // a subpath of path was added before
+                            false
+                        },
+                        NodeType::Node(ref mut sub_tree) => {
+                            traverse(rest, sub_tree);
+                            false
                         }
                     }
-                };
+                }
+            };
 
-                if should_replace {
-                    let mut new_tree: Tree = HashMap::new();
-                    {
-                        let _new_tree = &mut new_tree;
-                        traverse(rest, _new_tree);
-                    };
+            if should_insert {
+                let mut new_tree: Tree = HashMap::new();
+                traverse(rest, &mut new_tree);
 
-                    tree.insert(first.clone(), NodeType::Node(new_tree));
-                }
+                tree.insert(first.clone(), NodeType::Node(new_tree));
             }
         }
     }
 }
 
 // For any leaf in the tree, test if its path (from root to leaf) is a subpath of path.
+//
+// Paths in the tree may have wildcard segments:
+// - `*` matches exactly one segment
+// - `**` matches any number of segments, including none
 fn path_satisfies_tree(tree: &Tree, path: &[String]) -> bool {
 
-    let mut current = tree;
+    if let Some((first, rest)) = path.split_first() {
 
-    for path_item in path {
+        if let Some(node_type) = tree.get(first) {
+            if node_satisfies_path(node_type, rest) {
+                return true;
+            }
+        }
 
-        if !current.contains_key(path_item) {
-            return false;
+        if let Some(node_type) = tree.get("*") {
+            if node_satisfies_path(node_type, rest) {
+                return true;
+            }
         }
+    }
 
-        match current.get(path_item) {
-            None => {
-                return false;
-            },
-            Some(node_type) => {
-                match *node_type {
-                    NodeType::Leaf => {
-                        // path is super path
+    if let Some(node_type) = tree.get("**") {
+        for index in 0..(path.len() + 1) {
+            if node_satisfies_path(node_type, &path[index..]) {
+                return true;
+            }
+        }
+    }
+
+    // None of the paths from root to leaf are subpaths of path
+
+    return false;
+}
+
+fn node_satisfies_path(node_type: &NodeType, path: &[String]) -> bool {
+    match *node_type {
+        NodeType::Leaf => {
+            // path is super path
+            true
+        },
+        NodeType::Node(ref tree) => {
+            path_satisfies_tree(tree, path)
+        }
+    }
+}
+
+// Test if prefix is a subpath of path. The prefix may have wildcard segments (see path_satisfies_tree).
+fn project_path_has_prefix(path: &[String], prefix: &[String]) -> bool {
+
+    match prefix.split_first() {
+        None => true,
+        Some((first, rest)) => {
+
+            if first == "**" {
+
+                for index in 0..(path.len() + 1) {
+                    if project_path_has_prefix(&path[index..], rest) {
                         return true;
-                    },
-                    NodeType::Node(ref tree) => {
-                        current = tree;
                     }
                 }
+
+                return false;
             }
-        };
+
+            match path.split_first() {
+                None => false,
+                Some((segment, path_rest)) => {
+                    (first == "*" || first == segment) && project_path_has_prefix(path_rest, rest)
+                }
+            }
+        }
     }
+}
 
-    // None of the paths from root to leaf are subpaths of path
+fn is_project_path_wildcard(segment: &str) -> bool {
+    segment == "*" || segment == "**"
+}
 
-    return false;
+#[test]
+fn project_path_wildcard_test() {
+
+    let to_path = |path: &str| -> Vec<String> {
+        path.split('/').map(|segment| segment.to_owned()).collect()
+    };
+
+    let mut tree: Tree = HashMap::new();
+    traverse(&mut to_path("clients/*/frontend"), &mut tree);
+    traverse(&mut to_path("**/docs"), &mut tree);
+
+    assert!(path_satisfies_tree(&tree, &to_path("clients/acme/frontend")));
+    assert!(path_satisfies_tree(&tree, &to_path("clients/acme/frontend/login")));
+    assert!(!path_satisfies_tree(&tree, &to_path("clients/acme/backend")));
+    assert!(!path_satisfies_tree(&tree, &to_path("clients/frontend")));
+    assert!(path_satisfies_tree(&tree, &to_path("docs")));
+    assert!(path_satisfies_tree(&tree, &to_path("work/backend/docs/api")));
+
+    assert!(project_path_has_prefix(&to_path("clients/acme/frontend"), &to_path("clients/*")));
+    assert!(project_path_has_prefix(&to_path("clients/acme/frontend"), &to_path("**/frontend")));
+    assert!(!project_path_has_prefix(&to_path("clients"), &to_path("clients/*")));
+    assert!(!project_path_has_prefix(&to_path("work/frontend"), &to_path("clients/**")));
+}
+
+#[test]
+fn traverse_test() {
+
+    let to_path = |path: &str| -> Vec<String> {
+        path.split('/').map(|segment| segment.to_owned()).collect()
+    };
+
+    // paths that share a prefix
+    let mut tree: Tree = HashMap::new();
+    traverse(&mut to_path("home/finances"), &mut tree);
+    traverse(&mut to_path("home/garden"), &mut tree);
+
+    assert!(path_satisfies_tree(&tree, &to_path("home/finances")));
+    assert!(path_satisfies_tree(&tree, &to_path("home/garden/shed")));
+    assert!(!path_satisfies_tree(&tree, &to_path("home")));
+    assert!(!path_satisfies_tree(&tree, &to_path("home/kitchen")));
+
+    // a subpath added after a path with the same prefix
+    traverse(&mut to_path("home"), &mut tree);
+    assert!(path_satisfies_tree(&tree, &to_path("home/kitchen")));
+
+    // a path added after its subpath
+    let mut tree: Tree = HashMap::new();
+    traverse(&mut to_path("work"), &mut tree);
+    traverse(&mut to_path("work/reports"), &mut tree);
+
+    assert!(path_satisfies_tree(&tree, &to_path("work")));
+    assert!(path_satisfies_tree(&tree, &to_path("work/meetings")));
+}
+
+// Match text against a glob pattern.
+// - `*` matches any sequence of characters except `/`
+// - `?` matches any one character except `/`
+// - `**` matches any sequence of characters, including `/`. `**/` may also match nothing.
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+
+    if pattern.len() <= 0 {
+        return text.len() <= 0;
+    }
+
+    if pattern.starts_with(b"**") {
+
+        let rest = &pattern[2..];
+
+        if rest.starts_with(b"/") && glob_matches(&rest[1..], text) {
+            return true;
+        }
+
+        for index in 0..(text.len() + 1) {
+            if glob_matches(rest, &text[index..]) {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    match pattern[0] {
+        b'*' => {
+
+            for index in 0..(text.len() + 1) {
+
+                if glob_matches(&pattern[1..], &text[index..]) {
+                    return true;
+                }
+
+                if index < text.len() && text[index] == b'/' {
+                    return false;
+                }
+            }
+
+            return false;
+        },
+        b'?' => {
+            return text.len() > 0 && text[0] != b'/' && glob_matches(&pattern[1..], &text[1..]);
+        },
+        character => {
+            return text.len() > 0 && text[0] == character && glob_matches(&pattern[1..], &text[1..]);
+        }
+    }
+}
+
+#[test]
+fn glob_matches_test() {
+
+    assert!(glob_matches(b"archive/*.gtd", b"archive/2016.gtd"));
+    assert!(!glob_matches(b"archive/*.gtd", b"archive/old/2016.gtd"));
+    assert!(!glob_matches(b"archive/*.gtd", b"todo.gtd"));
+    assert!(glob_matches(b"archive/**/*.gtd", b"archive/2016.gtd"));
+    assert!(glob_matches(b"archive/**/*.gtd", b"archive/old/2016.gtd"));
+    assert!(glob_matches(b"**/*.gtd", b"/home/user/todo.gtd"));
+    assert!(glob_matches(b"todo.gt?", b"todo.gtd"));
+    assert!(!glob_matches(b"todo.gt?", b"todo.gt"));
+    assert!(glob_matches(b"*", b"todo.gtd"));
+    assert!(!glob_matches(b"*", b"archive/todo.gtd"));
 }
 
 /* all things line-numbers */
@@ -6067,6 +15269,7 @@ fn path_satisfies_tree(tree: &Tree, path: &[String]) -> bool {
 type LineNumber = u64;
 
 // file_path, line_number, context
+#[derive(Debug)]
 struct LineLocation<T>(String, LineNumber, T);
 
 impl<T> LineLocation<T> {